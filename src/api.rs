@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::content_loader::slowest_renders;
+use crate::is_valid_post_slug;
+use crate::markdown::render_markdown_to_html;
+use crate::models::Post;
+use crate::page_meta::{plain_text, post_excerpt};
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub(crate) struct PostJson {
+    title: String,
+    slug: String,
+    date: String,
+    tags: Vec<String>,
+    html: String,
+    /// Estimated minutes to read the post.
+    reading_time: usize,
+}
+
+/// Just enough of a post for an in-site link preview tooltip.
+#[derive(Serialize)]
+pub(crate) struct PostPreviewJson {
+    title: String,
+    excerpt: String,
+    reading_time: usize,
+}
+
+/// Body text beyond this many characters is dropped from the search
+/// index; the opening of a post is enough to match on and keeps the
+/// download small.
+const SEARCH_BODY_CHARS: usize = 2000;
+
+#[derive(Serialize)]
+pub(crate) struct SearchEntryJson {
+    slug: String,
+    title: String,
+    tags: Vec<String>,
+    body_text: String,
+}
+
+#[derive(Serialize)]
+struct RenderTimingJson<'a> {
+    slug: &'a str,
+    millis: f64,
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    error: String,
+}
+
+fn json_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(ErrorJson {
+            error: message.into(),
+        }),
+    )
+        .into_response()
+}
+
+/// The listed post at `slug`, or the JSON `404` both post endpoints share.
+async fn listed_post(state: &AppState, slug: &str) -> Result<Post, Response> {
+    let not_found = || json_error(StatusCode::NOT_FOUND, format!("post not found: {slug}"));
+    if !is_valid_post_slug(slug) {
+        return Err(not_found());
+    }
+
+    let maybe_post = {
+        let posts = state.posts.read().await;
+        posts.iter().find(|post| post.slug == slug).cloned()
+    };
+    maybe_post
+        .filter(|post| state.is_listed(post))
+        .ok_or_else(not_found)
+}
+
+pub(crate) async fn get_post(
+    Path(slug): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let post = match listed_post(&state, &slug).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    Json(PostJson {
+        html: render_markdown_to_html(&post.markdown_body, &state.render_options),
+        reading_time: post.reading_minutes,
+        title: post.title,
+        slug: post.slug,
+        date: post.date,
+        tags: post.tags,
+    })
+    .into_response()
+}
+
+/// Title, excerpt and reading time without the rendered body, for link
+/// previews. The excerpt is the post's `description`, else the same text
+/// social cards get.
+pub(crate) async fn get_post_preview(
+    Path(slug): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let post = match listed_post(&state, &slug).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    Json(PostPreviewJson {
+        excerpt: post_excerpt(&post),
+        reading_time: post.reading_minutes,
+        title: post.title,
+    })
+    .into_response()
+}
+
+/// One entry per listed post for client-side search libraries such as
+/// lunr or MiniSearch. Built from the loaded posts on each request, so it
+/// follows content reloads.
+pub(crate) async fn get_search_index(State(state): State<Arc<AppState>>) -> Response {
+    let posts = state.posts.read().await;
+    let entries: Vec<SearchEntryJson> = posts
+        .iter()
+        .filter(|post| state.is_listed(post))
+        .map(|post| SearchEntryJson {
+            slug: post.slug.clone(),
+            title: post.title.clone(),
+            tags: post.tags.clone(),
+            body_text: plain_text(&post.markdown_body)
+                .chars()
+                .take(SEARCH_BODY_CHARS)
+                .collect(),
+        })
+        .collect();
+    Json(entries).into_response()
+}
+
+/// Post render times measured at load, slowest first. Development only.
+pub(crate) async fn get_render_timings(State(state): State<Arc<AppState>>) -> Response {
+    if !state.is_development() {
+        return json_error(StatusCode::NOT_FOUND, "not found");
+    }
+
+    let timings = state.render_timings.read().await;
+    let body: Vec<RenderTimingJson> = slowest_renders(&timings)
+        .into_iter()
+        .map(|(slug, elapsed)| RenderTimingJson {
+            slug,
+            millis: elapsed.as_secs_f64() * 1000.0,
+        })
+        .collect();
+    Json(body).into_response()
+}
+
+/// Renders a raw markdown request body to HTML, for iterating on the
+/// renderer without creating a post. Only routed in development.
+pub(crate) async fn render_preview(
+    State(state): State<Arc<AppState>>,
+    markdown: String,
+) -> Response {
+    Html(render_markdown_to_html(&markdown, &state.render_options)).into_response()
+}
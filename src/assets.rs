@@ -0,0 +1,250 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use tokio::fs;
+
+use crate::content_loader::ContentRoots;
+
+/// Content types for static files that `ServeDir` guesses wrong or not at
+/// all, keyed by lowercase extension.
+const DEFAULT_MIME_OVERRIDES: &[(&str, &str)] = &[
+    ("webmanifest", "application/manifest+json"),
+    ("wasm", "application/wasm"),
+];
+
+/// `DEFAULT_MIME_OVERRIDES` plus `STATIC_MIME_TYPES`.
+pub(crate) fn mime_overrides_from_env() -> HashMap<String, String> {
+    parse_mime_overrides(std::env::var("STATIC_MIME_TYPES").ok().as_deref())
+}
+
+/// Comma-separated `extension=type` pairs, e.g. `glb=model/gltf-binary`,
+/// layered over the defaults. Malformed entries are ignored.
+fn parse_mime_overrides(raw: Option<&str>) -> HashMap<String, String> {
+    let mut overrides: HashMap<String, String> = DEFAULT_MIME_OVERRIDES
+        .iter()
+        .map(|(extension, mime)| (extension.to_string(), mime.to_string()))
+        .collect();
+    for entry in raw.unwrap_or_default().split(',') {
+        let Some((extension, mime)) = entry.split_once('=') else {
+            continue;
+        };
+        let extension = extension
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase();
+        let mime = mime.trim();
+        if !extension.is_empty() && !mime.is_empty() {
+            overrides.insert(extension, mime.to_string());
+        }
+    }
+    overrides
+}
+
+/// Version string appended as `?v=` to static links in templates.
+/// `ASSET_VERSION` pins it (e.g. to a commit SHA); otherwise it is a
+/// checksum of the served static files, so it stays the same for a
+/// deploy and changes whenever an asset does.
+pub(crate) async fn asset_version(roots: &ContentRoots) -> Result<String, std::io::Error> {
+    if let Some(version) = std::env::var("ASSET_VERSION")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    {
+        return Ok(version);
+    }
+
+    let (static_dir, theme_static_dir) = roots.static_dirs();
+    let mut hasher = DefaultHasher::new();
+    for dir in std::iter::once(static_dir).chain(theme_static_dir) {
+        hash_dir(&dir, &mut hasher).await?;
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+async fn hash_dir(root: &Path, hasher: &mut DefaultHasher) -> Result<(), std::io::Error> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    // Directory iteration order is not stable across filesystems.
+    files.sort();
+    for path in files {
+        relative_to(&path, root).hash(hasher);
+        fs::read(&path).await?.hash(hasher);
+    }
+    Ok(())
+}
+
+fn relative_to(path: &Path, root: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+/// Icon files looked up in the static directories, in the order their
+/// `<link>` tags are emitted: (file name, public href, extra attributes).
+const ICON_FILES: &[(&str, &str, &str)] = &[
+    ("favicon.ico", "/favicon.ico", r#"rel="icon" sizes="any""#),
+    (
+        "favicon.svg",
+        "/static/favicon.svg",
+        r#"rel="icon" type="image/svg+xml""#,
+    ),
+    (
+        "favicon-32x32.png",
+        "/static/favicon-32x32.png",
+        r#"rel="icon" type="image/png" sizes="32x32""#,
+    ),
+    (
+        "favicon-16x16.png",
+        "/static/favicon-16x16.png",
+        r#"rel="icon" type="image/png" sizes="16x16""#,
+    ),
+    (
+        "apple-touch-icon.png",
+        "/static/apple-touch-icon.png",
+        r#"rel="apple-touch-icon" sizes="180x180""#,
+    ),
+];
+
+/// `<link>` tags for the `{{ icon_links }}` placeholder, one per icon file
+/// present in the static directories; missing sizes are left out.
+pub(crate) async fn render_icon_links(roots: &ContentRoots) -> Result<String, std::io::Error> {
+    let (static_dir, theme_static_dir) = roots.static_dirs();
+    let mut links = Vec::new();
+    for (file, href, attributes) in ICON_FILES {
+        let mut present = false;
+        for dir in std::iter::once(&static_dir).chain(theme_static_dir.as_ref()) {
+            if fs::try_exists(dir.join(file)).await? {
+                present = true;
+                break;
+            }
+        }
+        if present {
+            links.push(format!("<link {attributes} href=\"{href}\" />"));
+        }
+    }
+    Ok(links.join("\n    "))
+}
+
+/// Appends `?v=<version>` to `href`/`src` attributes pointing under
+/// `/static/`. Links that already carry a query string are left alone.
+pub(crate) fn version_static_links(template: &str, version: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(index) = next_static_attribute(rest) {
+        let (before, attribute) = rest.split_at(index);
+        out.push_str(before);
+        let value_len = attribute.find('"').unwrap_or(attribute.len());
+        let value = &attribute[..value_len];
+        out.push_str(value);
+        if !value.contains('?') {
+            out.push_str("?v=");
+            out.push_str(version);
+        }
+        rest = &attribute[value_len..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Byte offset just past the opening quote of the next `href="/static/`
+/// or `src="/static/` attribute.
+fn next_static_attribute(input: &str) -> Option<usize> {
+    ["href=\"/static/", "src=\"/static/"]
+        .iter()
+        .filter_map(|needle| {
+            input
+                .find(needle)
+                .map(|index| index + needle.find('/').unwrap_or(0))
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{asset_version, parse_mime_overrides, render_icon_links, version_static_links};
+    use crate::test_support::{TestContentDir, TestEnvGuard};
+
+    #[test]
+    fn configured_mime_overrides_extend_and_replace_defaults() {
+        let overrides =
+            parse_mime_overrides(Some(" .GLB = model/gltf-binary ,wasm=text/plain,bad"));
+        assert_eq!(overrides["glb"], "model/gltf-binary");
+        assert_eq!(overrides["wasm"], "text/plain");
+        assert_eq!(overrides["webmanifest"], "application/manifest+json");
+        assert!(!overrides.contains_key("bad"));
+    }
+
+    #[test]
+    fn appends_version_to_static_links() {
+        let template =
+            r#"<link href="/static/tailwind.css" /><img src="/static/a.png" /><a href="/posts/x">"#;
+        let versioned = version_static_links(template, "abc");
+        assert_eq!(
+            versioned,
+            r#"<link href="/static/tailwind.css?v=abc" /><img src="/static/a.png?v=abc" /><a href="/posts/x">"#
+        );
+    }
+
+    #[test]
+    fn leaves_links_with_existing_query_alone() {
+        let template = r#"<link href="/static/favicon-32x32.png?v=1" />"#;
+        assert_eq!(version_static_links(template, "abc"), template);
+    }
+
+    #[tokio::test]
+    async fn asset_version_changes_when_assets_change() {
+        let _guard = TestEnvGuard::set([("ASSET_VERSION", None)]);
+        let content = TestContentDir::new();
+        content.write("static/site.css", "body { color: red; }");
+
+        let first = asset_version(&content.roots()).await.expect("version");
+        let again = asset_version(&content.roots()).await.expect("version");
+        content.write("static/site.css", "body { color: blue; }");
+        let changed = asset_version(&content.roots()).await.expect("version");
+
+        assert_eq!(first, again);
+        assert_ne!(first, changed);
+    }
+
+    #[tokio::test]
+    async fn asset_version_env_pins_the_version() {
+        let _guard = TestEnvGuard::set([("ASSET_VERSION", Some("deadbeef"))]);
+        let content = TestContentDir::new();
+        let version = asset_version(&content.roots()).await.expect("version");
+        assert_eq!(version, "deadbeef");
+    }
+
+    #[tokio::test]
+    async fn icon_links_cover_only_files_that_exist() {
+        let content = TestContentDir::new();
+        content.write("static/favicon.ico", "ico");
+        content.write("static/apple-touch-icon.png", "png");
+
+        let links = render_icon_links(&content.roots()).await.expect("icons");
+
+        assert!(links.contains(r#"<link rel="icon" sizes="any" href="/favicon.ico" />"#));
+        assert!(links.contains(
+            r#"<link rel="apple-touch-icon" sizes="180x180" href="/static/apple-touch-icon.png" />"#
+        ));
+        assert!(!links.contains("favicon-32x32.png"));
+        assert!(!links.contains("favicon.svg"));
+    }
+}
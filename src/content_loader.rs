@@ -1,50 +1,780 @@
+use std::{
+    collections::{HashMap, HashSet},
+    panic::AssertUnwindSafe,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
 use gray_matter::{engine::YAML, Matter};
 use tokio::fs;
-use tracing::error;
+use tracing::{error, info, warn};
+
+use crate::assets::{asset_version, render_icon_links, version_static_links};
+use crate::is_valid_post_slug;
+use crate::markdown::{math_errors, render_markdown_to_html, RenderOptions};
+use crate::models::{FrontMatter, HomeMode, NavConfig, Post, SiteConfig, TagOrder};
+use crate::nav::{
+    group_by_category, group_by_tag, render_category_nav, render_nav_links, render_site_nav,
+    warn_on_dangling_post_links, warn_on_unknown_nav_routes,
+};
+use crate::page_meta::{reading_time_minutes, render_theme_color_meta, slugify};
+use crate::post_url::PostUrlPattern;
+
+const DEFAULT_CONTENT_DIR: &str = "content";
+
+/// Where content files are looked up. Each file is taken from
+/// `content_dir` when present there, otherwise from `theme_dir`, so a
+/// site can ship only the files it overrides on top of a shared theme.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentRoots {
+    pub content_dir: PathBuf,
+    pub theme_dir: Option<PathBuf>,
+    /// Replaces the layered `static/` directories when set.
+    pub static_dir: Option<PathBuf>,
+}
+
+impl ContentRoots {
+    /// Reads `CONTENT_DIR` (default `content`) and the optional `THEME_DIR`
+    /// and `STATIC_DIR`.
+    pub fn from_env() -> Self {
+        let non_empty = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from)
+        };
+        Self {
+            content_dir: non_empty("CONTENT_DIR")
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_CONTENT_DIR)),
+            theme_dir: non_empty("THEME_DIR"),
+            static_dir: non_empty("STATIC_DIR"),
+        }
+    }
+
+    /// The directory served under `/static`, plus the theme's static
+    /// directory to fall back to.
+    pub fn static_dirs(&self) -> (PathBuf, Option<PathBuf>) {
+        match &self.static_dir {
+            Some(dir) => (dir.clone(), None),
+            None => (
+                self.content_dir.join("static"),
+                self.theme_dir.as_ref().map(|dir| dir.join("static")),
+            ),
+        }
+    }
+
+    /// Path of `relative` in the first root that has it. Falls back to the
+    /// content directory so "not found" errors name the override location.
+    pub fn resolve(&self, relative: &str) -> PathBuf {
+        let primary = self.content_dir.join(relative);
+        match &self.theme_dir {
+            Some(theme_dir) if !primary.exists() => {
+                let themed = theme_dir.join(relative);
+                if themed.exists() {
+                    themed
+                } else {
+                    primary
+                }
+            }
+            _ => primary,
+        }
+    }
+}
+
+pub struct LoadedContent {
+    pub site_config: SiteConfig,
+    pub banner_html: String,
+    pub layout_html: String,
+    /// The layout for `/posts/{slug}/print`: `print.html` when the content
+    /// has one, else `layout.html` with its navigation placeholders empty.
+    pub print_layout_html: String,
+    pub home_post: Post,
+    pub not_found_markdown: String,
+    pub posts: Vec<Post>,
+    /// Lowercased slug to the post's own slug, for case-insensitive lookups.
+    pub slug_index: HashMap<String, String>,
+    /// Category slug to its posts, so `/categories/{category}` skips
+    /// regrouping every post per request.
+    pub category_index: HashMap<String, GroupEntry>,
+    /// Tag slug to its posts, for `/tags` and `/tags/{tag}`.
+    pub tag_index: HashMap<String, GroupEntry>,
+    /// How long each post took to render, keyed by slug. Empty unless
+    /// `LoadOptions::render_timing` is set.
+    pub render_timings: HashMap<String, Duration>,
+    /// Slugs listed in `gone.txt`: removed on purpose, answered with `410`.
+    pub gone_slugs: HashSet<String>,
+    /// A `MAINTENANCE` file exists in the content directory (themes cannot
+    /// set it).
+    pub maintenance: bool,
+}
+
+/// Layout placeholders filled with navigation, which the print view
+/// leaves empty.
+const NAV_PLACEHOLDERS: &[&str] = &["{{ nav }}", "{{ sitenav }}", "{{ categories }}"];
+
+const DEFAULT_MAX_POST_BYTES: u64 = 1024 * 1024;
+
+/// Environment-dependent choices about what gets loaded.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// Load posts from `content/drafts/` as well. Only development sets this.
+    pub include_drafts: bool,
+    /// Post files larger than this are skipped with a warning.
+    pub max_post_bytes: Option<u64>,
+    /// Render every post once with these options and record how long each
+    /// took, to find expensive posts (usually heavy math).
+    pub render_timing: Option<RenderOptions>,
+    /// Threads sharing the `render_timing` renders; 0 or 1 renders in turn.
+    pub render_threads: usize,
+    /// Typeset every math expression once and record KaTeX errors as post
+    /// problems. Only development sets this.
+    pub check_math: bool,
+    /// Fail the load on any content warning (dangling nav link, post
+    /// problem, unreplaced placeholder) instead of serving around it.
+    pub strict: bool,
+    /// Rewrite or skip posts whose slug the router would refuse.
+    pub invalid_slugs: InvalidSlugs,
+}
+
+/// What loading does with a front-matter slug that is not a valid post
+/// URL segment (uppercase, spaces, slashes, ...).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidSlugs {
+    /// Serve the post under its slugified form.
+    #[default]
+    Rewrite,
+    /// Skip the post.
+    Reject,
+}
+
+impl InvalidSlugs {
+    /// Reads `INVALID_SLUGS` (`rewrite` or `reject`), falling back to
+    /// `rewrite` with a warning on anything else.
+    pub fn from_env() -> Self {
+        let Ok(raw) = std::env::var("INVALID_SLUGS") else {
+            return Self::default();
+        };
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "rewrite" => Self::Rewrite,
+            "reject" => Self::Reject,
+            _ => {
+                warn!(value = %raw, "ignoring unknown INVALID_SLUGS");
+                Self::default()
+            }
+        }
+    }
+}
 
-use crate::models::{FrontMatter, Post, SiteConfig};
+/// Placeholders `render_with_layout` fills on every request, in the order
+/// it fills them; any other `{{ name }}` still in a layout after loading
+/// would reach the page as is.
+pub(crate) const PAGE_PLACEHOLDERS: &[&str] = &[
+    "banner",
+    "posts",
+    "page_title",
+    "page_description",
+    "page_url",
+    "page_canonical_url",
+    "page_image",
+    "page_author",
+    "page_published_time_meta",
+    "page_role_meta",
+    "page_keywords_meta",
+    "page_og_type",
+    "body_class",
+    "toc",
+    "comments",
+    "reading_time",
+    "content",
+    "math_css",
+];
+
+/// Reads `MAX_POST_BYTES`, defaulting to 1 MiB; `0` disables the guard.
+pub fn max_post_bytes_from_env() -> Option<u64> {
+    let limit = std::env::var("MAX_POST_BYTES")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_POST_BYTES);
+    (limit > 0).then_some(limit)
+}
 
-const CONTENT_DIR: &str = "content";
+/// Reads `STRICT` (`1`, `true`, `yes` or `on`).
+pub fn strict_from_env() -> bool {
+    std::env::var("STRICT")
+        .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
 
-pub async fn load_content(
-) -> Result<(SiteConfig, String, String, Post, String, Vec<Post>), std::io::Error> {
-    let site_config = load_site_config().await?;
-    let banner_template = fs::read_to_string(format!("{}/banner.html", CONTENT_DIR)).await?;
-    let layout_template = fs::read_to_string(format!("{}/layout.html", CONTENT_DIR)).await?;
-    let banner_html = apply_site_config_template(&banner_template, &site_config);
-    let layout_html = apply_site_config_template(&layout_template, &site_config);
-    let not_found_markdown = fs::read_to_string(format!("{}/404.md", CONTENT_DIR)).await?;
+/// Reads `RENDER_THREADS`, defaulting to the available parallelism.
+pub fn render_threads_from_env() -> usize {
+    std::env::var("RENDER_THREADS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|threads| *threads > 0)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from))
+}
 
-    let home_md_content = fs::read_to_string(format!("{}/home.md", CONTENT_DIR)).await?;
-    let home_post = parse_markdown_post(&home_md_content, true);
+pub async fn load_content(options: LoadOptions) -> Result<LoadedContent, std::io::Error> {
+    load_content_from(&ContentRoots::from_env(), options).await
+}
+
+pub async fn load_content_from(
+    roots: &ContentRoots,
+    options: LoadOptions,
+) -> Result<LoadedContent, std::io::Error> {
+    let site_config = load_site_config(roots).await?;
+    let banner_template = fs::read_to_string(roots.resolve("banner.html")).await?;
+    let icon_links = render_icon_links(roots).await?;
+    let layout_template = fs::read_to_string(roots.resolve("layout.html"))
+        .await?
+        .replace("{{ icon_links }}", &icon_links);
+    let asset_version = asset_version(roots).await?;
+    let print_template = match fs::read_to_string(roots.resolve("print.html")).await {
+        Ok(template) => Some(version_static_links(
+            &apply_site_config_template(&template, &site_config)
+                .replace("{{ icon_links }}", &icon_links),
+            &asset_version,
+        )),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => return Err(error),
+    };
+    let banner_html = version_static_links(
+        &apply_site_config_template(&banner_template, &site_config),
+        &asset_version,
+    );
+    let not_found_markdown = fs::read_to_string(roots.resolve("404.md")).await?;
+
+    let home_path = roots.resolve("home.md");
+    let home_md_content = fs::read_to_string(&home_path).await?;
+    let mut home_post = parse_markdown_post(&home_md_content, &home_path, true);
+    home_post.content_path = PathBuf::from("home.md");
+    append_includes(roots, &mut home_post, options.max_post_bytes).await?;
 
     // 3. Load posts metadata
-    let mut posts: Vec<Post> = Vec::new();
-    let mut entries = fs::read_dir(format!("{}/posts", CONTENT_DIR)).await?;
+    let mut posts = load_posts_dir(&roots.resolve("posts"), Path::new("posts"), &options).await?;
+    if options.include_drafts {
+        // Drafts live apart from published posts and are optional.
+        match load_posts_dir(&roots.resolve("drafts"), Path::new("drafts"), &options).await {
+            Ok(drafts) => posts.extend(drafts),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    } else {
+        // `draft: true` keeps a file among the published posts unpublished.
+        posts.retain(|post| !post.draft);
+    }
 
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
-        if path.extension().is_some_and(|ext| ext == "md") {
-            let file_content = fs::read_to_string(&path).await?;
-            posts.push(parse_markdown_post(&file_content, false));
+    let mut posts = normalize_slugs(posts, options.invalid_slugs);
+    for post in &mut posts {
+        append_includes(roots, post, options.max_post_bytes).await?;
+    }
+    for post in std::iter::once(&mut home_post).chain(&mut posts) {
+        post.reading_minutes = reading_time_minutes(&post.markdown_body);
+    }
+    if options.check_math || options.strict {
+        for post in std::iter::once(&mut home_post).chain(&mut posts) {
+            let errors = math_errors(&post.markdown_body);
+            post.problems.extend(errors);
         }
     }
 
-    // Keep listing stable across environments and fs implementations.
-    posts.sort_by(|a, b| b.slug.cmp(&a.slug));
+    // Keep listing stable across environments and fs implementations:
+    // newest first, same-day posts by slug, undated posts last.
+    // Ties on slug go to the earlier file name, so the file a duplicate
+    // keeps does not depend on directory listing order.
+    posts.sort_by(|a, b| {
+        b.slug
+            .cmp(&a.slug)
+            .then_with(|| a.source_path.cmp(&b.source_path))
+    });
+    let mut posts = dedupe_post_slugs(posts);
+    posts.sort_by_key(|post| std::cmp::Reverse(post.published_on()));
+    let post_order = read_slug_list(&roots.resolve("posts/order.txt")).await?;
+    let mut posts = apply_post_order(posts, &post_order);
 
-    Ok((
+    let nav_config = load_nav_config(roots).await?;
+    let mut warnings =
+        warn_on_unknown_nav_routes(&nav_config.links, &posts, &site_config.post_urls);
+    warnings.extend(warn_on_dangling_post_links(
+        std::iter::once(&home_post).chain(&posts),
+        &posts,
+    ));
+    let layout_base = version_static_links(
+        &apply_site_config_template(&layout_template, &site_config),
+        &asset_version,
+    );
+    let print_layout_html = print_template.clone().unwrap_or_else(|| {
+        NAV_PLACEHOLDERS
+            .iter()
+            .fold(layout_base.clone(), |html, placeholder| {
+                html.replace(placeholder, "")
+            })
+    });
+    let layout_html = layout_base
+        .replace("{{ nav }}", &render_nav_links(&nav_config.links))
+        .replace(
+            "{{ sitenav }}",
+            &render_site_nav(&posts, &site_config.post_urls, site_config.tag_order),
+        )
+        .replace(
+            "{{ categories }}",
+            &render_category_nav(&posts, site_config.tag_order),
+        );
+
+    if options.strict {
+        for post in std::iter::once(&home_post).chain(&posts) {
+            warnings.extend(
+                post.problems
+                    .iter()
+                    .map(|problem| format!("{}: {problem}", post.source_path.display())),
+            );
+        }
+        for (name, template) in [
+            ("layout.html", Some(&layout_html)),
+            ("print.html", print_template.as_ref()),
+        ] {
+            warnings.extend(
+                template
+                    .into_iter()
+                    .flat_map(|template| unknown_placeholders(template))
+                    .map(|placeholder| format!("{name}: unreplaced placeholder {placeholder}")),
+            );
+        }
+        if !warnings.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("strict mode: {}", warnings.join("; ")),
+            ));
+        }
+    }
+
+    let slug_index = build_slug_index(&posts);
+    let category_index = build_category_index(&posts);
+    let tag_index = build_tag_index(&posts);
+    let gone_slugs = load_gone_slugs(roots).await?;
+    let render_timings = match &options.render_timing {
+        Some(render_options) => {
+            let renders = render_posts(&posts, render_options, options.render_threads);
+            // Serve the fallback rather than panic again on every request.
+            for (post, (html, _)) in posts.iter_mut().zip(&renders) {
+                if html.is_none() {
+                    post.markdown_body = site_config.render_failure_text.clone();
+                    post.reading_minutes = reading_time_minutes(&post.markdown_body);
+                }
+            }
+            time_post_renders(&posts, &renders)
+        }
+        None => HashMap::new(),
+    };
+
+    Ok(LoadedContent {
         site_config,
         banner_html,
         layout_html,
+        print_layout_html,
         home_post,
         not_found_markdown,
         posts,
-    ))
+        slug_index,
+        category_index,
+        tag_index,
+        render_timings,
+        gone_slugs,
+        maintenance: roots.content_dir.join("MAINTENANCE").exists(),
+    })
+}
+
+/// Every `{{ name }}` in `template` that no request fills.
+fn unknown_placeholders(template: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let candidate = &rest[start..];
+        let Some(end) = candidate.find("}}") else {
+            break;
+        };
+        let name = candidate[2..end].trim();
+        if !PAGE_PLACEHOLDERS.contains(&name) {
+            unknown.push(format!("{{{{ {name} }}}}"));
+        }
+        rest = &candidate[end + 2..];
+    }
+    unknown
+}
+
+async fn load_gone_slugs(roots: &ContentRoots) -> Result<HashSet<String>, std::io::Error> {
+    Ok(read_slug_list(&roots.resolve("gone.txt"))
+        .await?
+        .into_iter()
+        .collect())
+}
+
+/// One slug per line; blank lines and `#` comments are ignored. The file
+/// is optional.
+async fn read_slug_list(path: &Path) -> Result<Vec<String>, std::io::Error> {
+    let raw = match fs::read_to_string(path).await {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    Ok(raw
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Lists slugs named in `posts/order.txt` first, in file order, then the
+/// rest in their existing order. Without a manifest nothing moves.
+fn apply_post_order(mut posts: Vec<Post>, order: &[String]) -> Vec<Post> {
+    if order.is_empty() {
+        return posts;
+    }
+    let mut positions = HashMap::new();
+    for (index, slug) in order.iter().enumerate() {
+        if !posts.iter().any(|post| &post.slug == slug) {
+            warn!(slug = %slug, "posts/order.txt names an unknown post");
+            continue;
+        }
+        positions.entry(slug.as_str()).or_insert(index);
+    }
+    posts.sort_by(|a, b| {
+        match (
+            positions.get(a.slug.as_str()),
+            positions.get(b.slug.as_str()),
+        ) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+    });
+    posts
+}
+
+const SLOWEST_RENDERS_LOGGED: usize = 5;
+
+fn time_post_renders(
+    posts: &[Post],
+    renders: &[(Option<String>, Duration)],
+) -> HashMap<String, Duration> {
+    let timings: HashMap<String, Duration> = posts
+        .iter()
+        .zip(renders)
+        .map(|(post, (_, elapsed))| (post.slug.clone(), *elapsed))
+        .collect();
+
+    for (slug, elapsed) in slowest_renders(&timings)
+        .into_iter()
+        .take(SLOWEST_RENDERS_LOGGED)
+    {
+        info!(slug = %slug, elapsed_ms = elapsed.as_secs_f64() * 1000.0, "post render time");
+    }
+    timings
+}
+
+fn render_posts(
+    posts: &[Post],
+    options: &RenderOptions,
+    threads: usize,
+) -> Vec<(Option<String>, Duration)> {
+    render_posts_with(posts, threads, |post| {
+        render_markdown_to_html(&post.markdown_body, options)
+    })
+}
+
+/// Renders every post, split into contiguous runs over up to `threads`
+/// threads. KaTeX keeps one engine per thread, so math renders in parallel
+/// too. Results line up with `posts` whatever the thread count; a post
+/// whose render panics is logged and gets `None`.
+fn render_posts_with<F>(
+    posts: &[Post],
+    threads: usize,
+    render: F,
+) -> Vec<(Option<String>, Duration)>
+where
+    F: Fn(&Post) -> String + Sync,
+{
+    let render = |post: &Post| {
+        let started = Instant::now();
+        let html = std::panic::catch_unwind(AssertUnwindSafe(|| render(post)))
+            .map_err(|panic| {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("non-string panic payload");
+                error!(slug = %post.slug, message, "post render panicked");
+            })
+            .ok();
+        (html, started.elapsed())
+    };
+    let render = &render;
+    let threads = threads.clamp(1, posts.len().max(1));
+    if threads == 1 {
+        return posts.iter().map(render).collect();
+    }
+
+    let run = posts.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = posts
+            .chunks(run)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(render).collect::<Vec<_>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+/// Render timings ordered slowest first.
+pub(crate) fn slowest_renders(timings: &HashMap<String, Duration>) -> Vec<(&str, Duration)> {
+    let mut ordered: Vec<_> = timings
+        .iter()
+        .map(|(slug, elapsed)| (slug.as_str(), *elapsed))
+        .collect();
+    ordered.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ordered
+}
+
+/// Posts whose slugs differ only by case keep the first one, matching how
+/// exact duplicates are resolved.
+pub(crate) fn build_slug_index(posts: &[Post]) -> HashMap<String, String> {
+    let mut index = HashMap::with_capacity(posts.len());
+    for post in posts {
+        index
+            .entry(post.slug.to_lowercase())
+            .or_insert_with(|| post.slug.clone());
+    }
+    index
 }
 
-async fn load_site_config() -> Result<SiteConfig, std::io::Error> {
-    let raw = fs::read_to_string(format!("{}/site.toml", CONTENT_DIR)).await?;
+/// One category's or tag's name, spelled as by its first post, and the
+/// positions in `posts` of its posts in listing order. Expired posts and
+/// drafts stay in; handlers filter them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GroupEntry {
+    pub name: String,
+    pub posts: Vec<usize>,
+}
+
+pub(crate) fn build_category_index(posts: &[Post]) -> HashMap<String, GroupEntry> {
+    index_groups(posts, group_by_category(posts, TagOrder::default()))
+}
+
+pub(crate) fn build_tag_index(posts: &[Post]) -> HashMap<String, GroupEntry> {
+    index_groups(posts, group_by_tag(posts, TagOrder::default()))
+}
+
+/// Keys groups by slug and swaps their posts for positions in `posts`.
+/// Slugs are unique by now, so they identify each post.
+fn index_groups(posts: &[Post], groups: Vec<(&str, Vec<&Post>)>) -> HashMap<String, GroupEntry> {
+    let positions: HashMap<&str, usize> = posts
+        .iter()
+        .enumerate()
+        .map(|(position, post)| (post.slug.as_str(), position))
+        .collect();
+    groups
+        .into_iter()
+        .map(|(name, members)| {
+            let entry = GroupEntry {
+                name: name.to_string(),
+                posts: members
+                    .iter()
+                    .filter_map(|post| positions.get(post.slug.as_str()).copied())
+                    .collect(),
+            };
+            (slugify(name), entry)
+        })
+        .collect()
+}
+
+/// Loads every `.md` file under `dir`, subfolders included. `relative` is
+/// `dir` within the content root and prefixes each post's `content_path`.
+async fn load_posts_dir(
+    dir: &Path,
+    relative: &Path,
+    options: &LoadOptions,
+) -> Result<Vec<Post>, std::io::Error> {
+    let mut posts = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), relative.to_path_buf())];
+
+    while let Some((dir, relative)) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let content_path = relative.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                pending.push((path, content_path));
+                continue;
+            }
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            // A runaway file would otherwise be rendered (KaTeX and all) on
+            // every request for it.
+            let metadata = entry.metadata().await?;
+            let size = metadata.len();
+            if let Some(limit) = options.max_post_bytes.filter(|limit| size > *limit) {
+                warn!(
+                    path = %path.display(),
+                    size,
+                    limit,
+                    "skipping post larger than MAX_POST_BYTES"
+                );
+                continue;
+            }
+            let file_content = fs::read_to_string(&path).await?;
+            let mut post = parse_markdown_post(&file_content, &path, false);
+            post.content_path = content_path;
+            post.modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+            if post.updated.is_none() {
+                post.updated = post
+                    .modified
+                    .map(|modified| modified.format("%Y-%m-%d").to_string());
+            }
+            posts.push(post);
+        }
+    }
+
+    Ok(posts)
+}
+
+/// Appends each of the post's `includes`, read from `fragments/`, to its
+/// body. Names that would reach outside that directory, missing fragments,
+/// and fragments that would take the body past `max_post_bytes` are
+/// skipped with a warning.
+async fn append_includes(
+    roots: &ContentRoots,
+    post: &mut Post,
+    max_post_bytes: Option<u64>,
+) -> Result<(), std::io::Error> {
+    for name in post.includes.clone() {
+        let Some(relative) = fragment_path(&name) else {
+            warn!(slug = %post.slug, include = %name, "ignoring include outside fragments/");
+            continue;
+        };
+        let path = roots.resolve(&relative);
+        // Checked before reading so a runaway fragment is never loaded.
+        let size = match fs::metadata(&path).await {
+            Ok(metadata) => metadata.len(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                warn!(slug = %post.slug, include = %name, "missing include");
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        let combined = post.markdown_body.len() as u64 + 2 + size;
+        if let Some(limit) = max_post_bytes.filter(|limit| combined > *limit) {
+            warn!(
+                slug = %post.slug,
+                include = %name,
+                size,
+                limit,
+                "skipping include that takes the post past MAX_POST_BYTES"
+            );
+            continue;
+        }
+        let fragment = fs::read_to_string(&path).await?;
+        post.markdown_body.push_str("\n\n");
+        post.markdown_body.push_str(&fragment.replace("\r\n", "\n"));
+    }
+    Ok(())
+}
+
+/// `fragments/<name>` when `name` is a plain relative path: no root, no
+/// `..` and no `.` segments.
+fn fragment_path(name: &str) -> Option<String> {
+    let plain = !name.trim().is_empty()
+        && Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    plain.then(|| format!("fragments/{name}"))
+}
+
+/// Slugs are used verbatim in routes and links, so one the router would
+/// refuse is rewritten to its slugified form or the post dropped.
+fn normalize_slugs(posts: Vec<Post>, policy: InvalidSlugs) -> Vec<Post> {
+    posts
+        .into_iter()
+        .filter_map(|mut post| {
+            if is_valid_post_slug(&post.slug) {
+                return Some(post);
+            }
+            let normalized = slugify(&post.slug);
+            if policy == InvalidSlugs::Reject || !is_valid_post_slug(&normalized) {
+                warn!(
+                    slug = %post.slug,
+                    path = %post.source_path.display(),
+                    "skipping post with an invalid slug"
+                );
+                return None;
+            }
+            warn!(
+                slug = %post.slug,
+                normalized = %normalized,
+                path = %post.source_path.display(),
+                "rewriting invalid post slug"
+            );
+            post.slug = normalized;
+            Some(post)
+        })
+        .collect()
+}
+
+/// Routes are keyed by the front-matter slug, not the file name, so two
+/// files may claim the same URL. Keep the first file per slug and log the
+/// rest instead of serving whichever one happens to sort first later.
+fn dedupe_post_slugs(posts: Vec<Post>) -> Vec<Post> {
+    let mut slug_paths: HashMap<String, PathBuf> = HashMap::new();
+    let mut unique = Vec::with_capacity(posts.len());
+
+    for post in posts {
+        if let Some(existing) = slug_paths.get(&post.slug) {
+            warn!(
+                slug = %post.slug,
+                kept = %existing.display(),
+                ignored = %post.source_path.display(),
+                "duplicate post slug"
+            );
+            continue;
+        }
+        slug_paths.insert(post.slug.clone(), post.source_path.clone());
+        unique.push(post);
+    }
+
+    unique
+}
+
+async fn load_site_config(roots: &ContentRoots) -> Result<SiteConfig, std::io::Error> {
+    let raw = fs::read_to_string(roots.resolve("site.toml")).await?;
+    let mut site_config: SiteConfig = toml::from_str(&raw)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+    site_config.post_urls = PostUrlPattern::from_env();
+    site_config.home_mode = HomeMode::from_env();
+    site_config.edit_base_url = std::env::var("EDIT_BASE_URL")
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty());
+    Ok(site_config)
+}
+
+// The nav file is optional so content overlays that predate it keep loading.
+async fn load_nav_config(roots: &ContentRoots) -> Result<NavConfig, std::io::Error> {
+    let raw = match fs::read_to_string(roots.resolve("nav.toml")).await {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(NavConfig::default())
+        }
+        Err(error) => return Err(error),
+    };
     toml::from_str(&raw)
         .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
 }
@@ -55,15 +785,29 @@ fn apply_site_config_template(template: &str, site_config: &SiteConfig) -> Strin
         .replace("{{ site_author }}", &site_config.author)
         .replace("{{ site_description }}", &site_config.description)
         .replace("{{ site_og_name }}", &site_config.og_site_name)
+        .replace(
+            "{{ theme_color_meta }}",
+            &render_theme_color_meta(site_config.theme_color.as_ref()),
+        )
 }
 
-fn parse_markdown_post(file_content: &str, is_home: bool) -> Post {
+fn parse_markdown_post(file_content: &str, source_path: &Path, is_home: bool) -> Post {
+    // Files saved on Windows would otherwise leak `\r` into front-matter
+    // values and math sources.
+    let file_content = file_content.replace("\r\n", "\n");
     let matter = Matter::<YAML>::new();
-    let result = matter.parse::<FrontMatter>(file_content);
+    let result = matter.parse::<FrontMatter>(&file_content);
 
+    let mut problems = Vec::new();
     let (front_matter, markdown_body) = match result {
-        Ok(parsed) => (parsed.data, parsed.content),
+        Ok(parsed) => {
+            if parsed.data.is_none() {
+                problems.push("Missing front matter".to_string());
+            }
+            (parsed.data, parsed.content)
+        }
         Err(e) => {
+            problems.push(format!("Invalid front matter: {e}"));
             if is_home {
                 error!("Failed to parse home front matter: {}", e);
             } else {
@@ -82,6 +826,18 @@ fn parse_markdown_post(file_content: &str, is_home: bool) -> Post {
                     image: None,
                     role: None,
                     subtitle: None,
+                    tags: Vec::new(),
+                    expires: None,
+                    comments: None,
+                    keywords: None,
+                    canonical_source: None,
+                    updated: None,
+                    toc: false,
+                    category: None,
+                    show_banner: None,
+                    og_type: None,
+                    includes: Vec::new(),
+                    draft: false,
                 }),
                 file_content.to_string(),
             )
@@ -111,6 +867,691 @@ fn parse_markdown_post(file_content: &str, is_home: bool) -> Post {
         image: front_matter.as_ref().and_then(|fm| fm.image.clone()),
         role: front_matter.as_ref().and_then(|fm| fm.role.clone()),
         subtitle: front_matter.as_ref().and_then(|fm| fm.subtitle.clone()),
+        tags: front_matter
+            .as_ref()
+            .map(|fm| fm.tags.clone())
+            .unwrap_or_default(),
+        expires: front_matter.as_ref().and_then(|fm| fm.expires.clone()),
+        comments: front_matter.as_ref().and_then(|fm| fm.comments),
+        keywords: front_matter
+            .as_ref()
+            .map(|fm| fm.keywords.clone().unwrap_or_else(|| fm.tags.clone()))
+            .unwrap_or_default(),
+        canonical_source: front_matter
+            .as_ref()
+            .and_then(|fm| fm.canonical_source.clone()),
+        updated: front_matter.as_ref().and_then(|fm| fm.updated.clone()),
+        toc: front_matter.as_ref().is_some_and(|fm| fm.toc),
+        category: front_matter.as_ref().and_then(|fm| fm.category.clone()),
+        show_banner: front_matter.as_ref().and_then(|fm| fm.show_banner),
+        og_type: front_matter.as_ref().and_then(|fm| fm.og_type.clone()),
+        includes: front_matter
+            .as_ref()
+            .map(|fm| fm.includes.clone())
+            .unwrap_or_default(),
+        draft: front_matter.as_ref().is_some_and(|fm| fm.draft),
         markdown_body,
+        // Counted once `includes` are appended.
+        reading_minutes: 0,
+        source_path: source_path.to_path_buf(),
+        // Stamped by `load_posts_dir` from the file's metadata.
+        modified: None,
+        problems,
+        content_path: PathBuf::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_content_from, parse_markdown_post, render_posts, render_posts_with, ContentRoots,
+        GroupEntry, LoadOptions,
+    };
+    use crate::markdown::{render_markdown_to_html, RenderOptions};
+    use crate::models::{Post, TagOrder};
+    use crate::nav::{group_by_category, group_by_tag};
+    use crate::page_meta::slugify;
+    use crate::test_support::{capture_logs, TestContentDir};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn routes_posts_by_front_matter_slug_not_file_name() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "my-post.md",
+            "---\ntitle: \"Mismatched\"\ndate: 2026-03-04\nslug: different\n---\nBody.",
+        );
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+
+        let post = loaded
+            .posts
+            .iter()
+            .find(|post| post.slug == "different")
+            .expect("post keyed by front-matter slug");
+        assert!(post.source_path.ends_with("posts/my-post.md"));
+    }
+
+    #[tokio::test]
+    async fn keeps_first_post_when_slugs_collide() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "a.md",
+            "---\ntitle: \"First\"\ndate: 2026-03-04\nslug: same\n---\nA.",
+        );
+        content.write_post(
+            "b.md",
+            "---\ntitle: \"Second\"\ndate: 2026-03-05\nslug: same\n---\nB.",
+        );
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+
+        assert_eq!(loaded.posts.len(), 1);
+        assert_eq!(loaded.posts[0].title, "First");
+        assert!(loaded.posts[0].source_path.ends_with("posts/a.md"));
+    }
+
+    #[tokio::test]
+    async fn lists_posts_newest_first_with_undated_posts_last() {
+        let content = TestContentDir::new();
+        for (slug, date) in [
+            ("a-middle", "2026-02-01"),
+            ("b-undated", "someday"),
+            ("c-newest", "2026-03-01"),
+            ("d-oldest", "2025-12-31"),
+            ("e-undated", "soon"),
+        ] {
+            content.write_post(
+                &format!("{slug}.md"),
+                &format!("---\ntitle: \"{slug}\"\ndate: {date}\nslug: {slug}\n---\nBody."),
+            );
+        }
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+
+        let slugs: Vec<&str> = loaded.posts.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(
+            slugs,
+            ["c-newest", "a-middle", "d-oldest", "e-undated", "b-undated"]
+        );
+    }
+
+    #[tokio::test]
+    async fn order_manifest_overrides_date_order() {
+        let content = TestContentDir::new();
+        for (slug, date) in [
+            ("old", "2026-01-01"),
+            ("mid", "2026-02-01"),
+            ("new", "2026-03-01"),
+        ] {
+            content.write_post(
+                &format!("{slug}.md"),
+                &format!("---\ntitle: \"{slug}\"\ndate: {date}\nslug: {slug}\n---\nBody."),
+            );
+        }
+        content.write_post("order.txt", "# pinned first\nold\nmissing\n");
+
+        let (logs, _guard) = capture_logs();
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+
+        let slugs: Vec<&str> = loaded.posts.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(slugs, ["old", "new", "mid"]);
+        assert!(logs
+            .contents()
+            .contains("posts/order.txt names an unknown post"));
+    }
+
+    #[tokio::test]
+    async fn group_indexes_match_grouping_and_follow_reloads() {
+        fn assert_matches_groups(
+            posts: &[Post],
+            index: &HashMap<String, GroupEntry>,
+            groups: Vec<(&str, Vec<&Post>)>,
+        ) {
+            assert_eq!(index.len(), groups.len());
+            for (name, members) in groups {
+                let entry = &index[&slugify(name)];
+                assert_eq!(entry.name, name);
+                let indexed: Vec<&str> = entry
+                    .posts
+                    .iter()
+                    .map(|&position| posts[position].slug.as_str())
+                    .collect();
+                let grouped: Vec<&str> = members.iter().map(|post| post.slug.as_str()).collect();
+                assert_eq!(indexed, grouped, "{name}");
+            }
+        }
+
+        let content = TestContentDir::new();
+        content.write_post(
+            "borrowing.md",
+            "---\ntitle: \"Borrowing\"\ndate: 2026-03-04\nslug: borrowing\ncategory: Rust Notes\ntags: [Rust, Ownership]\n---\nBody.",
+        );
+        content.write_post(
+            "snakes.md",
+            "---\ntitle: \"Snakes\"\ndate: 2026-03-01\nslug: snakes\ntags: [python]\n---\nBody.",
+        );
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert_matches_groups(
+            &loaded.posts,
+            &loaded.category_index,
+            group_by_category(&loaded.posts, TagOrder::default()),
+        );
+        assert_matches_groups(
+            &loaded.posts,
+            &loaded.tag_index,
+            group_by_tag(&loaded.posts, TagOrder::default()),
+        );
+
+        content.write_post(
+            "lifetimes.md",
+            "---\ntitle: \"Lifetimes\"\ndate: 2026-03-05\nslug: lifetimes\ncategory: rust notes\ntags: [rust]\n---\nBody.",
+        );
+        let reloaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("reload");
+        assert_matches_groups(
+            &reloaded.posts,
+            &reloaded.category_index,
+            group_by_category(&reloaded.posts, TagOrder::default()),
+        );
+        assert_matches_groups(
+            &reloaded.posts,
+            &reloaded.tag_index,
+            group_by_tag(&reloaded.posts, TagOrder::default()),
+        );
+        let rust = &reloaded.tag_index["rust"];
+        assert_eq!(rust.name, "rust");
+        let slugs: Vec<&str> = rust
+            .posts
+            .iter()
+            .map(|&position| reloaded.posts[position].slug.as_str())
+            .collect();
+        assert_eq!(slugs, ["lifetimes", "borrowing"]);
+    }
+
+    #[tokio::test]
+    async fn loads_drafts_folder_only_when_requested() {
+        let content = TestContentDir::new();
+        content.write(
+            "drafts/wip.md",
+            "---\ntitle: \"Work in progress\"\ndate: 2026-03-04\nslug: wip\n---\nDraft.",
+        );
+
+        let published = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(published.posts.iter().all(|post| post.slug != "wip"));
+
+        let development = load_content_from(
+            &content.roots(),
+            LoadOptions {
+                include_drafts: true,
+                ..LoadOptions::default()
+            },
+        )
+        .await
+        .expect("load");
+        assert!(development.posts.iter().any(|post| post.slug == "wip"));
+    }
+
+    #[tokio::test]
+    async fn draft_flag_hides_posts_unless_drafts_are_shown() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "flagged.md",
+            "---\ntitle: \"Flagged\"\ndate: 2026-03-04\nslug: flagged\ndraft: true\n---\nDraft.",
+        );
+
+        let published = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(published.posts.iter().all(|post| post.slug != "flagged"));
+
+        let development = load_content_from(
+            &content.roots(),
+            LoadOptions {
+                include_drafts: true,
+                ..LoadOptions::default()
+            },
+        )
+        .await
+        .expect("load");
+        assert!(development.posts.iter().any(|post| post.slug == "flagged"));
+    }
+
+    #[tokio::test]
+    async fn appends_included_fragments_after_the_body() {
+        let content = TestContentDir::new();
+        content.write("fragments/part-two.md", "## Part two\n\nMore.");
+        content.write("secret.md", "Outside fragments.");
+        content.write_post(
+            "long.md",
+            "---\ntitle: \"Long\"\ndate: 2026-03-04\nslug: long\nincludes:\n  - part-two.md\n  - ../secret.md\n---\nPart one.",
+        );
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let post = loaded
+            .posts
+            .iter()
+            .find(|post| post.slug == "long")
+            .expect("post");
+        let html = render_markdown_to_html(&post.markdown_body, &RenderOptions::default());
+
+        assert!(html.contains("<p>Part one.</p>"));
+        assert!(html.contains("Part two</h2>"));
+        assert!(html.find("Part one.") < html.find("Part two"));
+        assert!(!html.contains("Outside fragments."));
+    }
+
+    #[tokio::test]
+    async fn skips_includes_that_exceed_the_size_limit() {
+        let content = TestContentDir::new();
+        content.write("fragments/huge.md", &"x".repeat(4096));
+        content.write("fragments/half.md", &"y".repeat(600));
+        content.write("fragments/other-half.md", &"z".repeat(600));
+        content.write_post(
+            "long.md",
+            "---\ntitle: \"Long\"\ndate: 2026-03-04\nslug: long\nincludes:\n  - huge.md\n  - half.md\n  - other-half.md\n---\nPart one.",
+        );
+
+        let (logs, _guard) = capture_logs();
+        let loaded = load_content_from(
+            &content.roots(),
+            LoadOptions {
+                max_post_bytes: Some(1024),
+                ..LoadOptions::default()
+            },
+        )
+        .await
+        .expect("load");
+        let post = loaded
+            .posts
+            .iter()
+            .find(|post| post.slug == "long")
+            .expect("post");
+
+        // Each fragment fits on its own, but only the first half fits after
+        // the body.
+        assert!(!post.markdown_body.contains('x'));
+        assert!(post.markdown_body.contains('y'));
+        assert!(!post.markdown_body.contains('z'));
+        assert!(post.markdown_body.len() <= 1024);
+        let logs = logs.contents();
+        assert!(logs.contains("huge.md"));
+        assert!(logs.contains("other-half.md"));
+        assert!(logs.contains("skipping include that takes the post past MAX_POST_BYTES"));
+    }
+
+    #[tokio::test]
+    async fn override_files_take_precedence_over_theme_files() {
+        let theme = TestContentDir::new();
+        theme.write("layout.html", "<html>theme layout {{ content }}</html>");
+        theme.write("banner.html", "<header>theme banner</header>");
+        let site = TestContentDir::new();
+        site.write("layout.html", "<html>site layout {{ content }}</html>");
+        std::fs::remove_file(site.path().join("banner.html")).expect("remove banner");
+
+        let roots = ContentRoots {
+            content_dir: site.path().to_path_buf(),
+            theme_dir: Some(theme.path().to_path_buf()),
+            static_dir: None,
+        };
+        let loaded = load_content_from(&roots, LoadOptions::default())
+            .await
+            .expect("load");
+
+        assert!(loaded.layout_html.contains("site layout"));
+        assert_eq!(loaded.banner_html, "<header>theme banner</header>");
+    }
+
+    #[tokio::test]
+    async fn versions_static_links_in_templates() {
+        let content = TestContentDir::new();
+        content.write("static/site.css", "body {}");
+        content.write(
+            "layout.html",
+            "<html><head><link rel=\"stylesheet\" href=\"/static/site.css\" /></head><body>{{ content }}</body></html>",
+        );
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+
+        assert!(loaded.layout_html.contains("href=\"/static/site.css?v="));
+    }
+
+    #[tokio::test]
+    async fn layout_head_includes_apple_touch_icon_when_present() {
+        let content = TestContentDir::new();
+        content.write("static/apple-touch-icon.png", "png");
+        content.write(
+            "layout.html",
+            "<html><head>{{ icon_links }}</head><body>{{ content }}</body></html>",
+        );
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+
+        assert!(loaded.layout_html.contains(
+            r#"rel="apple-touch-icon" sizes="180x180" href="/static/apple-touch-icon.png?v="#
+        ));
+        assert!(!loaded.layout_html.contains("{{ icon_links }}"));
+    }
+
+    #[tokio::test]
+    async fn records_render_time_for_each_loaded_post() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "plain.md",
+            "---\ntitle: \"Plain\"\ndate: 2026-03-04\nslug: plain\n---\nText.",
+        );
+        content.write_post(
+            "math.md",
+            "---\ntitle: \"Math\"\ndate: 2026-03-05\nslug: math\n---\n$$x^2$$",
+        );
+
+        let untimed = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(untimed.render_timings.is_empty());
+
+        let loaded = load_content_from(
+            &content.roots(),
+            LoadOptions {
+                render_timing: Some(RenderOptions::default()),
+                ..LoadOptions::default()
+            },
+        )
+        .await
+        .expect("load");
+
+        let mut slugs: Vec<_> = loaded.render_timings.keys().cloned().collect();
+        slugs.sort();
+        assert_eq!(slugs, ["math", "plain"]);
+    }
+
+    #[test]
+    fn parallel_renders_match_sequential_output() {
+        let posts: Vec<Post> = (0..7)
+            .map(|index| Post {
+                slug: format!("post-{index}"),
+                markdown_body: format!(
+                    "# Post {index}\n\n$x^{index}$ and $$\\frac{{{index}}}{{2}}$$"
+                ),
+                ..Post::default()
+            })
+            .collect();
+        let options = RenderOptions::default();
+
+        let html = |threads| -> Vec<String> {
+            render_posts(&posts, &options, threads)
+                .into_iter()
+                .map(|(html, _)| html.expect("rendered"))
+                .collect()
+        };
+        let sequential = html(1);
+        assert!(sequential[3].contains("Post 3"));
+        assert_eq!(html(3), sequential);
+        assert_eq!(html(16), sequential);
+    }
+
+    #[test]
+    fn a_panicking_render_fails_only_its_own_post() {
+        let posts: Vec<Post> = ["first", "cursed", "last"]
+            .into_iter()
+            .map(|slug| Post {
+                slug: slug.to_string(),
+                ..Post::default()
+            })
+            .collect();
+        let (logs, _guard) = capture_logs();
+
+        for threads in [1, 3] {
+            let rendered: Vec<Option<String>> = render_posts_with(&posts, threads, |post| {
+                if post.slug == "cursed" {
+                    panic!("pathological markdown");
+                }
+                format!("<p>{}</p>", post.slug)
+            })
+            .into_iter()
+            .map(|(html, _)| html)
+            .collect();
+
+            assert_eq!(
+                rendered,
+                [
+                    Some("<p>first</p>".to_string()),
+                    None,
+                    Some("<p>last</p>".to_string())
+                ],
+                "{threads} threads"
+            );
+        }
+        let logs = logs.contents();
+        assert!(logs.contains("post render panicked"));
+        assert!(logs.contains("pathological markdown"));
+    }
+
+    #[test]
+    fn keywords_come_from_front_matter_or_fall_back_to_tags() {
+        let path = std::path::Path::new("posts/p.md");
+        let explicit = parse_markdown_post(
+            "---\ntitle: T\ndate: 2026-03-04\nslug: p\ntags: [rust]\nkeywords: [axum, katex]\n---\nBody",
+            path,
+            false,
+        );
+        assert_eq!(explicit.keywords, ["axum", "katex"]);
+
+        let from_tags = parse_markdown_post(
+            "---\ntitle: T\ndate: 2026-03-04\nslug: p\ntags: [rust, web]\n---\nBody",
+            path,
+            false,
+        );
+        assert_eq!(from_tags.keywords, ["rust", "web"]);
+    }
+
+    #[test]
+    fn parses_front_matter_and_math_from_crlf_files() {
+        let post = parse_markdown_post(
+            "---\r\ntitle: \"Windows\"\r\ndate: 2026-03-04\r\nslug: windows\r\n---\r\nBefore\r\n\\[\r\nx^2\r\n\\]\r\nAfter\r\n",
+            std::path::Path::new("posts/windows.md"),
+            false,
+        );
+
+        assert_eq!(post.title, "Windows");
+        assert_eq!(post.slug, "windows");
+        assert_eq!(post.date, "2026-03-04");
+        assert!(!post.markdown_body.contains('\r'));
+        let html = render_markdown_to_html(&post.markdown_body, &RenderOptions::default());
+        assert!(html.contains("katex-display"));
+    }
+
+    #[tokio::test]
+    async fn loads_gone_slugs_ignoring_comments_and_blank_lines() {
+        let content = TestContentDir::new();
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(loaded.gone_slugs.is_empty());
+
+        content.write(
+            "gone.txt",
+            "# removed posts\nold-post\n\n  retired-post  \n",
+        );
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let mut gone: Vec<_> = loaded.gone_slugs.into_iter().collect();
+        gone.sort();
+        assert_eq!(gone, ["old-post", "retired-post"]);
+    }
+
+    #[tokio::test]
+    async fn maintenance_sentinel_file_is_detected() {
+        let content = TestContentDir::new();
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(!loaded.maintenance);
+
+        content.write("MAINTENANCE", "");
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(loaded.maintenance);
+    }
+
+    #[tokio::test]
+    async fn skips_posts_larger_than_the_size_limit() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "small.md",
+            "---\ntitle: \"Small\"\ndate: 2026-03-04\nslug: small\n---\nShort.",
+        );
+        content.write_post(
+            "huge.md",
+            &format!(
+                "---\ntitle: \"Huge\"\ndate: 2026-03-04\nslug: huge\n---\n{}",
+                "x".repeat(4096)
+            ),
+        );
+
+        let (logs, _guard) = capture_logs();
+        let loaded = load_content_from(
+            &content.roots(),
+            LoadOptions {
+                max_post_bytes: Some(1024),
+                ..LoadOptions::default()
+            },
+        )
+        .await
+        .expect("load");
+
+        let slugs: Vec<_> = loaded.posts.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(slugs, ["small"]);
+        let logs = logs.contents();
+        assert!(logs.contains("skipping post larger than MAX_POST_BYTES"));
+        assert!(logs.contains("huge.md"));
+    }
+
+    #[tokio::test]
+    async fn print_layout_defaults_to_layout_without_navigation() {
+        let content = TestContentDir::new();
+        content.write(
+            "layout.html",
+            "<body><nav>{{ nav }}</nav><nav>{{ sitenav }}</nav><nav>{{ categories }}</nav>{{ content }}</body>",
+        );
+        content.write(
+            "nav.toml",
+            "[[links]]\nlabel = \"Posts\"\nhref = \"/posts\"\n",
+        );
+        content.write_post(
+            "borrowing.md",
+            "---\ntitle: \"Borrowing\"\ndate: 2026-03-04\nslug: borrowing\ncategory: Rust\ntags: [rust]\n---\nBody.",
+        );
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert!(loaded.layout_html.contains("href=\"/posts\""));
+        assert!(loaded.layout_html.contains("/categories/rust"));
+        assert_eq!(
+            loaded.print_layout_html,
+            "<body><nav></nav><nav></nav><nav></nav>{{ content }}</body>"
+        );
+
+        content.write("print.html", "<article>{{ content }}</article>");
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert_eq!(loaded.print_layout_html, "<article>{{ content }}</article>");
+    }
+
+    #[tokio::test]
+    async fn dangling_nav_link_fails_only_in_strict_mode() {
+        let content = TestContentDir::new();
+        content.write(
+            "nav.toml",
+            "[[links]]\nlabel = \"Missing\"\nhref = \"/posts/missing\"\n",
+        );
+
+        let (logs, _guard) = capture_logs();
+        load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("lenient load");
+        assert!(logs
+            .contents()
+            .contains("nav entry points at an unknown internal route"));
+
+        let Err(error) = load_content_from(
+            &content.roots(),
+            LoadOptions {
+                strict: true,
+                ..LoadOptions::default()
+            },
+        )
+        .await
+        else {
+            panic!("strict load accepted a dangling nav link");
+        };
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("/posts/missing"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn strict_mode_fails_on_post_problems_and_unreplaced_placeholders() {
+        let strict = LoadOptions {
+            strict: true,
+            ..LoadOptions::default()
+        };
+
+        let content = TestContentDir::new();
+        load_content_from(&content.roots(), strict.clone())
+            .await
+            .expect("clean content loads");
+
+        content.write(
+            "layout.html",
+            "<html><body>{{ content }}{{ footer }}</body></html>",
+        );
+        content.write_post(
+            "math.md",
+            "---\ntitle: \"Math\"\ndate: 2026-03-04\nslug: math\n---\n$\\frac{1}$",
+        );
+        content.write_post("broken.md", "---\ntitle: \"Broken\"\n---\nBody.");
+        content.write_post(
+            "links.md",
+            "---\ntitle: \"Links\"\ndate: 2026-03-04\nslug: links\n---\nSee [math](/posts/math), [its text](/posts/math.txt#top) and [a missing one](/posts/missing-post?ref=x).",
+        );
+
+        let Err(error) = load_content_from(&content.roots(), strict).await else {
+            panic!("strict load accepted broken content");
+        };
+        let error = error.to_string();
+        assert!(
+            error.contains("unreplaced placeholder {{ footer }}"),
+            "{error}"
+        );
+        assert!(error.contains("Invalid front matter"), "{error}");
+        assert!(error.contains("in `\\frac{1}`"), "{error}");
+        assert!(
+            error.contains("links.md: links to unknown post /posts/missing-post?ref=x"),
+            "{error}"
+        );
+        assert_eq!(error.matches("links to unknown post").count(), 1, "{error}");
     }
 }
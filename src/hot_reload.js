@@ -1,5 +1,20 @@
 if (!window.__hotReloadController) {
     const devloopEventsUrl = __DEVLOOP_BROWSER_EVENTS_URL__;
+    const reloadFlashTitle = __HOT_RELOAD_FLASH_TITLE__;
+    const flashPendingKey = "hotReloadFlashPending";
+
+    // Briefly shows `reloadFlashTitle` as the tab title so a reload is
+    // noticeable when the window is not in focus.
+    const flashTitle = () => {
+        if (!reloadFlashTitle) {
+            return;
+        }
+        const original = document.title;
+        document.title = reloadFlashTitle;
+        window.setTimeout(() => {
+            document.title = original;
+        }, 1500);
+    };
     const reportCurrentPath = () => {
         fetch("/__dev/current-path", {
             method: "POST",
@@ -14,9 +29,50 @@ if (!window.__hotReloadController) {
             return;
         }
         window.__hotReloadController.reloadPending = true;
+        if (reloadFlashTitle) {
+            try {
+                window.sessionStorage.setItem(flashPendingKey, "1");
+            } catch {}
+        }
         window.location.reload();
     };
 
+    // `reload:<path>` means only that page's content changed. When it is the
+    // page being viewed, swap in its partial render so scroll position
+    // survives; anything else falls back to a full reload.
+    const swapContent = async () => {
+        const main = document.querySelector("main");
+        if (!main) {
+            triggerReload();
+            return;
+        }
+        try {
+            const response = await fetch(window.location.pathname + "?partial=1", {
+                headers: { "x-requested-with": "hot-reload" },
+                cache: "no-store",
+            });
+            if (!response.ok) {
+                throw new Error(`partial fetch failed: ${response.status}`);
+            }
+            main.innerHTML = await response.text();
+            flashTitle();
+        } catch {
+            triggerReload();
+        }
+    };
+
+    const handleMessage = (data) => {
+        if (data === "reload") {
+            triggerReload();
+        } else if (data.startsWith("reload:")) {
+            if (data.slice("reload:".length) === window.location.pathname) {
+                swapContent();
+            } else {
+                triggerReload();
+            }
+        }
+    };
+
     const connect = () => {
         const controller = window.__hotReloadController;
 
@@ -31,11 +87,7 @@ if (!window.__hotReloadController) {
         const source = new EventSource(devloopEventsUrl);
         controller.eventSource = source;
 
-        source.onmessage = (event) => {
-            if (event.data === "reload") {
-                triggerReload();
-            }
-        };
+        source.onmessage = (event) => handleMessage(event.data);
 
         source.onerror = () => {
             source.close();
@@ -72,6 +124,13 @@ if (!window.__hotReloadController) {
     reportCurrentPath();
     window.addEventListener("popstate", reportCurrentPath);
 
+    try {
+        if (window.sessionStorage.getItem(flashPendingKey)) {
+            window.sessionStorage.removeItem(flashPendingKey);
+            flashTitle();
+        }
+    } catch {}
+
     if (document.readyState === "loading") {
         document.addEventListener("DOMContentLoaded", connect, { once: true });
     } else {
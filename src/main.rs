@@ -1,40 +1,89 @@
-use std::{io, net::SocketAddr, path::Path as StdPath, sync::Arc, time::UNIX_EPOCH};
+use std::{
+    any::Any,
+    collections::HashMap,
+    convert::Infallible,
+    hash::{BuildHasher, Hasher, RandomState},
+    io,
+    net::SocketAddr,
+    path::{Path as StdPath, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
     body::Bytes,
-    extract::{OriginalUri, Path, State},
-    http::StatusCode,
+    extract::{FromRequestParts, OriginalUri, Path, Query, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri},
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
-    routing::{get, get_service},
+    routing::{get, get_service, post},
     Router,
 };
+use serde::Deserialize;
 use tokio::{net::TcpListener, sync::RwLock};
-use tower_http::services::{ServeDir, ServeFile};
-use tracing::{error, info, warn};
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    services::{ServeDir, ServeFile},
+    timeout::TimeoutLayer,
+};
+use tracing::{error, info, info_span, warn, Instrument};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod api;
+mod assets;
 mod content_loader;
 mod markdown;
 mod models;
+mod nav;
 mod page_meta;
+mod post_url;
+mod sanitize;
 mod state;
 #[cfg(test)]
 mod test_support;
 
-use content_loader::load_content;
-use markdown::render_markdown_to_html;
-use models::{Post, SiteConfig};
+use assets::mime_overrides_from_env;
+use content_loader::{
+    load_content, load_content_from, max_post_bytes_from_env, render_threads_from_env,
+    strict_from_env, ContentRoots, InvalidSlugs, LoadOptions, LoadedContent, PAGE_PLACEHOLDERS,
+};
+use markdown::{render_markdown_to_html, render_markdown_with_toc, RenderOptions};
+use models::{HomeMode, Post, SiteConfig};
+use nav::{group_heading, sort_groups};
 use page_meta::{
-    build_post_meta, default_not_found_meta, escape_html, page_url, PageMeta, PostMetaInput,
+    build_post_meta, category_meta, category_path, default_error_meta, default_gone_meta,
+    default_maintenance_meta, default_not_found_meta, encoded_path, escape_html, page_url,
+    plain_text, post_excerpt, post_index_meta, post_path, tag_index_meta, tag_meta, tag_path,
+    PageMeta, PageType, PostMetaInput,
 };
-use state::{AppState, DevloopEventClient, RouterState};
+use state::{AppState, DevloopEventClient, Environment, ExpiredPosts, RouterState};
 
 // Load the hot reload script template at compile time.
 const HOT_RELOAD_SCRIPT: &str = include_str!("hot_reload.js");
 const HOT_RELOAD_EVENTS_URL_PLACEHOLDER: &str = "__DEVLOOP_BROWSER_EVENTS_URL__";
+const HOT_RELOAD_FLASH_TITLE_PLACEHOLDER: &str = "__HOT_RELOAD_FLASH_TITLE__";
 const HOT_RELOAD_TAG_START: &str = "<script>";
 const HOT_RELOAD_TAG_END: &str = "</script>";
+const MAINTENANCE_MARKDOWN: &str =
+    "# Down for maintenance\n\nThe site is being updated. Please check back shortly.";
+const NOINDEX_META: &str = "<meta name=\"robots\" content=\"noindex\" />";
+const PANIC_MARKDOWN: &str =
+    "# Something went wrong\n\nThe server hit an unexpected error rendering this page.";
+const GONE_MARKDOWN: &str =
+    "# Post removed\n\nThe post `{{slug}}` has been removed and will not be coming back.";
 const DEV_STYLESHEET_HREF: &str = "/static/tailwind.css";
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longer incoming request IDs are replaced rather than logged.
+const MAX_REQUEST_ID_LEN: usize = 128;
+const DEFAULT_DEV_ALLOWED_ORIGINS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+/// Posts shown on the homepage when `HOME_MODE=list`.
+const HOME_LIST_LEN: usize = 10;
+/// Most posts `/partials/latest` lists, whatever `count` asks for.
+const LATEST_PARTIAL_MAX: usize = 50;
 
 fn log_boxed_banner(message: &str) {
     let width = message.chars().count() + 4;
@@ -45,7 +94,10 @@ fn log_boxed_banner(message: &str) {
     info!("{}", border);
 }
 
-fn render_post_list(posts: &[Post]) -> String {
+fn render_post_list<'a>(
+    posts: impl IntoIterator<Item = &'a Post>,
+    site_config: &SiteConfig,
+) -> String {
     // Single pass: collect ordered groups preserving first-seen role order.
     let mut groups: Vec<(Option<&str>, Vec<&Post>)> = Vec::new();
     for post in posts {
@@ -57,20 +109,17 @@ fn render_post_list(posts: &[Post]) -> String {
         }
     }
 
+    if groups.is_empty() {
+        return format!(
+            "<li class=\"sidebar-empty\">{}</li>",
+            escape_html(&site_config.no_posts_text)
+        );
+    }
+
     let mut list_items = String::new();
     for (role, group_posts) in groups {
         if let Some(r) = role {
-            let header = r
-                .split('-')
-                .map(|w| {
-                    let mut c = w.chars();
-                    match c.next() {
-                        None => String::new(),
-                        Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(" ");
+            let header = group_heading(r);
             list_items.push_str(&format!(
                 "<li class=\"sidebar-group-header\">{}</li>",
                 escape_html(&header)
@@ -87,27 +136,101 @@ fn render_post_list(posts: &[Post]) -> String {
                     )
                 })
                 .unwrap_or_default();
+            let href = site_config.post_urls.path(post);
+            let excerpt_html = post
+                .description
+                .as_deref()
+                .filter(|_| site_config.listing_excerpts)
+                .map(|description| {
+                    format!(
+                        "<p class=\"sidebar-post-excerpt\">{} <a href=\"{href}\" class=\"read-more-link\">{}</a></p>",
+                        escape_html(description),
+                        escape_html(&site_config.read_more_text)
+                    )
+                })
+                .unwrap_or_default();
             list_items.push_str(&format!(
-                "<li><a href=\"/posts/{}\" class=\"sidebar-post-link\"><span class=\"sidebar-post-title\">{}</span>{}</a></li>",
-                post.slug, escape_html(&post.title), subtitle_html
+                "<li><a href=\"{href}\" class=\"sidebar-post-link\"><span class=\"sidebar-post-title\">{}</span>{}</a>{}</li>",
+                escape_html(&post.title), subtitle_html, excerpt_html
             ));
         }
     }
     list_items
 }
 
-fn render_with_layout(
+/// What fills a layout's `{{ content }}`, `{{ toc }}`, `{{ comments }}` and
+/// `{{ reading_time }}`. A bare `&str` is a page with none of the extras.
+#[derive(Clone, Copy, Default)]
+struct PageContent<'a> {
+    body: &'a str,
+    toc: &'a str,
+    comments: &'a str,
+    /// "N min read" on post pages.
+    reading_time: &'a str,
+}
+
+impl<'a> From<&'a str> for PageContent<'a> {
+    fn from(body: &'a str) -> Self {
+        Self {
+            body,
+            ..Self::default()
+        }
+    }
+}
+
+impl<'a> From<&'a String> for PageContent<'a> {
+    fn from(body: &'a String) -> Self {
+        Self::from(body.as_str())
+    }
+}
+
+/// Substitutes `{{ name }}` placeholders in one pass over `template`, so
+/// text that arrives through a value (a post body about templating, a
+/// heading in the table of contents) is never expanded itself. Unknown
+/// names are left as written.
+fn fill_placeholders(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        let value = candidate.find("}}").and_then(|end| {
+            let name = candidate[2..end].trim();
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (*value, end + 2))
+        });
+        match value {
+            Some((value, consumed)) => {
+                out.push_str(value);
+                rest = &candidate[consumed..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = &candidate[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_with_layout<'a, 'b>(
     layout: &str,
     banner: &str,
-    content: &str,
-    posts: &[Post],
+    content: impl Into<PageContent<'b>>,
+    posts: impl IntoIterator<Item = &'a Post>,
+    site_config: &SiteConfig,
     meta: &PageMeta,
-    is_development: bool,
+    audience: impl Into<Audience>,
 ) -> String {
-    let list_items = render_post_list(posts);
+    let audience = audience.into();
+    let list_items = render_post_list(posts, site_config);
     let escaped_title = escape_html(&meta.title);
     let escaped_description = escape_html(&meta.description);
     let escaped_url = escape_html(&meta.url);
+    let escaped_canonical_url = escape_html(&meta.canonical_url);
     let escaped_image = escape_html(&meta.image);
     let escaped_author = escape_html(&meta.author);
 
@@ -121,6 +244,14 @@ fn render_with_layout(
             )
         })
         .unwrap_or_default();
+    let keywords_meta = if meta.keywords.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<meta name=\"keywords\" content=\"{}\" />",
+            escape_html(&meta.keywords.join(", "))
+        )
+    };
     let published_time_meta = meta
         .published_time
         .as_deref()
@@ -132,26 +263,129 @@ fn render_with_layout(
         })
         .unwrap_or_default();
 
-    let mut page = layout
-        .replace("{{ banner }}", banner)
-        .replace("{{ posts }}", &list_items)
-        .replace("{{ page_title }}", &escaped_title)
-        .replace("{{ page_description }}", &escaped_description)
-        .replace("{{ page_url }}", &escaped_url)
-        .replace("{{ page_image }}", &escaped_image)
-        .replace("{{ page_author }}", &escaped_author)
-        .replace("{{ page_published_time_meta }}", &published_time_meta)
-        .replace("{{ page_role_meta }}", &role_meta)
-        .replace("{{ content }}", content);
+    let content = content.into();
+    let body = content_landmark(layout, content.body);
+    let math_css = if has_math(content.body) {
+        format!(
+            "<link rel=\"stylesheet\" href=\"{}\" />",
+            escape_html(&site_config.katex_css_url)
+        )
+    } else {
+        String::new()
+    };
+    let og_type = escape_html(meta.og_type());
+    let fills: Vec<(&str, &str)> = PAGE_PLACEHOLDERS
+        .iter()
+        .map(|&name| {
+            let value: &str = match name {
+                "banner" => banner,
+                "posts" => &list_items,
+                "page_title" => &escaped_title,
+                "page_description" => &escaped_description,
+                "page_url" => &escaped_url,
+                "page_canonical_url" => &escaped_canonical_url,
+                "page_image" => &escaped_image,
+                "page_author" => &escaped_author,
+                "page_published_time_meta" => &published_time_meta,
+                "page_role_meta" => &role_meta,
+                "page_keywords_meta" => &keywords_meta,
+                "page_og_type" => &og_type,
+                "body_class" => meta.page_type.body_class(),
+                "toc" => content.toc,
+                "comments" => content.comments,
+                "reading_time" => content.reading_time,
+                "content" => &body,
+                "math_css" => &math_css,
+                _ => unreachable!("page placeholder {name} has no value"),
+            };
+            (name, value)
+        })
+        .collect();
+    let mut page = fill_placeholders(layout, &fills);
 
-    if is_development {
-        page = inject_dev_stylesheet_version(page);
-        page = inject_hot_reload_script(page);
+    match audience.environment {
+        Environment::Development => {
+            page = inject_dev_stylesheet_version(page);
+            page = inject_hot_reload_script(page);
+        }
+        // Staging serves production pages that must not be indexed or
+        // counted as real visits.
+        Environment::Staging => page = inject_into_head(page, NOINDEX_META),
+        Environment::Production => {
+            match (
+                site_config.analytics_snippet.as_deref(),
+                site_config.consent_banner.as_deref(),
+            ) {
+                // Until the visitor accepts, show the banner instead.
+                (Some(_), Some(banner)) if !audience.analytics_consent => {
+                    page = inject_before_body_end(page, banner);
+                }
+                (Some(snippet), _) => page = inject_into_head(page, snippet),
+                (None, _) => {}
+            }
+        }
     }
 
     page
 }
 
+/// Who a full page is rendered for: the environment decides what gets
+/// injected, and in production the visitor's consent decides between the
+/// analytics snippet and the consent banner.
+#[derive(Clone, Copy, Debug)]
+struct Audience {
+    environment: Environment,
+    analytics_consent: bool,
+}
+
+impl Audience {
+    fn new(environment: Environment, consent: AnalyticsConsent) -> Self {
+        Self {
+            environment,
+            analytics_consent: consent.0,
+        }
+    }
+}
+
+impl From<Environment> for Audience {
+    fn from(environment: Environment) -> Self {
+        Self::new(environment, AnalyticsConsent::default())
+    }
+}
+
+/// Server-typeset KaTeX output or the `math` spans left for client-side
+/// rendering, either of which needs the KaTeX stylesheet.
+fn has_math(body: &str) -> bool {
+    body.contains("class=\"katex") || body.contains("class=\"math ")
+}
+
+fn inject_before_body_end(page: String, snippet: &str) -> String {
+    match page.rsplit_once("</body>") {
+        Some((head, tail)) => format!("{head}{snippet}</body>{tail}"),
+        None => format!("{page}{snippet}"),
+    }
+}
+
+fn inject_into_head(page: String, snippet: &str) -> String {
+    if let Some((head, tail)) = page.split_once("</head>") {
+        format!("{head}{snippet}</head>{tail}")
+    } else if let Some((head, tail)) = page.rsplit_once("</body>") {
+        format!("{head}{snippet}</body>{tail}")
+    } else {
+        format!("{page}{snippet}")
+    }
+}
+
+/// The skip link in the layout targets `#content`; layouts that bring no
+/// `<main>` of their own get one around the content so it still resolves.
+fn content_landmark(layout: &str, content: &str) -> String {
+    if layout.contains("<main") {
+        content.to_string()
+    } else {
+        format!("<main id=\"content\">{content}</main>")
+    }
+}
+
 fn inject_dev_stylesheet_version(page: String) -> String {
     let stylesheet_path =
         StdPath::new(env!("CARGO_MANIFEST_DIR")).join("content/static/tailwind.css");
@@ -161,18 +395,27 @@ fn inject_dev_stylesheet_version(page: String) -> String {
         .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
         .map(|duration| duration.as_millis().to_string())
         .unwrap_or_else(|| "dev".to_string());
-    let needle = format!("href=\"{DEV_STYLESHEET_HREF}\"");
-    if !page.contains(&needle) {
+    // The href may already carry the load-time asset version; the
+    // stylesheet is rebuilt without a restart in development, so its mtime
+    // wins.
+    let needle = format!("href=\"{DEV_STYLESHEET_HREF}");
+    let Some(start) = page.find(&needle) else {
         warn!(
             stylesheet = DEV_STYLESHEET_HREF,
             stylesheet_path = %stylesheet_path.display(),
             "development stylesheet href not found for cache busting"
         );
         return page;
-    }
+    };
+    let value_start = start + "href=\"".len();
+    let Some(value_len) = page[value_start..].find('"') else {
+        return page;
+    };
 
-    let versioned_href = format!("href=\"{DEV_STYLESHEET_HREF}?v={version}\"");
-    page.replace(&needle, &versioned_href)
+    let versioned_href = format!("{DEV_STYLESHEET_HREF}?v={version}");
+    let mut page = page;
+    page.replace_range(value_start..value_start + value_len, &versioned_href);
+    page
 }
 
 fn inject_hot_reload_script(page: String) -> String {
@@ -191,23 +434,220 @@ fn inject_hot_reload_script(page: String) -> String {
     }
 }
 
+/// Fills the script template: the devloop events URL, and the tab title to
+/// flash after a reload (`HOT_RELOAD_FLASH_TITLE`, off when unset).
 fn render_hot_reload_script() -> String {
-    let events_url = std::env::var("DEVLOOP_BROWSER_EVENTS_URL").ok();
-    let replacement = events_url
+    HOT_RELOAD_SCRIPT
+        .replace(
+            HOT_RELOAD_EVENTS_URL_PLACEHOLDER,
+            &js_string_or_null("DEVLOOP_BROWSER_EVENTS_URL"),
+        )
+        .replace(
+            HOT_RELOAD_FLASH_TITLE_PLACEHOLDER,
+            &js_string_or_null("HOT_RELOAD_FLASH_TITLE"),
+        )
+}
+
+/// The env value as a JS string literal, or `null` when unset or blank.
+fn js_string_or_null(key: &str) -> String {
+    std::env::var(key)
+        .ok()
         .filter(|value| !value.trim().is_empty())
         .map(|value| serde_json::to_string(&value).unwrap_or_else(|_| "null".to_string()))
-        .unwrap_or_else(|| "null".to_string());
-    HOT_RELOAD_SCRIPT.replace(HOT_RELOAD_EVENTS_URL_PLACEHOLDER, &replacement)
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Query parameters shared by the page handlers.
+#[derive(Deserialize, Default)]
+struct PageQuery {
+    partial: Option<String>,
+}
+
+/// `?sort=date|title|reading_time&order=asc|desc` on `/posts`.
+#[derive(Deserialize, Default)]
+struct PostIndexQuery {
+    sort: Option<String>,
+    order: Option<String>,
+}
+
+/// `?count=N` on `/partials/latest`.
+#[derive(Deserialize, Default)]
+struct LatestQuery {
+    count: Option<String>,
+}
+
+impl LatestQuery {
+    /// Defaults to the homepage list length; clamped to
+    /// `1..=LATEST_PARTIAL_MAX`.
+    fn count(&self) -> usize {
+        self.count
+            .as_deref()
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(HOME_LIST_LEN)
+            .clamp(1, LATEST_PARTIAL_MAX)
+    }
+}
+
+/// `?page=N` on `/feed.xml`, counted from 1.
+#[derive(Deserialize, Default)]
+struct FeedQuery {
+    page: Option<String>,
+}
+
+impl FeedQuery {
+    /// `None` for anything that is not a page number.
+    fn page(&self) -> Option<usize> {
+        match self.page.as_deref() {
+            None => Some(1),
+            Some(page) => page.trim().parse().ok().filter(|page| *page > 0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PostSort {
+    Date,
+    Title,
+    ReadingTime,
+}
+
+impl PostIndexQuery {
+    /// The requested field and whether to reverse it; anything unrecognised
+    /// falls back to newest first.
+    fn sort(&self) -> (PostSort, bool) {
+        let sort = match self.sort.as_deref().map(str::trim) {
+            Some("title") => PostSort::Title,
+            Some("reading_time") => PostSort::ReadingTime,
+            _ => PostSort::Date,
+        };
+        let descending = self.order.as_deref().map(str::trim) != Some("asc");
+        (sort, descending)
+    }
+}
+
+/// Client-side navigation asks for just the rendered content block, via
+/// `?partial=1` or an `X-Requested-With` header, so it can swap
+/// `{{ content }}` in place without reloading the layout.
+fn wants_partial(query: &PageQuery, headers: &HeaderMap) -> bool {
+    let partial_query = query
+        .partial
+        .as_deref()
+        .is_some_and(|value| matches!(value, "1" | "true"));
+    partial_query || headers.contains_key("x-requested-with")
+}
+
+fn partial_response(body: String) -> Response {
+    // The same URL answers with and without the layout.
+    ([(header::VARY, "X-Requested-With")], Html(body)).into_response()
 }
 
-async fn homepage(State(state): State<Arc<AppState>>) -> Html<String> {
+async fn homepage(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PageQuery>,
+    consent: AnalyticsConsent,
+    headers: HeaderMap,
+) -> Response {
+    let home_mode = state.site_config.read().await.home_mode;
+    match home_mode {
+        HomeMode::Page => {}
+        HomeMode::Latest => {
+            // With nothing listed yet, fall through to `home.md`.
+            if let Some(post) = recent_posts(&state, 1).await.pop() {
+                if wants_partial(&query, &headers) {
+                    return partial_response(render_markdown_body(&state, &post, true).0);
+                }
+                let path = state.site_config.read().await.post_urls.path(&post);
+                let page =
+                    render_markdown_page(&state, &post, &path, PageType::Post, consent).await;
+                return Html(page).into_response();
+            }
+        }
+        HomeMode::List => {
+            let posts = recent_posts(&state, HOME_LIST_LEN).await;
+            let body = render_recent_posts(&posts, &*state.site_config.read().await);
+            if wants_partial(&query, &headers) {
+                return partial_response(body);
+            }
+            let home = state.home_post.read().await.clone();
+            let page =
+                render_post_layout(&state, &home, "/", PageType::Home, &body, "", consent).await;
+            return Html(page).into_response();
+        }
+    }
+
     let home = state.home_post.read().await.clone();
-    let page = render_markdown_page(&state, &home, "/", false).await;
-    Html(page)
+    if wants_partial(&query, &headers) {
+        return partial_response(render_markdown_body(&state, &home, false).0);
+    }
+    let page = render_markdown_page(&state, &home, "/", PageType::Home, consent).await;
+    Html(page).into_response()
+}
+
+/// Listed posts, newest first, at most `limit` of them.
+async fn recent_posts(state: &AppState, limit: usize) -> Vec<Post> {
+    // The loader already keeps posts newest first, undated last, with
+    // `posts/order.txt` applied on top.
+    let posts = state.posts.read().await;
+    posts
+        .iter()
+        .filter(|post| state.is_listed(post))
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+/// Body of the `HOME_MODE=list` homepage: each post's title, date and
+/// excerpt.
+fn render_recent_posts(posts: &[Post], site_config: &SiteConfig) -> String {
+    if posts.is_empty() {
+        return format!(
+            "<p class=\"home-recent-empty\">{}</p>",
+            escape_html(&site_config.no_posts_text)
+        );
+    }
+    let items: String = posts
+        .iter()
+        .map(|post| {
+            format!(
+                "<li><a href=\"{}\" class=\"home-recent-post-link\">{}</a> <span class=\"home-recent-post-date\">{}</span><p class=\"home-recent-post-excerpt\">{}</p></li>",
+                site_config.post_urls.path(post),
+                escape_html(&post.title),
+                escape_html(&post.date),
+                escape_html(&post_excerpt(post))
+            )
+        })
+        .collect();
+    format!("<ul class=\"home-recent-posts\">{items}</ul>")
+}
+
+/// The newest posts as a bare HTML fragment for embedding elsewhere,
+/// rendered like the `HOME_MODE=list` homepage.
+async fn render_latest_partial(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LatestQuery>,
+) -> Response {
+    let posts = recent_posts(&state, query.count()).await;
+    Html(render_recent_posts(
+        &posts,
+        &*state.site_config.read().await,
+    ))
+    .into_response()
+}
+
+async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+async fn readyz(State(state): State<Arc<AppState>>) -> StatusCode {
+    if state.ready.load(Ordering::Acquire) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
 }
 
 async fn get_current_path(State(state): State<Arc<AppState>>) -> Response {
-    if !state.is_development {
+    if !state.is_development() {
         return StatusCode::NOT_FOUND.into_response();
     }
 
@@ -215,10 +655,21 @@ async fn get_current_path(State(state): State<Arc<AppState>>) -> Response {
     (StatusCode::OK, path).into_response()
 }
 
-async fn set_current_path(State(state): State<Arc<AppState>>, body: Bytes) -> StatusCode {
-    if !state.is_development {
+async fn set_current_path(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !state.is_development() {
         return StatusCode::NOT_FOUND;
     }
+    if !dev_origin_allowed(&headers, &state.dev_allowed_origins) {
+        warn!(
+            origin = ?headers.get(header::ORIGIN),
+            "rejected dev channel request from disallowed origin"
+        );
+        return StatusCode::FORBIDDEN;
+    }
 
     let Ok(raw) = std::str::from_utf8(&body) else {
         return StatusCode::BAD_REQUEST;
@@ -240,38 +691,218 @@ async fn set_current_path(State(state): State<Arc<AppState>>, body: Bytes) -> St
     StatusCode::NO_CONTENT
 }
 
-async fn render_post(Path(slug): Path<String>, State(state): State<Arc<AppState>>) -> Response {
+/// A post on its own for printing or saving as PDF: `print.html` when the
+/// content has one, else the normal layout with the `print` body class and
+/// no banner, navigation or post list.
+async fn render_print(
+    Path(slug): Path<String>,
+    State(state): State<Arc<AppState>>,
+    consent: AnalyticsConsent,
+) -> Response {
     if !is_valid_post_slug(&slug) {
-        return render_not_found_response(&state, &slug).await;
+        return render_not_found_response(&state, &slug, consent).await;
     }
+    let post = match reachable_post(&state, &slug, consent).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    let path = state.site_config.read().await.post_urls.path(&post);
+    Html(render_markdown_page(&state, &post, &path, PageType::Print, consent).await).into_response()
+}
 
-    let maybe_post = {
-        let posts = state.posts.read().await;
-        posts.iter().find(|post| post.slug == slug).cloned()
+/// A 301 to `path` that carries the request's query along, so `?partial=1`
+/// and campaign parameters survive the hop.
+fn redirect_keeping_query(path: String, uri: &Uri) -> Response {
+    let location = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
     };
-    let post = match maybe_post {
-        Some(post) => post,
-        None => return render_not_found_response(&state, &slug).await,
+    (
+        StatusCode::MOVED_PERMANENTLY,
+        [(header::LOCATION, location)],
+    )
+        .into_response()
+}
+
+/// Serves both `/posts/{slug}` and the `POST_URL_PATTERN` route. Any path
+/// other than the post's configured one redirects there, so old
+/// `/posts/...` links and mistyped dates keep working.
+async fn render_post(
+    Path(mut params): Path<HashMap<String, String>>,
+    OriginalUri(uri): OriginalUri,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PageQuery>,
+    consent: AnalyticsConsent,
+    headers: HeaderMap,
+) -> Response {
+    let slug = params.remove("slug").unwrap_or_default();
+    // Axum cannot route a `.txt` suffix on its own, so it arrives as part
+    // of the slug.
+    if let Some(slug) = slug.strip_suffix(".txt") {
+        return render_post_text(&state, slug, consent).await;
+    }
+    if !is_valid_post_slug(&slug) {
+        if let Some(canonical) = canonical_slug(&state, &slug).await {
+            let location = match find_post(&state, &canonical).await {
+                Some(post) => state.site_config.read().await.post_urls.path(&post),
+                None => post_path(&canonical),
+            };
+            return redirect_keeping_query(location, &uri);
+        }
+        return render_not_found_response(&state, &slug, consent).await;
+    }
+
+    let post = match reachable_post(&state, &slug, consent).await {
+        Ok(post) => post,
+        Err(response) => return response,
     };
 
-    let page = render_markdown_page(&state, &post, &format!("/posts/{}", post.slug), true).await;
-    Html(page).into_response()
+    let path = state.site_config.read().await.post_urls.path(&post);
+    if !uri.path().eq_ignore_ascii_case(&path) {
+        return redirect_keeping_query(path, &uri);
+    }
+
+    let partial = wants_partial(&query, &headers);
+    let body = if partial {
+        render_markdown_body(&state, &post, true).0
+    } else {
+        render_markdown_page(&state, &post, &path, PageType::Post, consent).await
+    };
+    let mut validators = HeaderMap::new();
+    let etag = weak_etag(&body);
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        validators.insert(header::ETAG, value);
+    }
+    if let Some(value) = post
+        .modified
+        .and_then(|modified| HeaderValue::from_str(&http_date(modified)).ok())
+    {
+        validators.insert(header::LAST_MODIFIED, value);
+    }
+    if etag_matches(&headers, &etag) {
+        if partial {
+            validators.insert(header::VARY, HeaderValue::from_static("X-Requested-With"));
+        }
+        return (StatusCode::NOT_MODIFIED, validators).into_response();
+    }
+    let response = if partial {
+        partial_response(body)
+    } else {
+        Html(body).into_response()
+    };
+    (validators, response).into_response()
+}
+
+/// Weak validator for a rendered page: a 64-bit FNV-1a hash of the
+/// bytes. The hash is fixed by its specification, unlike `DefaultHasher`,
+/// so a tag stays valid across restarts and Rust upgrades.
+fn weak_etag(body: &str) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+    let hash = body.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("W/\"{hash:016x}\"")
+}
+
+/// Weak comparison against every tag in `If-None-Match`, as RFC 9110
+/// requires for `GET`.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+fn http_date(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// `/posts/{slug}.txt`: title, date and the prose as plain text, for
+/// minimalist readers.
+async fn render_post_text(
+    state: &Arc<AppState>,
+    slug: &str,
+    consent: AnalyticsConsent,
+) -> Response {
+    if !is_valid_post_slug(slug) {
+        return render_not_found_response(state, slug, consent).await;
+    }
+    let post = match reachable_post(state, slug, consent).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        format!(
+            "{}\n{}\n\n{}\n",
+            post.title,
+            post.date,
+            plain_text(&post.markdown_body)
+        ),
+    )
+        .into_response()
+}
+
+/// The post at `slug` if it may be shown, else the `410` or `404` page.
+async fn reachable_post(
+    state: &Arc<AppState>,
+    slug: &str,
+    consent: AnalyticsConsent,
+) -> Result<Post, Response> {
+    match find_post(state, slug).await {
+        Some(post) if state.is_reachable(&post) => Ok(post),
+        _ if state.gone_slugs.read().await.contains(slug) => {
+            Err(render_gone_response(state, slug, consent).await)
+        }
+        _ => Err(render_not_found_response(state, slug, consent).await),
+    }
+}
+
+async fn find_post(state: &AppState, slug: &str) -> Option<Post> {
+    let posts = state.posts.read().await;
+    posts.iter().find(|post| post.slug == slug).cloned()
+}
+
+/// With `CASE_INSENSITIVE_SLUGS` on, maps a mixed-case request such as
+/// `My-Post` onto the loaded slug it differs from only by case.
+async fn canonical_slug(state: &AppState, slug: &str) -> Option<String> {
+    if !state.case_insensitive_slugs {
+        return None;
+    }
+    let lowercase = slug.to_lowercase();
+    if !is_valid_post_slug(&lowercase) {
+        return None;
+    }
+    state.slug_index.read().await.get(&lowercase).cloned()
 }
 
 async fn fallback_not_found(
     OriginalUri(uri): OriginalUri,
     State(state): State<Arc<AppState>>,
+    consent: AnalyticsConsent,
 ) -> Response {
-    render_not_found_response(&state, uri.path()).await
+    render_not_found_response(&state, uri.path(), consent).await
 }
 
-async fn render_markdown_page(
-    state: &Arc<AppState>,
+/// The rendered post body, plus its table of contents when the post opts
+/// in with `toc: true` (empty otherwise).
+fn render_markdown_body(
+    state: &AppState,
     post: &Post,
-    page_path: &str,
     include_post_header: bool,
-) -> String {
-    let html_out = render_markdown_to_html(&post.markdown_body);
+) -> (String, String) {
+    let (html_out, toc) = if include_post_header && post.toc {
+        render_markdown_with_toc(&post.markdown_body, &state.render_options)
+    } else {
+        (
+            render_markdown_to_html(&post.markdown_body, &state.render_options),
+            String::new(),
+        )
+    };
     let body = if include_post_header {
         let role_span = post
             .role
@@ -293,8 +924,26 @@ async fn render_markdown_page(
         } else {
             format!("<div class=\"post-eyebrow\">{role_span}{subtitle_span}</div>")
         };
+        let expired_notice = match post.expires.as_deref() {
+            Some(expires) if state.is_expired(post) => format!(
+                "<p class=\"post-expired-notice\">This post expired on {}.</p>",
+                escape_html(expires)
+            ),
+            _ => String::new(),
+        };
+        let cross_post_notice = post
+            .canonical_source
+            .as_ref()
+            .map(|source| {
+                format!(
+                    "<p class=\"post-cross-post-notice\">Originally published at <a href=\"{}\">{}</a>.</p>",
+                    escape_html(&source.url),
+                    escape_html(&source.name)
+                )
+            })
+            .unwrap_or_default();
         format!(
-            "<header class=\"post-header\">{eyebrow_html}<h1>{title}</h1><p class=\"post-date\">{date}</p></header>{content}",
+            "<header class=\"post-header\">{eyebrow_html}<h1>{title}</h1><p class=\"post-date\">{date}</p></header>{expired_notice}{cross_post_notice}{content}",
             title = escape_html(&post.title),
             date = escape_html(&post.date),
             content = html_out,
@@ -302,7 +951,57 @@ async fn render_markdown_page(
     } else {
         html_out
     };
+    (format!("{}{body}", render_problems(state, post)), toc)
+}
+
+/// Development-only banner listing what went wrong loading `post`, so a
+/// broken edit shows on the page instead of only in the logs.
+fn render_problems(state: &AppState, post: &Post) -> String {
+    if !state.is_development() || post.problems.is_empty() {
+        return String::new();
+    }
+    let items: String = post
+        .problems
+        .iter()
+        .map(|problem| format!("<li>{}</li>", escape_html(problem)))
+        .collect();
+    format!(
+        "<aside class=\"dev-error-banner\" role=\"alert\"><p class=\"dev-error-banner-title\">Problems in {}</p><ul>{items}</ul></aside>",
+        escape_html(&post.source_path.display().to_string())
+    )
+}
+
+async fn render_markdown_page(
+    state: &Arc<AppState>,
+    post: &Post,
+    page_path: &str,
+    page_type: PageType,
+    consent: AnalyticsConsent,
+) -> String {
+    let include_post_header = matches!(page_type, PageType::Post | PageType::Print);
+    let (mut body, toc) = render_markdown_body(state, post, include_post_header);
+
+    let site_config = state.site_config.read().await;
+    if include_post_header {
+        body.push_str(&render_last_updated(&site_config, post));
+    }
+    if page_type == PageType::Post {
+        body.push_str(&render_edit_link(&site_config, post));
+    }
+    drop(site_config);
+    render_post_layout(state, post, page_path, page_type, &body, &toc, consent).await
+}
 
+/// Wraps an already rendered `body` in the layout, with `post`'s metadata.
+async fn render_post_layout(
+    state: &Arc<AppState>,
+    post: &Post,
+    page_path: &str,
+    page_type: PageType,
+    body: &str,
+    toc: &str,
+    consent: AnalyticsConsent,
+) -> String {
     let site_config = state.site_config.read().await;
     let meta = build_post_meta(
         page_path,
@@ -311,53 +1010,615 @@ async fn render_markdown_page(
             title: Some(&post.title),
             date: Some(&post.date),
             subtitle: post.subtitle.as_deref(),
+            description: post.description.as_deref(),
             role: post.role.as_deref(),
             image: post.image.as_deref(),
             markdown_body: &post.markdown_body,
+            keywords: &post.keywords,
+            canonical_url: post
+                .canonical_source
+                .as_ref()
+                .map(|source| source.url.as_str()),
         },
     );
+    let meta = PageMeta {
+        page_type,
+        og_type: post.og_type.clone(),
+        ..meta
+    };
 
-    let layout = state.layout_html.read().await;
+    let comments = if page_type == PageType::Post {
+        render_comments(&site_config, post)
+    } else {
+        String::new()
+    };
+    let reading_time = if matches!(page_type, PageType::Post | PageType::Print) {
+        format!("{} min read", post.reading_minutes)
+    } else {
+        String::new()
+    };
+    let layout = if page_type == PageType::Print {
+        state.print_layout_html.read().await
+    } else {
+        state.layout_html.read().await
+    };
     let banner = state.banner_html.read().await;
+    let banner = if post.show_banner == Some(false) || page_type == PageType::Print {
+        ""
+    } else {
+        banner.as_str()
+    };
     let posts = state.posts.read().await;
 
-    render_with_layout(&layout, &banner, &body, &posts, &meta, state.is_development)
-}
+    // The print view drops the post list along with the rest of the
+    // navigation.
+    let listed = posts
+        .iter()
+        .filter(|post| page_type != PageType::Print && state.is_listed(post));
 
-fn is_valid_post_slug(slug: &str) -> bool {
-    !slug.is_empty()
-        && slug
-            .chars()
-            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    render_with_layout(
+        &layout,
+        banner,
+        PageContent {
+            body,
+            toc,
+            comments: &comments,
+            reading_time: &reading_time,
+        },
+        listed,
+        &site_config,
+        &meta,
+        Audience::new(state.environment, consent),
+    )
 }
 
-fn normalize_browser_path(path: &str) -> Option<String> {
-    let path = path.trim();
-    if path.is_empty() || !path.starts_with('/') || path.starts_with("//") {
-        return None;
+/// "Last updated" footer for `post` when the site enables it.
+fn render_last_updated(site_config: &SiteConfig, post: &Post) -> String {
+    if !site_config.show_last_updated {
+        return String::new();
     }
+    post.updated
+        .as_deref()
+        .map(|updated| {
+            format!(
+                "<footer class=\"post-footer\"><p class=\"post-last-updated\">Last updated: {}</p></footer>",
+                escape_html(updated)
+            )
+        })
+        .unwrap_or_default()
+}
 
-    let path = path.split(['?', '#']).next().unwrap_or(path);
-
-    if path.len() > 1 {
-        Some(path.trim_end_matches('/').to_string())
-    } else {
-        Some("/".to_string())
+/// "Edit this page" link to the post's source file under `EDIT_BASE_URL`,
+/// or nothing when no base URL is configured.
+fn render_edit_link(site_config: &SiteConfig, post: &Post) -> String {
+    let Some(base) = site_config.edit_base_url.as_deref() else {
+        return String::new();
+    };
+    if post.content_path.as_os_str().is_empty() {
+        return String::new();
     }
+    let path: String = post
+        .content_path
+        .iter()
+        .map(|component| encoded_path("/", &component.to_string_lossy()))
+        .collect();
+    format!(
+        "<p class=\"post-edit-link\"><a href=\"{}{path}\">Edit this page</a></p>",
+        escape_html(base)
+    )
 }
 
-async fn render_not_found_response(state: &Arc<AppState>, slug: &str) -> Response {
-    let not_found_markdown = state.not_found_markdown.read().await;
-    let body = render_markdown_to_html(&not_found_markdown.replace("{{slug}}", slug));
-
-    let site_config = state.site_config.read().await;
-    let layout = state.layout_html.read().await;
-    let banner = state.banner_html.read().await;
+/// The site's comments embed for `post`, or nothing when comments are off
+/// for it or no embed is configured.
+fn render_comments(site_config: &SiteConfig, post: &Post) -> String {
+    if !post.comments.unwrap_or(site_config.comments) {
+        return String::new();
+    }
+    site_config
+        .comments_embed
+        .as_deref()
+        .map(|embed| {
+            format!(
+                "<section class=\"post-comments\">{}</section>",
+                embed.replace("{{slug}}", &post.slug)
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Lowercase ASCII letters, digits and `-`, plus non-ASCII letters and
+/// digits that are not uppercase, so accented and other non-Latin slugs
+/// (kept on purpose, see `INVALID_SLUGS`) route once decoded. Bidi and
+/// zero-width format characters, fullwidth punctuation and emoji are
+/// refused, and so is mixing Latin, Greek and Cyrillic letters in one
+/// slug, as in `pаypal` with a Cyrillic `а`. A slug written wholly in a
+/// lookalike script still passes.
+fn is_valid_post_slug(slug: &str) -> bool {
+    let mut scripts = slug.chars().filter_map(confusable_script);
+    let first_script = scripts.next();
+    !slug.is_empty()
+        && slug.chars().all(|c| {
+            if c.is_ascii() {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'
+            } else {
+                c.is_alphanumeric() && !c.is_uppercase()
+            }
+        })
+        && scripts.all(|script| Some(script) == first_script)
+}
+
+/// The script of a Latin, Greek or Cyrillic letter: the three whose
+/// letters are most often mistaken for one another.
+fn confusable_script(c: char) -> Option<&'static str> {
+    match c {
+        'a'..='z' | '\u{c0}'..='\u{24f}' | '\u{1e00}'..='\u{1eff}' => Some("latin"),
+        '\u{370}'..='\u{3ff}' | '\u{1f00}'..='\u{1fff}' => Some("greek"),
+        '\u{400}'..='\u{52f}' => Some("cyrillic"),
+        _ => None,
+    }
+}
+
+fn normalize_browser_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    if path.is_empty() || !path.starts_with('/') || path.starts_with("//") {
+        return None;
+    }
+
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+
+    if path.len() > 1 {
+        Some(path.trim_end_matches('/').to_string())
+    } else {
+        Some("/".to_string())
+    }
+}
+
+async fn render_not_found_response(
+    state: &Arc<AppState>,
+    slug: &str,
+    consent: AnalyticsConsent,
+) -> Response {
+    let not_found_markdown = state.not_found_markdown.read().await.clone();
+    let meta = default_not_found_meta(slug, &*state.site_config.read().await);
+    render_status_page(
+        state,
+        StatusCode::NOT_FOUND,
+        &not_found_markdown,
+        slug,
+        meta,
+        consent,
+    )
+    .await
+}
+
+async fn render_gone_response(
+    state: &Arc<AppState>,
+    slug: &str,
+    consent: AnalyticsConsent,
+) -> Response {
+    let meta = default_gone_meta(slug, &*state.site_config.read().await);
+    render_status_page(state, StatusCode::GONE, GONE_MARKDOWN, slug, meta, consent).await
+}
+
+/// Stands in front of every route while `AppState::maintenance` is set.
+/// Health checks stay green so the platform keeps the instance, and static
+/// assets keep the maintenance page styled.
+async fn maintenance_gate(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let exempt = matches!(
+        path,
+        "/healthz" | "/readyz" | "/favicon.ico" | "/favicon.png"
+    ) || path.starts_with("/static/");
+    if !state.maintenance || exempt {
+        return next.run(request).await;
+    }
+
+    let consent = AnalyticsConsent(has_analytics_consent(request.headers()));
+    let meta = default_maintenance_meta(path, &*state.site_config.read().await);
+    render_status_page(
+        &state,
+        StatusCode::SERVICE_UNAVAILABLE,
+        MAINTENANCE_MARKDOWN,
+        "",
+        meta,
+        consent,
+    )
+    .await
+}
+
+/// Cookie the consent banner sets once the visitor accepts analytics.
+const ANALYTICS_CONSENT_COOKIE: &str = "analytics_consent=yes";
+
+fn has_analytics_consent(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .any(|pair| pair.trim() == ANALYTICS_CONSENT_COOKIE)
+}
+
+/// Whether the request carries the consent cookie.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct AnalyticsConsent(bool);
+
+impl<S: Send + Sync> FromRequestParts<S> for AnalyticsConsent {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(has_analytics_consent(&parts.headers)))
+    }
+}
+
+/// Full pages differ by the consent cookie while a consent banner is
+/// configured, so caches must key on it.
+async fn vary_on_consent(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let gated = {
+        let site_config = state.site_config.read().await;
+        state.environment == Environment::Production
+            && site_config.analytics_snippet.is_some()
+            && site_config.consent_banner.is_some()
+    };
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if gated && is_html {
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("Cookie"));
+    }
+    response
+}
+
+async fn render_status_page(
+    state: &Arc<AppState>,
+    status: StatusCode,
+    markdown: &str,
+    slug: &str,
+    meta: PageMeta,
+    consent: AnalyticsConsent,
+) -> Response {
+    let body = render_markdown_to_html(&markdown.replace("{{slug}}", slug), &state.render_options);
+    (
+        status,
+        Html(render_standalone_page(state, &body, &meta, consent).await),
+    )
+        .into_response()
+}
+
+/// Wraps `body` in the layout for pages that are not a post: no comments,
+/// no table of contents.
+async fn render_standalone_page(
+    state: &Arc<AppState>,
+    body: &str,
+    meta: &PageMeta,
+    consent: AnalyticsConsent,
+) -> String {
+    let site_config = state.site_config.read().await;
+    let layout = state.layout_html.read().await;
+    let banner = state.banner_html.read().await;
+    let posts = state.posts.read().await;
+    let listed = posts.iter().filter(|post| state.is_listed(post));
+    render_with_layout(
+        &layout,
+        &banner,
+        body,
+        listed,
+        &site_config,
+        meta,
+        Audience::new(state.environment, consent),
+    )
+}
+
+/// RSS 2.0 feed of every listed post in listing order (newest first). The
+/// newest post date doubles as the build date and `Last-Modified`. Each
+/// page links itself, and with `feed_page_size` set its neighbours too, as
+/// RFC 5005 describes.
+async fn render_feed(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FeedQuery>,
+) -> Response {
+    let site_config = state.site_config.read().await;
+    let posts = state.posts.read().await;
+    let listed: Vec<&Post> = posts.iter().filter(|post| state.is_listed(post)).collect();
+    let updated = listed
+        .iter()
+        .filter_map(|post| post.published_on())
+        .max()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc());
+
+    let page_size = site_config
+        .feed_page_size
+        .filter(|size| *size > 0)
+        .unwrap_or(listed.len().max(1));
+    let pages = listed.len().div_ceil(page_size).max(1);
+    let Some(page) = query.page().filter(|page| *page <= pages) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut page_links = feed_page_link(page, "self");
+    if page > 1 {
+        page_links.push_str(&feed_page_link(page - 1, "previous"));
+    }
+    if page < pages {
+        page_links.push_str(&feed_page_link(page + 1, "next"));
+    }
+
+    let items: String = listed
+        .iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .map(|post| {
+            let link = escape_html(&page_url(&site_config.post_urls.path(post)));
+            let pub_date = post
+                .published_on()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|datetime| {
+                    format!("<pubDate>{}</pubDate>", datetime.and_utc().to_rfc2822())
+                })
+                .unwrap_or_default();
+            format!(
+                "<item><title>{}</title><link>{link}</link><guid>{link}</guid>{pub_date}<description>{}</description></item>",
+                escape_html(&post.title),
+                escape_html(&post_excerpt(post))
+            )
+        })
+        .collect();
+    let last_build_date = updated
+        .map(|updated| format!("<lastBuildDate>{}</lastBuildDate>", updated.to_rfc2822()))
+        .unwrap_or_default();
+    let feed = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\"><channel><title>{}</title><link>{}</link><description>{}</description>{last_build_date}{page_links}{items}</channel></rss>\n",
+        escape_html(&site_config.title),
+        escape_html(&page_url("/")),
+        escape_html(&site_config.description)
+    );
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/rss+xml"),
+    );
+    if let Some(value) = updated.and_then(|updated| HeaderValue::from_str(&http_date(updated)).ok())
+    {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+    (headers, feed).into_response()
+}
+
+/// Absolute URL of one `/feed.xml` page; the first has no query.
+fn feed_page_url(page: usize) -> String {
+    if page == 1 {
+        page_url("/feed.xml")
+    } else {
+        page_url(&format!("/feed.xml?page={page}"))
+    }
+}
+
+fn feed_page_link(page: usize, rel: &str) -> String {
+    format!(
+        "<atom:link href=\"{}\" rel=\"{rel}\" type=\"application/rss+xml\"/>",
+        escape_html(&feed_page_url(page))
+    )
+}
+
+/// `<urlset>` of the homepage and every listed post, for search engines.
+async fn render_sitemap(State(state): State<Arc<AppState>>) -> Response {
+    let site_config = state.site_config.read().await;
     let posts = state.posts.read().await;
-    let meta = default_not_found_meta(slug, &site_config);
-    let page = render_with_layout(&layout, &banner, &body, &posts, &meta, state.is_development);
+    let urls: String = posts
+        .iter()
+        .filter(|post| state.is_listed(post))
+        .map(|post| {
+            let lastmod = post
+                .published_on()
+                .map(|date| format!("<lastmod>{}</lastmod>", date.format("%Y-%m-%d")))
+                .unwrap_or_default();
+            format!(
+                "<url><loc>{}</loc>{lastmod}</url>",
+                escape_html(&page_url(&site_config.post_urls.path(post)))
+            )
+        })
+        .collect();
+    let sitemap = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\"><url><loc>{}</loc></url>{urls}</urlset>\n",
+        escape_html(&page_url("/"))
+    );
+    ([(header::CONTENT_TYPE, "application/xml")], sitemap).into_response()
+}
+
+/// Every listed post, ordered by the query with a stable sort so equal keys
+/// keep the loaded order.
+async fn render_post_index(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PostIndexQuery>,
+    consent: AnalyticsConsent,
+) -> Response {
+    let (sort, descending) = query.sort();
+    let mut posts: Vec<(Post, usize)> = state
+        .posts
+        .read()
+        .await
+        .iter()
+        .filter(|post| state.is_listed(post))
+        .map(|post| (post.clone(), post.reading_minutes))
+        .collect();
+    posts.sort_by(|(a, a_minutes), (b, b_minutes)| {
+        let ordering = match sort {
+            PostSort::Date => match (a.published_on(), b.published_on()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                // Undated posts stay last whichever way the list runs.
+                (a, b) => return a.is_none().cmp(&b.is_none()),
+            },
+            PostSort::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            PostSort::ReadingTime => a_minutes.cmp(b_minutes),
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let site_config = state.site_config.read().await;
+    let items: String = posts
+        .iter()
+        .map(|(post, minutes)| {
+            format!(
+                "<li><a href=\"{}\" class=\"post-index-link\">{}</a> <span class=\"post-index-meta\">{} · {minutes} min read</span></li>",
+                site_config.post_urls.path(post),
+                escape_html(&post.title),
+                escape_html(&post.date)
+            )
+        })
+        .collect();
+    let body =
+        format!("<h1 class=\"post-index-title\">Posts</h1><ul class=\"post-index\">{items}</ul>");
+    let meta = post_index_meta(&site_config);
+    drop(site_config);
+    Html(render_standalone_page(&state, &body, &meta, consent).await).into_response()
+}
+
+/// Index of the listed posts filed under one category, addressed by the
+/// category's slug; `uncategorized` collects posts without one.
+async fn render_category(
+    Path(category): Path<String>,
+    State(state): State<Arc<AppState>>,
+    consent: AnalyticsConsent,
+) -> Response {
+    let group = {
+        let category_index = state.category_index.read().await;
+        let posts = state.posts.read().await;
+        let post_urls = state.site_config.read().await.post_urls.clone();
+        category_index.get(&category).and_then(|entry| {
+            let items: String = entry
+                .posts
+                .iter()
+                .filter_map(|&position| posts.get(position))
+                .filter(|post| state.is_listed(post))
+                .map(|post| {
+                    format!(
+                        "<li><a href=\"{}\" class=\"category-post-link\">{}</a> <span class=\"category-post-date\">{}</span></li>",
+                        post_urls.path(post),
+                        escape_html(&post.title),
+                        escape_html(&post.date)
+                    )
+                })
+                .collect();
+            (!items.is_empty()).then(|| (entry.name.clone(), items))
+        })
+    };
+    let Some((name, items)) = group else {
+        return render_not_found_response(&state, &category, consent).await;
+    };
+
+    let body = format!(
+        "<h1 class=\"category-title\">{}</h1><ul class=\"category-posts\">{items}</ul>",
+        escape_html(&name)
+    );
+    let meta = category_meta(
+        &name,
+        &category_path(&category),
+        &*state.site_config.read().await,
+    );
+    Html(render_standalone_page(&state, &body, &meta, consent).await).into_response()
+}
+
+/// Listed posts carrying one tag, addressed by the tag's slug.
+async fn render_tag(
+    Path(tag): Path<String>,
+    State(state): State<Arc<AppState>>,
+    consent: AnalyticsConsent,
+) -> Response {
+    let group = {
+        let tag_index = state.tag_index.read().await;
+        let posts = state.posts.read().await;
+        let post_urls = state.site_config.read().await.post_urls.clone();
+        tag_index.get(&tag).and_then(|entry| {
+            let items: String = entry
+                .posts
+                .iter()
+                .filter_map(|&position| posts.get(position))
+                .filter(|post| state.is_listed(post))
+                .map(|post| {
+                    format!(
+                        "<li><a href=\"{}\" class=\"tag-post-link\">{}</a> <span class=\"tag-post-date\">{}</span></li>",
+                        post_urls.path(post),
+                        escape_html(&post.title),
+                        escape_html(&post.date)
+                    )
+                })
+                .collect();
+            (!items.is_empty()).then(|| (entry.name.clone(), items))
+        })
+    };
+    let Some((name, items)) = group else {
+        return render_not_found_response(&state, &tag, consent).await;
+    };
+
+    let body = format!(
+        "<h1 class=\"tag-title\">{}</h1><ul class=\"tag-posts\">{items}</ul>",
+        escape_html(&name)
+    );
+    let meta = tag_meta(&name, &tag_path(&tag), &*state.site_config.read().await);
+    Html(render_standalone_page(&state, &body, &meta, consent).await).into_response()
+}
+
+/// Every tag on a listed post with its post count, in `tag_order`.
+async fn render_tag_index(
+    State(state): State<Arc<AppState>>,
+    consent: AnalyticsConsent,
+) -> Response {
+    let items: String = {
+        let tag_index = state.tag_index.read().await;
+        let posts = state.posts.read().await;
+        let tag_order = state.site_config.read().await.tag_order;
+        let mut tags: Vec<(&str, &str, usize)> = tag_index
+            .iter()
+            .map(|(slug, entry)| {
+                let listed = entry
+                    .posts
+                    .iter()
+                    .filter_map(|&position| posts.get(position))
+                    .filter(|post| state.is_listed(post))
+                    .count();
+                (slug.as_str(), entry.name.as_str(), listed)
+            })
+            .filter(|(_, _, listed)| *listed > 0)
+            .collect();
+        tags.sort_by_key(|(slug, _, _)| *slug);
+        sort_groups(
+            &mut tags,
+            tag_order,
+            |(_, name, _)| name.to_string(),
+            |(_, _, listed)| *listed,
+        );
+        tags.into_iter()
+            .map(|(slug, name, listed)| {
+                format!(
+                    "<li><a href=\"{}\" class=\"tag-index-link\">{}</a> <span class=\"tag-index-count\">{listed}</span></li>",
+                    tag_path(slug),
+                    escape_html(name)
+                )
+            })
+            .collect()
+    };
 
-    (StatusCode::NOT_FOUND, Html(page)).into_response()
+    let body =
+        format!("<h1 class=\"tag-index-title\">Tags</h1><ul class=\"tag-index\">{items}</ul>");
+    let meta = tag_index_meta(&*state.site_config.read().await);
+    Html(render_standalone_page(&state, &body, &meta, consent).await).into_response()
 }
 
 fn setup_logging() {
@@ -371,11 +1632,293 @@ fn default_rust_log() -> String {
     std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string())
 }
 
+fn static_dirs() -> (PathBuf, Option<PathBuf>) {
+    ContentRoots::from_env().static_dirs()
+}
+
+fn case_insensitive_slugs() -> bool {
+    std::env::var("CASE_INSENSITIVE_SLUGS")
+        .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+fn maintenance_from_env() -> bool {
+    std::env::var("MAINTENANCE")
+        .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+fn force_https() -> bool {
+    std::env::var("FORCE_HTTPS")
+        .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+fn request_timeout() -> Duration {
+    let secs = std::env::var("REQUEST_TIMEOUT_SECS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Reads `REQUEST_ID_HEADER`, defaulting to `X-Request-Id` (with a warning
+/// when the value is not a valid header name).
+fn request_id_header() -> HeaderName {
+    let default = HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER);
+    let Ok(raw) = std::env::var("REQUEST_ID_HEADER") else {
+        return default;
+    };
+    HeaderName::from_bytes(raw.trim().as_bytes()).unwrap_or_else(|_| {
+        warn!(header = %raw, "ignoring invalid REQUEST_ID_HEADER");
+        default
+    })
+}
+
+fn dev_allowed_origins() -> Vec<String> {
+    parse_allowed_origins(std::env::var("DEV_ALLOWED_ORIGINS").ok().as_deref())
+}
+
+fn parse_allowed_origins(raw: Option<&str>) -> Vec<String> {
+    let configured: Vec<String> = raw
+        .unwrap_or_default()
+        .split(',')
+        .map(|entry| entry.trim().trim_end_matches('/').to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if configured.is_empty() {
+        DEFAULT_DEV_ALLOWED_ORIGINS
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    } else {
+        configured
+    }
+}
+
+/// Guards the dev channel against cross-site requests. Requests without an
+/// `Origin` (curl, devloop hooks) and same-origin requests pass; anything
+/// else must match an allowlist entry, either a full origin such as
+/// `https://example.trycloudflare.com` or a bare host matched on any port.
+fn dev_origin_allowed(headers: &HeaderMap, allowlist: &[String]) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+    let Some((_, authority)) = origin.split_once("://") else {
+        return false;
+    };
+
+    let same_origin = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .is_some_and(|host| host.eq_ignore_ascii_case(authority));
+    if same_origin {
+        return true;
+    }
+
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => authority,
+    };
+    allowlist
+        .iter()
+        .any(|entry| *entry == origin || entry == host)
+}
+
+/// Bounds every route registered so far. Long-lived connections such as
+/// WebSocket upgrades must be added to the router after this layer so the
+/// deadline does not cut them off.
+fn with_request_timeout<S>(router: Router<S>, timeout: Duration) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(TimeoutLayer::with_status_code(
+        StatusCode::SERVICE_UNAVAILABLE,
+        timeout,
+    ))
+}
+
+/// Behind a TLS-terminating proxy the scheme the client used only shows up
+/// in `X-Forwarded-Proto`; requests that arrived over plain `http` are sent
+/// to the same host and path on `https`.
+fn with_https_redirect<S>(router: Router<S>, force_https: bool) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if force_https {
+        router.layer(middleware::from_fn(redirect_forwarded_http))
+    } else {
+        router
+    }
+}
+
+async fn redirect_forwarded_http(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let forwarded_http = headers
+        .get("x-forwarded-proto")
+        .and_then(|proto| proto.to_str().ok())
+        .and_then(|proto| proto.split(',').next())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("http"));
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+
+    match host {
+        Some(host) if forwarded_http => {
+            let path = request
+                .uri()
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or("/");
+            (
+                StatusCode::MOVED_PERMANENTLY,
+                [(header::LOCATION, format!("https://{host}{path}"))],
+            )
+                .into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+/// Outermost layer, so timeouts, panics and redirects carry the ID too.
+fn with_request_id<S>(router: Router<S>, header_name: HeaderName) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(middleware::from_fn_with_state(
+        header_name,
+        propagate_request_id,
+    ))
+}
+
+/// Keeps the caller's request ID when it is sane, otherwise makes one up,
+/// then logs the request under a span carrying it and echoes it back.
+async fn propagate_request_id(
+    State(header_name): State<HeaderName>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let request_id = request
+        .headers()
+        .get(&header_name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| is_valid_request_id(id))
+        .map(ToString::to_string)
+        .unwrap_or_else(generate_request_id);
+    let value = HeaderValue::from_str(&request_id).expect("request IDs are visible ASCII");
+    request
+        .headers_mut()
+        .insert(header_name.clone(), value.clone());
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let mut response = next.run(request).instrument(span).await;
+    response.headers_mut().insert(header_name, value);
+    response
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// 32 hex digits from std's randomly keyed hasher, which is enough to tell
+/// requests apart in the logs without a random number crate.
+fn generate_request_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let half = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", half(), half())
+}
+
+/// `ServeDir` guesses content types from a fixed table; files whose
+/// extension has an override get that type instead.
+async fn override_static_content_type(
+    State(overrides): State<Arc<HashMap<String, String>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let content_type = StdPath::new(request.uri().path())
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| overrides.get(&extension.to_ascii_lowercase()))
+        .and_then(|mime| header::HeaderValue::from_str(mime).ok());
+    let mut response = next.run(request).await;
+    if let Some(content_type) = content_type {
+        if response.status().is_success() {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, content_type);
+        }
+    }
+    response
+}
+
+/// Turns a handler panic into a logged, styled `500` instead of a dropped
+/// connection.
+fn with_panic_page<S>(router: Router<S>, state: Arc<AppState>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(CatchPanicLayer::custom(
+        move |payload: Box<dyn Any + Send + 'static>| render_panic_response(&state, payload),
+    ))
+}
+
+/// Runs synchronously inside the panic handler, so content is read with
+/// `try_read`; if a lock is held the page falls back to the bare message.
+fn render_panic_response(state: &AppState, payload: Box<dyn Any + Send + 'static>) -> Response {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload");
+    error!(panic = message, "request handler panicked");
+
+    let body = render_markdown_to_html(PANIC_MARKDOWN, &state.render_options);
+    let page = match (
+        state.layout_html.try_read(),
+        state.banner_html.try_read(),
+        state.site_config.try_read(),
+        state.posts.try_read(),
+    ) {
+        (Ok(layout), Ok(banner), Ok(site_config), Ok(posts)) => render_with_layout(
+            &layout,
+            &banner,
+            &body,
+            posts.iter().filter(|post| state.is_listed(post)),
+            &site_config,
+            &default_error_meta(&site_config),
+            state.environment,
+        ),
+        _ => body,
+    };
+    (StatusCode::INTERNAL_SERVER_ERROR, Html(page)).into_response()
+}
+
 fn load_devloop_event_client() -> Option<DevloopEventClient> {
     let browser_path_url = std::env::var("DEVLOOP_EVENT_BROWSER_PATH_URL").ok()?;
     let token = std::env::var("DEVLOOP_EVENTS_TOKEN").ok()?;
     Some(DevloopEventClient {
         browser_path_url,
+        reload_url: std::env::var("DEVLOOP_EVENT_RELOAD_URL").ok(),
         token,
         client: reqwest::Client::new(),
     })
@@ -396,77 +1939,351 @@ async fn publish_browser_path_event(
     Ok(())
 }
 
-async fn initialize_state() -> RouterState {
-    let is_development = std::env::var("RUST_ENV")
-        .map(|v| v == "development")
-        .unwrap_or(false);
+/// Posts `event` (`reload` or `reload:<path>`) to devloop's reload event,
+/// when one is configured.
+async fn publish_reload_event(
+    client: &DevloopEventClient,
+    event: &str,
+) -> Result<(), reqwest::Error> {
+    let Some(reload_url) = &client.reload_url else {
+        return Ok(());
+    };
+    client
+        .client
+        .post(reload_url)
+        .bearer_auth(&client.token)
+        .json(&serde_json::json!({ "value": event }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
 
-    info!("RUST_ENV is set to development: {}", is_development);
+/// `POST /__dev/reload`: loads the content directory again without a
+/// restart and tells devloop what changed. A failed load keeps the content
+/// already served.
+async fn reload_content(State(state): State<Arc<AppState>>, headers: HeaderMap) -> StatusCode {
+    if !state.is_development() {
+        return StatusCode::NOT_FOUND;
+    }
+    if !dev_origin_allowed(&headers, &state.dev_allowed_origins) {
+        warn!(
+            origin = ?headers.get(header::ORIGIN),
+            "rejected dev channel request from disallowed origin"
+        );
+        return StatusCode::FORBIDDEN;
+    }
+    match reload_content_from(&state, &ContentRoots::from_env()).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(error) => {
+            error!(
+                "content reload failed, keeping the loaded content: {}",
+                error
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
 
-    let (site_config, banner_html, layout_html, home_post, not_found_markdown, posts) =
-        match load_content().await {
-            Ok(content) => content,
-            Err(e) => {
-                error!("Failed to load initial content files: {}", e);
-                (
-                    SiteConfig::default(),
-                    String::new(),
-                    "<!doctype html><html><body>{{ content }}</body></html>".to_string(),
-                    Post {
-                        title: "Home".to_string(),
-                        slug: "home".to_string(),
-                        date: String::new(),
-                        description: None,
-                        image: None,
-                        role: None,
-                        subtitle: None,
-                        markdown_body: "Content loading failed during startup.".to_string(),
-                    },
-                    "# Post not found\n\nNo post exists for slug `{{slug}}`.".to_string(),
-                    Vec::new(),
-                )
-            }
-        };
+/// Swaps `roots`' content into `state` and publishes the reload event for
+/// the change, which it also returns. `MAINTENANCE` is only read at startup.
+async fn reload_content_from(state: &AppState, roots: &ContentRoots) -> io::Result<String> {
+    let content = load_content_from(roots, load_options(state.environment)).await?;
+    let event = reload_event(state, &content).await;
+    let LoadedContent {
+        site_config,
+        banner_html,
+        layout_html,
+        print_layout_html,
+        home_post,
+        not_found_markdown,
+        posts,
+        slug_index,
+        category_index,
+        tag_index,
+        render_timings,
+        gone_slugs,
+        maintenance: _,
+    } = content;
+    *state.site_config.write().await = site_config;
+    *state.banner_html.write().await = banner_html;
+    *state.layout_html.write().await = layout_html;
+    *state.print_layout_html.write().await = print_layout_html;
+    *state.home_post.write().await = home_post;
+    *state.not_found_markdown.write().await = not_found_markdown;
+    *state.posts.write().await = posts;
+    *state.slug_index.write().await = slug_index;
+    *state.category_index.write().await = category_index;
+    *state.tag_index.write().await = tag_index;
+    *state.render_timings.write().await = render_timings;
+    *state.gone_slugs.write().await = gone_slugs;
 
-    let state = Arc::new(AppState {
-        site_config: RwLock::new(site_config),
-        banner_html: RwLock::new(banner_html),
-        layout_html: RwLock::new(layout_html),
-        home_post: RwLock::new(home_post),
-        current_browser_path: RwLock::new("/".to_string()),
-        devloop_event_client: load_devloop_event_client(),
+    if let Some(client) = &state.devloop_event_client {
+        if let Err(error) = publish_reload_event(client, &event).await {
+            error!("failed to notify devloop reload event: {}", error);
+        }
+    }
+    Ok(event)
+}
+
+/// `reload:<path>` when the only difference from what `state` serves is
+/// one post's body, so a browser showing that post can swap its content in
+/// place; `reload` for anything else.
+async fn reload_event(state: &AppState, content: &LoadedContent) -> String {
+    // `modified`, and `updated` which falls back to it, move on every edit.
+    let without_body = |post: &Post| Post {
+        markdown_body: String::new(),
+        reading_minutes: 0,
+        updated: None,
+        modified: None,
+        ..post.clone()
+    };
+    let posts = state.posts.read().await;
+    let same_frame = *state.layout_html.read().await == content.layout_html
+        && *state.banner_html.read().await == content.banner_html
+        && *state.home_post.read().await == content.home_post
+        && posts.len() == content.posts.len();
+    let mut changed = posts
+        .iter()
+        .zip(&content.posts)
+        .filter(|(old, new)| old != new);
+    match (changed.next(), changed.next()) {
+        (Some((old, new)), None) if same_frame && without_body(old) == without_body(new) => {
+            format!("reload:{}", content.site_config.post_urls.path(new))
+        }
+        _ => "reload".to_string(),
+    }
+}
+
+fn load_options(environment: Environment) -> LoadOptions {
+    LoadOptions {
+        include_drafts: environment.shows_drafts(),
+        max_post_bytes: max_post_bytes_from_env(),
+        render_timing: environment.is_development().then(RenderOptions::from_env),
+        render_threads: render_threads_from_env(),
+        check_math: environment.is_development(),
+        strict: strict_from_env(),
+        invalid_slugs: InvalidSlugs::from_env(),
+    }
+}
+
+/// Falls back to placeholder content when loading fails, except in strict
+/// mode, where the failure is returned so the server does not start.
+async fn initialize_state() -> io::Result<RouterState> {
+    let environment = Environment::from_env();
+    info!(?environment, "resolved RUST_ENV");
+
+    let load_options = load_options(environment);
+    let strict = load_options.strict;
+    let (content, content_loaded) = match load_content(load_options).await {
+        Ok(content) => (content, true),
+        Err(e) if strict => {
+            error!("Refusing to start with content warnings: {}", e);
+            return Err(e);
+        }
+        Err(e) => {
+            error!("Failed to load initial content files: {}", e);
+            let layout_html = "<!doctype html><html><body>{{ content }}</body></html>".to_string();
+            let fallback = LoadedContent {
+                site_config: SiteConfig::default(),
+                banner_html: String::new(),
+                print_layout_html: layout_html.clone(),
+                layout_html,
+                home_post: Post {
+                    title: "Home".to_string(),
+                    slug: "home".to_string(),
+                    date: String::new(),
+                    description: None,
+                    image: None,
+                    role: None,
+                    subtitle: None,
+                    markdown_body: "Content loading failed during startup.".to_string(),
+                    ..Post::default()
+                },
+                not_found_markdown: "# Post not found\n\nNo post exists for slug `{{slug}}`."
+                    .to_string(),
+                posts: Vec::new(),
+                slug_index: HashMap::new(),
+                category_index: HashMap::new(),
+                tag_index: HashMap::new(),
+                render_timings: HashMap::new(),
+                gone_slugs: Default::default(),
+                maintenance: false,
+            };
+            (fallback, false)
+        }
+    };
+
+    let router_state = build_router_state(content, environment, load_devloop_event_client());
+    // Fallback content keeps the site answering, but it is not what the
+    // instance should be serving, so leave it out of rotation.
+    router_state
+        .app_state
+        .ready
+        .store(content_loaded, Ordering::Release);
+    Ok(router_state)
+}
+
+fn build_router_state(
+    content: LoadedContent,
+    environment: Environment,
+    devloop_event_client: Option<DevloopEventClient>,
+) -> RouterState {
+    let LoadedContent {
+        site_config,
+        banner_html,
+        layout_html,
+        print_layout_html,
+        home_post,
+        not_found_markdown,
+        posts,
+        slug_index,
+        category_index,
+        tag_index,
+        render_timings,
+        gone_slugs,
+        maintenance,
+    } = content;
+
+    let state = Arc::new(AppState {
+        site_config: RwLock::new(site_config),
+        banner_html: RwLock::new(banner_html),
+        layout_html: RwLock::new(layout_html),
+        print_layout_html: RwLock::new(print_layout_html),
+        home_post: RwLock::new(home_post),
+        current_browser_path: RwLock::new("/".to_string()),
+        devloop_event_client,
         not_found_markdown: RwLock::new(not_found_markdown),
         posts: RwLock::new(posts),
-        is_development,
+        environment,
+        render_options: RenderOptions::from_env(),
+        ready: AtomicBool::new(false),
+        dev_allowed_origins: dev_allowed_origins(),
+        expired_posts: ExpiredPosts::from_env(),
+        slug_index: RwLock::new(slug_index),
+        category_index: RwLock::new(category_index),
+        tag_index: RwLock::new(tag_index),
+        case_insensitive_slugs: case_insensitive_slugs(),
+        render_timings: RwLock::new(render_timings),
+        gone_slugs: RwLock::new(gone_slugs),
+        maintenance: maintenance || maintenance_from_env(),
     });
 
     state::RouterState { app_state: state }
 }
 
+/// Every path `setup_router` serves besides `/` and a custom post URL
+/// pattern, which `PostUrlPattern::parse` checks against. A trailing
+/// `{*rest}` stands for a nested service.
+pub(crate) const BUILT_IN_ROUTES: &[&str] = &[
+    "/posts",
+    "/feed.xml",
+    "/sitemap.xml",
+    "/partials/latest",
+    "/posts/{slug}",
+    "/posts/{slug}/print",
+    "/categories/{category}",
+    "/tags",
+    "/tags/{tag}",
+    "/api/posts/{slug}",
+    "/api/posts/{slug}/preview",
+    "/api/render",
+    "/api/render-timings",
+    "/search-index.json",
+    "/healthz",
+    "/readyz",
+    "/__dev/current-path",
+    "/__dev/reload",
+    "/favicon.ico",
+    "/favicon.png",
+    "/static/{*rest}",
+];
+
 fn setup_router(router_state: RouterState) -> Router {
-    let static_dir = get_service(ServeDir::new("content/static"));
-    let favicon_ico = get_service(ServeFile::new("content/static/favicon.ico"));
-    let favicon_png = get_service(ServeFile::new("content/static/favicon.png"));
-
-    Router::new()
-        .route("/", get(homepage))
-        .route("/posts/{slug}", get(render_post))
-        .route(
-            "/__dev/current-path",
-            get(get_current_path).post(set_current_path),
-        )
-        .nest_service("/static", static_dir)
-        .route_service("/favicon.ico", favicon_ico)
-        .route_service("/favicon.png", favicon_png)
-        .fallback(fallback_not_found)
-        .with_state(router_state)
+    let (static_root, theme_static_root) = static_dirs();
+    let static_file = |name: &str| match &theme_static_root {
+        Some(theme_root) if !static_root.join(name).exists() => theme_root.join(name),
+        _ => static_root.join(name),
+    };
+    let favicon_ico = get_service(ServeFile::new(static_file("favicon.ico")));
+    let favicon_png = get_service(ServeFile::new(static_file("favicon.png")));
+    let static_dir = match theme_static_root {
+        Some(theme_root) => {
+            get_service(ServeDir::new(&static_root).fallback(ServeDir::new(theme_root)))
+        }
+        None => get_service(ServeDir::new(&static_root)),
+    }
+    .layer(middleware::from_fn_with_state(
+        Arc::new(mime_overrides_from_env()),
+        override_static_content_type,
+    ));
+
+    let is_development = router_state.app_state.is_development();
+    let post_urls = router_state
+        .app_state
+        .site_config
+        .try_read()
+        .map(|site_config| site_config.post_urls.clone())
+        .unwrap_or_default();
+    let router = BUILT_IN_ROUTES
+        .iter()
+        .fold(Router::new().route("/", get(homepage)), |router, &path| {
+            match path {
+                "/posts" => router.route(path, get(render_post_index)),
+                "/feed.xml" => router.route(path, get(render_feed)),
+                "/sitemap.xml" => router.route(path, get(render_sitemap)),
+                "/partials/latest" => router.route(path, get(render_latest_partial)),
+                "/posts/{slug}" => router.route(path, get(render_post)),
+                "/posts/{slug}/print" => router.route(path, get(render_print)),
+                "/categories/{category}" => router.route(path, get(render_category)),
+                "/tags" => router.route(path, get(render_tag_index)),
+                "/tags/{tag}" => router.route(path, get(render_tag)),
+                "/api/posts/{slug}" => router.route(path, get(api::get_post)),
+                "/api/posts/{slug}/preview" => router.route(path, get(api::get_post_preview)),
+                // Not registered at all in production, rather than answering 404.
+                "/api/render" if is_development => router.route(path, post(api::render_preview)),
+                "/api/render" => router,
+                "/api/render-timings" => router.route(path, get(api::get_render_timings)),
+                "/search-index.json" => router.route(path, get(api::get_search_index)),
+                "/healthz" => router.route(path, get(healthz)),
+                "/readyz" => router.route(path, get(readyz)),
+                "/__dev/current-path" => {
+                    router.route(path, get(get_current_path).post(set_current_path))
+                }
+                "/__dev/reload" if is_development => router.route(path, post(reload_content)),
+                "/__dev/reload" => router,
+                "/favicon.ico" => router.route_service(path, favicon_ico.clone()),
+                "/favicon.png" => router.route_service(path, favicon_png.clone()),
+                "/static/{*rest}" => router.nest_service("/static", static_dir.clone()),
+                _ => unreachable!("built-in route {path} has no handler"),
+            }
+        })
+        .fallback(fallback_not_found);
+    let router = if post_urls.is_default() {
+        router
+    } else {
+        router.route(&post_urls.route(), get(render_post))
+    };
+
+    let router = router.layer(middleware::from_fn_with_state(
+        router_state.app_state.clone(),
+        maintenance_gate,
+    ));
+    let router = with_panic_page(router, router_state.app_state.clone());
+    let router = router.layer(middleware::from_fn_with_state(
+        router_state.app_state.clone(),
+        vary_on_consent,
+    ));
+    let router = with_request_timeout(router, request_timeout());
+    let router = with_https_redirect(router, force_https());
+    with_request_id(router, request_id_header()).with_state(router_state)
 }
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     setup_logging();
 
-    let router_state = initialize_state().await;
+    let router_state = initialize_state().await?;
     let app = setup_router(router_state);
 
     let port: u16 = std::env::var("PORT")
@@ -490,488 +2307,2648 @@ async fn main() -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::{
-        default_rust_log, is_valid_post_slug, load_devloop_event_client, normalize_browser_path,
-        publish_browser_path_event, render_hot_reload_script, render_post_list, render_with_layout,
-        setup_router, HOT_RELOAD_SCRIPT,
-    };
-    use crate::models::{Post, SiteConfig};
-    use crate::page_meta::PageMeta;
-    use crate::state::{AppState, DevloopEventClient, RouterState};
-    use crate::test_support::TestEnvGuard;
+        build_router_state, default_rust_log, fill_placeholders, is_valid_post_slug,
+        load_devloop_event_client, normalize_browser_path, parse_allowed_origins,
+        publish_browser_path_event, reload_content_from, render_hot_reload_script,
+        render_post_list, render_with_layout, request_timeout, setup_router, static_dirs,
+        weak_etag, with_https_redirect, with_panic_page, with_request_id, with_request_timeout,
+        DEFAULT_REQUEST_ID_HEADER, HOT_RELOAD_SCRIPT, PAGE_PLACEHOLDERS,
+    };
+    use crate::content_loader::{
+        build_category_index, build_slug_index, build_tag_index, load_content_from, InvalidSlugs,
+        LoadOptions,
+    };
+    use crate::markdown::RenderOptions;
+    use crate::models::{CanonicalSource, HomeMode, Post, SiteConfig};
+    use crate::page_meta::{PageMeta, PageType};
+    use crate::post_url::PostUrlPattern;
+    use crate::state::{AppState, DevloopEventClient, Environment, ExpiredPosts, RouterState};
+    use crate::test_support::{capture_logs, TestContentDir, TestEnvGuard};
     use axum::{
         body::{to_bytes, Body},
         extract::State,
-        http::{HeaderMap, Request, StatusCode},
-        routing::post,
+        http::{header, HeaderMap, HeaderName, Request, StatusCode},
+        response::Response,
+        routing::{self, post},
         Json, Router,
     };
     use serde_json::Value;
-    use std::sync::{Arc, Mutex};
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
     use tokio::net::TcpListener;
     use tokio::sync::{oneshot, RwLock};
     use tower::ServiceExt;
 
-    type CapturedBrowserPathRequest = Arc<Mutex<Option<oneshot::Sender<(String, Value)>>>>;
+    type CapturedBrowserPathRequest = Arc<Mutex<Option<oneshot::Sender<(String, Value)>>>>;
+
+    fn test_layout() -> &'static str {
+        "<html><head><title>{{ page_title }}</title><meta name=\"description\" content=\"{{ page_description }}\" /><meta name=\"author\" content=\"{{ page_author }}\" /><meta property=\"og:title\" content=\"{{ page_title }}\" /><meta property=\"og:description\" content=\"{{ page_description }}\" /><meta property=\"og:url\" content=\"{{ page_url }}\" /><link rel=\"canonical\" href=\"{{ page_canonical_url }}\" /><meta property=\"og:image\" content=\"{{ page_image }}\" />{{ page_published_time_meta }}{{ page_role_meta }}{{ page_keywords_meta }}<meta name=\"twitter:title\" content=\"{{ page_title }}\" /><meta name=\"twitter:description\" content=\"{{ page_description }}\" /><meta name=\"twitter:image\" content=\"{{ page_image }}\" /></head><body class=\"{{ body_class }}\">{{ banner }}<main>{{ content }}</main><ul>{{ posts }}</ul></body></html>"
+    }
+
+    fn content_layout() -> &'static str {
+        include_str!("../content/layout.html")
+    }
+
+    #[test]
+    fn content_layout_contains_new_meta_placeholders() {
+        let layout = include_str!("../content/layout.html");
+        assert!(layout.contains("{{ page_author }}"));
+        assert!(layout.contains("{{ page_published_time_meta }}"));
+        assert!(layout.contains("{{ categories }}"));
+        assert!(layout.contains("{{ sitenav }}"));
+    }
+
+    #[test]
+    fn content_templates_use_site_placeholders() {
+        let layout = include_str!("../content/layout.html");
+        let banner = include_str!("../content/banner.html");
+        assert!(layout.contains("{{ site_og_name }}"));
+        assert!(layout.contains("{{ nav }}"));
+        assert!(layout.contains("{{ theme_color_meta }}"));
+        assert!(banner.contains("{{ site_title }}"));
+    }
+
+    fn test_posts() -> Vec<Post> {
+        vec![Post {
+            title: "First post".to_string(),
+            slug: "first-post".to_string(),
+            date: "2026-03-04".to_string(),
+            description: None,
+            image: None,
+            role: None,
+            subtitle: None,
+            markdown_body: "Body".to_string(),
+            reading_minutes: 1,
+            ..Post::default()
+        }]
+    }
+
+    fn test_meta() -> PageMeta {
+        PageMeta {
+            title: "Test title".to_string(),
+            description: "Test description".to_string(),
+            url: "https://example.com/posts/test".to_string(),
+            canonical_url: "https://origin.example/test".to_string(),
+            image: "https://example.com/static/test.png".to_string(),
+            author: "Daniel Vianna".to_string(),
+            published_time: Some("2026-03-04T00:00:00Z".to_string()),
+            role: Some("mechanism".to_string()),
+            keywords: vec!["rust".to_string(), "a&b".to_string()],
+            page_type: PageType::Post,
+            og_type: None,
+        }
+    }
+
+    #[test]
+    fn site_config_defaults_match_content_defaults() {
+        let config = SiteConfig::default();
+        assert_eq!(config.title, "Bon Élève Blog");
+        assert_eq!(config.author, "Daniel Vianna");
+    }
+
+    #[test]
+    fn injects_hot_reload_script_once_in_development() {
+        let page = render_with_layout(
+            content_layout(),
+            "<header>banner</header>",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Development,
+        );
+        assert_eq!(page.matches("new EventSource").count(), 1);
+        assert!(page.contains("window.__hotReloadController"));
+        assert!(page.contains("/static/tailwind.css?v="));
+    }
+
+    #[test]
+    fn hot_reload_script_uses_devloop_event_stream_listener() {
+        assert!(HOT_RELOAD_SCRIPT.contains("new EventSource(devloopEventsUrl)"));
+        assert!(HOT_RELOAD_SCRIPT.contains("triggerReload()"));
+        assert!(HOT_RELOAD_SCRIPT.contains("reportCurrentPath"));
+    }
+
+    #[test]
+    fn hot_reload_script_swaps_partial_content_for_current_post_changes() {
+        assert!(HOT_RELOAD_SCRIPT.contains("data.startsWith(\"reload:\")"));
+        assert!(HOT_RELOAD_SCRIPT.contains("=== window.location.pathname"));
+        assert!(HOT_RELOAD_SCRIPT.contains("\"?partial=1\""));
+        assert!(HOT_RELOAD_SCRIPT.contains("main.innerHTML"));
+    }
+
+    #[test]
+    fn render_with_layout_fills_every_page_placeholder() {
+        let layout: String = PAGE_PLACEHOLDERS
+            .iter()
+            .map(|name| format!("<p>{{{{ {name} }}}}</p>"))
+            .collect();
+        let page = render_with_layout(
+            &format!("<html><body><main>{layout}</main></body></html>"),
+            "banner",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Production,
+        );
+        assert!(!page.contains("{{"), "{page}");
+    }
+
+    #[test]
+    fn content_layout_wraps_content_in_main_landmark_with_skip_link() {
+        let page = render_with_layout(
+            content_layout(),
+            "banner",
+            "<p>post body</p>",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Production,
+        );
+
+        assert!(page.contains("<a class=\"skip-link\" href=\"#content\">Skip to content</a>"));
+        let main_start = page.find("<main id=\"content\"").expect("main landmark");
+        let main_end = page.find("</main>").expect("main landmark end");
+        let body = page.find("<p>post body</p>").expect("content");
+        assert!(main_start < body && body < main_end);
+    }
+
+    #[test]
+    fn layouts_without_main_get_a_content_landmark() {
+        let layout = "<html><body>{{ banner }}<div>{{ content }}</div></body></html>";
+        let page = render_with_layout(
+            layout,
+            "banner",
+            "<p>post body</p>",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Production,
+        );
+
+        assert!(page.contains("<div><main id=\"content\"><p>post body</p></main></div>"));
+    }
+
+    #[test]
+    fn analytics_snippet_is_injected_in_production_only() {
+        let site_config = SiteConfig {
+            analytics_snippet: Some(
+                "<script src=\"https://stats.example/a.js\"></script>".to_string(),
+            ),
+            ..SiteConfig::default()
+        };
+        let render = |environment| {
+            render_with_layout(
+                content_layout(),
+                "banner",
+                "content",
+                &test_posts(),
+                &site_config,
+                &test_meta(),
+                environment,
+            )
+        };
+
+        let production = render(Environment::Production);
+        assert!(production.contains("<script src=\"https://stats.example/a.js\"></script></head>"));
+        assert!(!production.contains("noindex"));
+        let staging = render(Environment::Staging);
+        assert!(!staging.contains("stats.example"));
+        assert!(staging.contains("<meta name=\"robots\" content=\"noindex\" /></head>"));
+        let development = render(Environment::Development);
+        assert!(!development.contains("stats.example"));
+    }
+
+    #[tokio::test]
+    async fn analytics_snippet_waits_for_the_consent_cookie() {
+        let router_state = test_router_state();
+        {
+            let mut site_config = router_state.app_state.site_config.write().await;
+            site_config.analytics_snippet =
+                Some("<script src=\"https://stats.example/a.js\"></script>".to_string());
+            site_config.consent_banner = Some("<div id=\"consent\">Cookies?</div>".to_string());
+        }
+        let app = setup_router(router_state);
+        let get = |cookie: Option<&str>| {
+            let mut request = Request::builder().uri("/");
+            if let Some(cookie) = cookie {
+                request = request.header("cookie", cookie);
+            }
+            request.body(Body::empty()).expect("build request")
+        };
+
+        let (_, body) = get_body(app.clone(), get(None)).await;
+        assert!(!body.contains("stats.example"));
+        assert!(body.contains("<div id=\"consent\">Cookies?</div></body>"));
+
+        let (_, body) = get_body(app, get(Some("theme=dark; analytics_consent=yes"))).await;
+        assert!(body.contains("<script src=\"https://stats.example/a.js\"></script></head>"));
+        assert!(!body.contains("id=\"consent\""));
+    }
+
+    #[tokio::test]
+    async fn consent_banner_only_wraps_full_pages_and_varies_on_cookie() {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            *app_state.posts.write().await = test_posts();
+            let mut site_config = app_state.site_config.write().await;
+            site_config.analytics_snippet =
+                Some("<script src=\"https://stats.example/a.js\"></script>".to_string());
+            site_config.consent_banner = Some("<div id=\"consent\">Cookies?</div>".to_string());
+        }
+        let app = setup_router(router_state);
+        let response = app.clone().oneshot(get("/")).await.expect("serve request");
+        let vary: Vec<_> = response.headers().get_all(header::VARY).iter().collect();
+        assert!(vary.iter().any(|value| *value == "Cookie"));
+
+        for uri in [
+            "/?partial=1",
+            "/posts/first-post?partial=1",
+            "/partials/latest",
+        ] {
+            let (status, body) = get_body(app.clone(), get(uri)).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert!(!body.contains("id=\"consent\""), "{uri}");
+            assert!(!body.contains("stats.example"), "{uri}");
+        }
+    }
+
+    #[test]
+    fn injects_script_at_end_when_body_tag_is_missing() {
+        let layout = "<html><div>{{ banner }}</div><main>{{ content }}</main></html>";
+        let page = render_with_layout(
+            layout,
+            "banner",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Development,
+        );
+        assert!(page.ends_with("</script>"));
+        assert_eq!(page.matches("new EventSource").count(), 1);
+    }
+
+    #[test]
+    fn does_not_inject_script_in_non_development() {
+        let page = render_with_layout(
+            content_layout(),
+            "banner",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Production,
+        );
+        assert_eq!(page.matches("new EventSource").count(), 0);
+        assert!(page.contains("href=\"/static/tailwind.css\""));
+    }
+
+    #[test]
+    fn development_pages_cache_bust_tailwind_stylesheet() {
+        let page = render_with_layout(
+            content_layout(),
+            "banner",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Development,
+        );
+
+        assert!(page.contains("href=\"/static/tailwind.css?v="));
+    }
+
+    #[test]
+    fn development_cache_busting_replaces_existing_asset_version() {
+        let layout = "<html><head><link rel=\"stylesheet\" href=\"/static/tailwind.css?v=abc123\" /></head><body>{{ content }}</body></html>";
+        let page = render_with_layout(
+            layout,
+            "banner",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Development,
+        );
+
+        assert!(page.contains("href=\"/static/tailwind.css?v="));
+        assert!(!page.contains("?v=abc123"));
+        assert_eq!(page.matches("?v=").count(), 1);
+    }
+
+    #[test]
+    fn render_hot_reload_script_injects_devloop_events_url_when_present() {
+        let _guard = TestEnvGuard::set([(
+            "DEVLOOP_BROWSER_EVENTS_URL",
+            Some("http://127.0.0.1:4455/browser-events"),
+        )]);
+
+        let script = render_hot_reload_script();
+
+        assert!(script.contains("\"http://127.0.0.1:4455/browser-events\""));
+        assert!(!script.contains("__DEVLOOP_BROWSER_EVENTS_URL__"));
+    }
+
+    #[test]
+    fn render_hot_reload_script_falls_back_to_null_when_devloop_events_url_is_missing() {
+        let _guard = TestEnvGuard::set([("DEVLOOP_BROWSER_EVENTS_URL", None)]);
+
+        let script = render_hot_reload_script();
+
+        assert!(script.contains("const devloopEventsUrl = null;"));
+    }
+
+    #[test]
+    fn render_hot_reload_script_flashes_title_only_when_configured() {
+        {
+            let _guard = TestEnvGuard::set([("HOT_RELOAD_FLASH_TITLE", Some("↻ Reloaded"))]);
+            let script = render_hot_reload_script();
+            assert!(script.contains("const reloadFlashTitle = \"↻ Reloaded\";"));
+            assert!(script.contains("document.title = reloadFlashTitle;"));
+            assert!(!script.contains("__HOT_RELOAD_FLASH_TITLE__"));
+        }
+        let _guard = TestEnvGuard::set([("HOT_RELOAD_FLASH_TITLE", None)]);
+        assert!(render_hot_reload_script().contains("const reloadFlashTitle = null;"));
+    }
+
+    #[test]
+    fn does_not_replace_posts_placeholder_inside_content() {
+        let content = "<p>literal {{ posts }}</p>";
+        let page = render_with_layout(
+            test_layout(),
+            "banner",
+            content,
+            &test_posts(),
+            &SiteConfig::default(),
+            &test_meta(),
+            Environment::Production,
+        );
+        assert!(page.contains("<p>literal {{ posts }}</p>"));
+    }
+
+    #[test]
+    fn renders_page_meta_tags_from_meta_input() {
+        let meta = test_meta();
+        let page = render_with_layout(
+            test_layout(),
+            "banner",
+            "content",
+            &test_posts(),
+            &SiteConfig::default(),
+            &meta,
+            Environment::Production,
+        );
+
+        assert!(page.contains("<title>Test title</title>"));
+        assert!(page.contains("<meta property=\"og:title\" content=\"Test title\" />"));
+        assert!(page.contains("<meta property=\"og:description\" content=\"Test description\" />"));
+        assert!(page
+            .contains("<meta property=\"og:url\" content=\"https://example.com/posts/test\" />"));
+        assert!(page.contains(
+            "<meta property=\"og:image\" content=\"https://example.com/static/test.png\" />"
+        ));
+        assert!(page.contains("<meta name=\"twitter:title\" content=\"Test title\" />"));
+        assert!(page.contains("<meta name=\"author\" content=\"Daniel Vianna\" />"));
+        assert!(page.contains(
+            "<meta property=\"article:published_time\" content=\"2026-03-04T00:00:00Z\" />"
+        ));
+        assert!(page.contains("<meta property=\"article:section\" content=\"mechanism\" />"));
+        assert!(page.contains("<meta name=\"keywords\" content=\"rust, a&amp;b\" />"));
+        assert!(page.contains("<link rel=\"canonical\" href=\"https://origin.example/test\" />"));
+    }
+
+    #[test]
+    fn validates_slug_characters_for_post_paths() {
+        assert!(is_valid_post_slug(
+            "2026-03-03-optimise-for-the-cheapest-change"
+        ));
+        assert!(!is_valid_post_slug("../secrets"));
+        assert!(!is_valid_post_slug("post/with/slash"));
+        assert!(!is_valid_post_slug("..\\secrets"));
+        assert!(!is_valid_post_slug("post\0"));
+        assert!(!is_valid_post_slug("UPPERCASE"));
+        assert!(!is_valid_post_slug("bad_slug"));
+        assert!(is_valid_post_slug("café-crème"));
+        assert!(!is_valid_post_slug("Café"));
+        assert!(!is_valid_post_slug("caf\u{e9}\u{a0}x"));
+        assert!(is_valid_post_slug("日本語-メモ"));
+    }
+
+    #[test]
+    fn rejects_invisible_slug_characters() {
+        for slug in [
+            "admin\u{202e}txt",
+            "zero\u{200b}width",
+            "bom\u{feff}",
+            "isolate\u{2066}x\u{2069}",
+            "full\u{ff0f}width",
+            "full\u{ff0e}stop",
+            "emoji-\u{1f600}",
+        ] {
+            assert!(!is_valid_post_slug(slug), "{slug:?}");
+            assert!(
+                is_valid_post_slug(&crate::page_meta::slugify(slug)),
+                "{slug:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_slugs_mixing_lookalike_scripts() {
+        for slug in ["p\u{430}ypal", "g\u{3bf}\u{3bf}gle", "\u{440}ust-notes"] {
+            assert!(!is_valid_post_slug(slug), "{slug:?}");
+        }
+        for slug in [
+            "\u{440}\u{430}\u{441}\u{442}",
+            "\u{3b1}\u{3b2}\u{3b3}-2",
+            "café-crème",
+        ] {
+            assert!(is_valid_post_slug(slug), "{slug:?}");
+        }
+    }
+
+    #[test]
+    fn normalize_browser_path_accepts_root_and_post_paths() {
+        assert_eq!(normalize_browser_path("/").as_deref(), Some("/"));
+        assert_eq!(
+            normalize_browser_path("/posts/example-post").as_deref(),
+            Some("/posts/example-post")
+        );
+        assert_eq!(
+            normalize_browser_path("/posts/example-post/?x=1#frag").as_deref(),
+            Some("/posts/example-post")
+        );
+    }
+
+    #[test]
+    fn normalize_browser_path_rejects_invalid_inputs() {
+        assert_eq!(normalize_browser_path("").as_deref(), None);
+        assert_eq!(normalize_browser_path("posts/example").as_deref(), None);
+        assert_eq!(normalize_browser_path("//example.com").as_deref(), None);
+    }
+
+    #[test]
+    fn default_rust_log_uses_info_when_unset() {
+        let _guard = TestEnvGuard::set([("RUST_LOG", None)]);
+        assert_eq!(default_rust_log(), "info");
+    }
+
+    #[test]
+    fn default_rust_log_respects_environment_override() {
+        let _guard = TestEnvGuard::set([("RUST_LOG", Some("warn,gcp_rust_blog=debug"))]);
+        assert_eq!(default_rust_log(), "warn,gcp_rust_blog=debug");
+    }
+
+    #[test]
+    fn static_dirs_default_to_content_static() {
+        let _guard = TestEnvGuard::set([
+            ("STATIC_DIR", None),
+            ("CONTENT_DIR", None),
+            ("THEME_DIR", None),
+        ]);
+        assert_eq!(
+            static_dirs(),
+            (std::path::PathBuf::from("content/static"), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_theme_static_assets_behind_site_overrides() {
+        let theme = TestContentDir::new();
+        theme.write("static/theme.css", "theme css");
+        theme.write("static/logo.png", "theme logo");
+        let site = TestContentDir::new();
+        site.write("static/logo.png", "site logo");
+        let theme_path = theme.path().to_string_lossy().into_owned();
+        let site_path = site.path().to_string_lossy().into_owned();
+        let app = {
+            let _guard = TestEnvGuard::set([
+                ("STATIC_DIR", None),
+                ("CONTENT_DIR", Some(site_path.as_str())),
+                ("THEME_DIR", Some(theme_path.as_str())),
+            ]);
+            setup_router(test_router_state())
+        };
+
+        for (uri, expected) in [
+            ("/static/theme.css", "theme css"),
+            ("/static/logo.png", "site logo"),
+        ] {
+            let (status, body) = get_body(app.clone(), get(uri)).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert_eq!(body, expected, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_static_assets_from_configured_directory() {
+        let content = TestContentDir::new();
+        content.write("assets/hello.txt", "hello from assets");
+        content.write("assets/favicon.ico", "icon");
+        let assets = content.path().join("assets");
+        let assets = assets.to_string_lossy().into_owned();
+        let app = {
+            let _guard =
+                TestEnvGuard::set([("STATIC_DIR", Some(assets.as_str())), ("THEME_DIR", None)]);
+            setup_router(test_router_state())
+        };
+
+        let response = app
+            .clone()
+            .oneshot(get("/static/hello.txt"))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        assert_eq!(&body[..], b"hello from assets");
+
+        let response = app
+            .oneshot(get("/favicon.ico"))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn serves_webmanifest_with_manifest_content_type() {
+        let content = TestContentDir::new();
+        content.write("assets/site.webmanifest", "{\"name\":\"Blog\"}");
+        let assets = content.path().join("assets");
+        let assets = assets.to_string_lossy().into_owned();
+        let app = {
+            let _guard = TestEnvGuard::set([
+                ("STATIC_DIR", Some(assets.as_str())),
+                ("THEME_DIR", None),
+                ("STATIC_MIME_TYPES", None),
+            ]);
+            setup_router(test_router_state())
+        };
+
+        let response = app
+            .oneshot(get("/static/site.webmanifest"))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[axum::http::header::CONTENT_TYPE],
+            "application/manifest+json"
+        );
+    }
+
+    #[test]
+    fn request_timeout_uses_default_and_environment_override() {
+        {
+            let _guard = TestEnvGuard::set([("REQUEST_TIMEOUT_SECS", None)]);
+            assert_eq!(request_timeout(), std::time::Duration::from_secs(30));
+        }
+        {
+            let _guard = TestEnvGuard::set([("REQUEST_TIMEOUT_SECS", Some("5"))]);
+            assert_eq!(request_timeout(), std::time::Duration::from_secs(5));
+        }
+        {
+            let _guard = TestEnvGuard::set([("REQUEST_TIMEOUT_SECS", Some("0"))]);
+            assert_eq!(request_timeout(), std::time::Duration::from_secs(30));
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_handlers_time_out_with_service_unavailable() {
+        async fn slow() -> &'static str {
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            "too late"
+        }
+
+        let app = with_request_timeout(
+            Router::new().route("/slow", routing::get(slow)),
+            std::time::Duration::from_millis(20),
+        );
+        let response = app.oneshot(get("/slow")).await.expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn panicking_handlers_render_a_logged_styled_500() {
+        async fn boom() -> &'static str {
+            panic!("boom in handler")
+        }
+
+        let (logs, _guard) = capture_logs();
+        let app = with_panic_page(
+            Router::new().route("/boom", routing::get(boom)),
+            test_router_state().app_state,
+        );
+        let (status, body) = get_body(app, get("/boom")).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body.contains("<body class=\"page-error\">"));
+        assert!(body.contains("<h1>Something went wrong</h1>"));
+        assert!(body.contains("<header>banner</header>"));
+        let logs = logs.contents();
+        assert!(logs.contains("request handler panicked"));
+        assert!(logs.contains("boom in handler"));
+    }
+
+    #[tokio::test]
+    async fn maintenance_mode_answers_503_but_keeps_healthz_green() {
+        let mut state = Arc::into_inner(test_router_state().app_state).expect("sole state owner");
+        state.maintenance = true;
+        let app = setup_router(RouterState {
+            app_state: Arc::new(state),
+        });
+        for uri in ["/", "/posts/first-post", "/api/posts/first-post"] {
+            let (status, body) = get_body(app.clone(), get(uri)).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{uri}");
+            assert!(body.contains("<body class=\"page-maintenance\">"), "{uri}");
+            assert!(body.contains("Down for maintenance"), "{uri}");
+        }
+
+        let (status, _) = get_body(app, get("/healthz")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn forced_https_redirects_forwarded_http_requests() {
+        let app = with_https_redirect(
+            Router::new().route("/posts/a", routing::get(|| async { "ok" })),
+            true,
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/posts/a?partial=1")
+                    .header("host", "blog.example.com")
+                    .header("x-forwarded-proto", "http")
+                    .body(Body::empty())
+                    .expect("build request"),
+            )
+            .await
+            .expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers().get("location").expect("location header"),
+            "https://blog.example.com/posts/a?partial=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn forced_https_passes_forwarded_https_requests() {
+        let app = with_https_redirect(
+            Router::new().route("/posts/a", routing::get(|| async { "ok" })),
+            true,
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/posts/a")
+                    .header("host", "blog.example.com")
+                    .header("x-forwarded-proto", "https")
+                    .body(Body::empty())
+                    .expect("build request"),
+            )
+            .await
+            .expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn https_redirect_is_off_unless_forced() {
+        let app = with_https_redirect(
+            Router::new().route("/posts/a", routing::get(|| async { "ok" })),
+            false,
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/posts/a")
+                    .header("host", "blog.example.com")
+                    .header("x-forwarded-proto", "http")
+                    .body(Body::empty())
+                    .expect("build request"),
+            )
+            .await
+            .expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_id_is_echoed_or_generated() {
+        let app = with_request_id(
+            Router::new().route(
+                "/posts/a",
+                routing::get(|headers: HeaderMap| async move {
+                    headers
+                        .get("x-request-id")
+                        .and_then(|id| id.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+            ),
+            HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER),
+        );
+        let request = |id: Option<&str>| {
+            let builder = Request::builder().uri("/posts/a");
+            match id {
+                Some(id) => builder.header("x-request-id", id),
+                None => builder,
+            }
+            .body(Body::empty())
+            .expect("build request")
+        };
+        let echoed = |response: &Response| {
+            response
+                .headers()
+                .get("x-request-id")
+                .expect("request id header")
+                .to_str()
+                .expect("ascii request id")
+                .to_string()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(Some("abc-123")))
+            .await
+            .expect("serve request");
+        assert_eq!(echoed(&response), "abc-123");
+
+        let response = app
+            .clone()
+            .oneshot(request(None))
+            .await
+            .expect("serve request");
+        let generated = echoed(&response);
+        assert_eq!(generated.len(), 32);
+        assert!(generated.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        assert_eq!(body, generated.as_bytes(), "handlers see the same ID");
+
+        let response = app
+            .oneshot(request(Some("has space")))
+            .await
+            .expect("serve request");
+        assert_ne!(echoed(&response), "has space");
+    }
+
+    #[test]
+    fn load_devloop_event_client_requires_url_and_token() {
+        {
+            let _guard = TestEnvGuard::set([
+                ("DEVLOOP_EVENT_BROWSER_PATH_URL", None),
+                ("DEVLOOP_EVENTS_TOKEN", None),
+            ]);
+
+            assert!(load_devloop_event_client().is_none());
+        }
+
+        {
+            let _guard = TestEnvGuard::set([
+                (
+                    "DEVLOOP_EVENT_BROWSER_PATH_URL",
+                    Some("http://127.0.0.1:1/events/browser_path"),
+                ),
+                ("DEVLOOP_EVENTS_TOKEN", None),
+            ]);
+
+            assert!(load_devloop_event_client().is_none());
+        }
+
+        {
+            let _guard = TestEnvGuard::set([
+                (
+                    "DEVLOOP_EVENT_BROWSER_PATH_URL",
+                    Some("http://127.0.0.1:1/events/browser_path"),
+                ),
+                ("DEVLOOP_EVENTS_TOKEN", Some("secret")),
+            ]);
+            let client = load_devloop_event_client().expect("load client");
+            assert_eq!(
+                client.browser_path_url,
+                "http://127.0.0.1:1/events/browser_path"
+            );
+            assert_eq!(client.token, "secret");
+        }
+    }
+
+    #[tokio::test]
+    async fn publish_browser_path_event_sends_bearer_token_and_json_body() {
+        async fn capture_request(
+            State(tx): State<CapturedBrowserPathRequest>,
+            headers: HeaderMap,
+            Json(body): Json<Value>,
+        ) -> StatusCode {
+            let auth = headers
+                .get(axum::http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+            if let Some(sender) = tx.lock().expect("lock capture sender").take() {
+                let _ = sender.send((auth, body));
+            }
+            StatusCode::NO_CONTENT
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let shared_tx = Arc::new(Mutex::new(Some(tx)));
+        let app = Router::new()
+            .route("/events/browser_path", post(capture_request))
+            .with_state(shared_tx);
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind test listener");
+        let addr = listener.local_addr().expect("listener addr");
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("serve test app");
+        });
+
+        let client = DevloopEventClient {
+            browser_path_url: format!("http://{addr}/events/browser_path"),
+            reload_url: None,
+            token: "secret".into(),
+            client: reqwest::Client::new(),
+        };
+
+        publish_browser_path_event(&client, "/posts/example-post")
+            .await
+            .expect("publish browser path event");
+
+        let (auth, body) = rx.await.expect("receive request");
+        assert_eq!(auth, "Bearer secret");
+        assert_eq!(body["value"], Value::String("/posts/example-post".into()));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn content_reload_broadcasts_the_path_of_a_single_changed_post() {
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new()
+            .route(
+                "/events/reload",
+                post(
+                    |State(events_tx): State<tokio::sync::mpsc::UnboundedSender<Value>>,
+                     Json(body): Json<Value>| async move {
+                        let _ = events_tx.send(body);
+                        StatusCode::NO_CONTENT
+                    },
+                ),
+            )
+            .with_state(events_tx);
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind test listener");
+        let addr = listener.local_addr().expect("listener addr");
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("serve test app");
+        });
+
+        let content = TestContentDir::new();
+        let post = |slug: &str, title: &str, body: &str| {
+            format!("---\ntitle: \"{title}\"\ndate: 2026-03-04\nslug: {slug}\n---\n{body}")
+        };
+        content.write_post(
+            "draft.md",
+            &post("draft-notes", "Draft notes", "First take."),
+        );
+        content.write_post("other.md", &post("other", "Other", "Unchanged."));
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let client = DevloopEventClient {
+            browser_path_url: format!("http://{addr}/events/browser_path"),
+            reload_url: Some(format!("http://{addr}/events/reload")),
+            token: "secret".into(),
+            client: reqwest::Client::new(),
+        };
+        let router_state = build_router_state(loaded, Environment::Development, Some(client));
+        let state = &router_state.app_state;
+
+        content.write_post(
+            "draft.md",
+            &post("draft-notes", "Draft notes", "Second take."),
+        );
+        reload_content_from(state, &content.roots())
+            .await
+            .expect("reload");
+        let event = events.recv().await.expect("reload event");
+        assert_eq!(event["value"], "reload:/posts/draft-notes");
+        assert!(state
+            .posts
+            .read()
+            .await
+            .iter()
+            .any(|post| post.markdown_body.contains("Second take.")));
+
+        content.write_post("draft.md", &post("draft-notes", "Renamed", "Second take."));
+        reload_content_from(state, &content.roots())
+            .await
+            .expect("reload");
+        assert_eq!(
+            events.recv().await.expect("reload event")["value"],
+            "reload"
+        );
+
+        content.write_post("draft.md", &post("draft-notes", "Renamed", "Third take."));
+        content.write_post("other.md", &post("other", "Other", "Changed too."));
+        reload_content_from(state, &content.roots())
+            .await
+            .expect("reload");
+        assert_eq!(
+            events.recv().await.expect("reload event")["value"],
+            "reload"
+        );
+
+        server.abort();
+    }
+
+    fn make_post(slug: &str, title: &str, role: Option<&str>, subtitle: Option<&str>) -> Post {
+        Post {
+            slug: slug.to_string(),
+            title: title.to_string(),
+            date: "2026-01-01".to_string(),
+            role: role.map(ToString::to_string),
+            subtitle: subtitle.map(ToString::to_string),
+            description: None,
+            image: None,
+            markdown_body: String::new(),
+            reading_minutes: 1,
+            ..Post::default()
+        }
+    }
+
+    fn test_router_state() -> RouterState {
+        let state = Arc::new(AppState {
+            site_config: RwLock::new(SiteConfig::default()),
+            banner_html: RwLock::new("<header>banner</header>".to_string()),
+            layout_html: RwLock::new(test_layout().to_string()),
+            print_layout_html: RwLock::new(test_layout().to_string()),
+            home_post: RwLock::new(Post {
+                title: "Home".to_string(),
+                slug: "home".to_string(),
+                date: "2026-03-24".to_string(),
+                description: None,
+                image: None,
+                role: None,
+                subtitle: None,
+                markdown_body: "# Home".to_string(),
+                ..Post::default()
+            }),
+            current_browser_path: RwLock::new("/".to_string()),
+            devloop_event_client: None,
+            not_found_markdown: RwLock::new(
+                "# Terra incognita\n\nThis path is not mapped.".to_string(),
+            ),
+            posts: RwLock::new(test_posts()),
+            environment: Environment::Production,
+            render_options: RenderOptions::default(),
+            ready: AtomicBool::new(false),
+            dev_allowed_origins: parse_allowed_origins(None),
+            expired_posts: ExpiredPosts::default(),
+            slug_index: RwLock::new(build_slug_index(&test_posts())),
+            category_index: RwLock::new(build_category_index(&test_posts())),
+            tag_index: RwLock::new(build_tag_index(&test_posts())),
+            case_insensitive_slugs: false,
+            render_timings: RwLock::new(Default::default()),
+            gone_slugs: RwLock::new(Default::default()),
+            maintenance: false,
+        });
+
+        RouterState { app_state: state }
+    }
+
+    #[test]
+    fn groups_posts_by_role_in_first_seen_order() {
+        let posts = vec![
+            make_post("a", "A", Some("mechanism"), None),
+            make_post("b", "B", Some("strategy"), None),
+            make_post("c", "C", Some("mechanism"), None),
+        ];
+        let html = render_post_list(&posts, &SiteConfig::default());
+        let mech_pos = html.find("Mechanism").unwrap();
+        let strat_pos = html.find("Strategy").unwrap();
+        // Mechanism group header appears before Strategy
+        assert!(mech_pos < strat_pos);
+        // Post C (second mechanism post) appears after post A
+        let a_pos = html.find("/posts/a").unwrap();
+        let c_pos = html.find("/posts/c").unwrap();
+        assert!(a_pos < c_pos);
+        // All posts are present
+        assert!(html.contains("/posts/b"));
+    }
+
+    #[test]
+    fn escapes_html_in_sidebar_titles_and_subtitles() {
+        let posts = vec![make_post(
+            "xss",
+            "<script>alert(1)</script>",
+            Some("<role>"),
+            Some("<b>bad</b>"),
+        )];
+        let html = render_post_list(&posts, &SiteConfig::default());
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<b>bad</b>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("&lt;b&gt;"));
+    }
+
+    #[test]
+    fn posts_without_role_render_without_group_header() {
+        let posts = vec![make_post("no-role", "No Role", None, None)];
+        let html = render_post_list(&posts, &SiteConfig::default());
+        assert!(!html.contains("sidebar-group-header"));
+        assert!(html.contains("/posts/no-role"));
+    }
+
+    #[tokio::test]
+    async fn missing_root_path_uses_not_found_page() {
+        let app = setup_router(test_router_state());
+        let response = app.oneshot(get("/missing")).await.expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(body.contains("Terra incognita"));
+        assert!(body.contains("This path is not mapped."));
+    }
+
+    #[tokio::test]
+    async fn missing_nested_path_uses_not_found_page() {
+        let app = setup_router(test_router_state());
+        let response = app
+            .oneshot(get("/any/other/path"))
+            .await
+            .expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(body.contains("Terra incognita"));
+    }
+
+    #[tokio::test]
+    async fn missing_post_slug_still_uses_not_found_page() {
+        let app = setup_router(test_router_state());
+        let response = app
+            .oneshot(get("/posts/does-not-exist"))
+            .await
+            .expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(body.contains("Terra incognita"));
+    }
+
+    #[tokio::test]
+    async fn crafted_slugs_cannot_escape_the_posts_directory() {
+        let app = setup_router(test_router_state());
+        for uri in [
+            "/posts/..%2F..%2Fetc%2Fpasswd",
+            "/posts/..%5C..%5Cetc%5Cpasswd",
+            "/posts/first-post%00",
+            "/posts/..",
+        ] {
+            let response = app.clone().oneshot(get(uri)).await.expect("serve request");
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_post_whose_slug_differs_from_file_name() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "my-post.md",
+            "---\ntitle: \"Mismatched\"\ndate: 2026-03-04\nslug: different\n---\nMismatched body.",
+        );
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let app = setup_router(build_router_state(loaded, Environment::Production, None));
+
+        let response = app
+            .oneshot(get("/posts/different"))
+            .await
+            .expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(body.contains("Mismatched body."));
+    }
+
+    #[tokio::test]
+    async fn readyz_reports_unavailable_until_content_is_loaded() {
+        let router_state = test_router_state();
+        let app_state = router_state.app_state.clone();
+        let app = setup_router(router_state);
+        let response = app
+            .clone()
+            .oneshot(get("/readyz"))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        app_state.ready.store(true, Ordering::Release);
+        let response = app.oneshot(get("/readyz")).await.expect("serve request");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn healthz_is_ok_regardless_of_readiness() {
+        let app = setup_router(test_router_state());
+        let response = app.oneshot(get("/healthz")).await.expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn drafts_are_served_only_in_development() {
+        let content = TestContentDir::new();
+        content.write(
+            "drafts/wip.md",
+            "---\ntitle: \"Work in progress\"\ndate: 2026-03-04\nslug: wip\n---\nDraft body.",
+        );
+        content.write_post(
+            "flagged.md",
+            "---\ntitle: \"Flagged\"\ndate: 2026-03-04\nslug: flagged\ndraft: true\n---\nDraft body.",
+        );
+
+        for (environment, expected) in [
+            (Environment::Development, StatusCode::OK),
+            (Environment::Staging, StatusCode::OK),
+            (Environment::Production, StatusCode::NOT_FOUND),
+        ] {
+            let loaded = load_content_from(
+                &content.roots(),
+                LoadOptions {
+                    include_drafts: environment.shows_drafts(),
+                    ..LoadOptions::default()
+                },
+            )
+            .await
+            .expect("load");
+            let app = setup_router(build_router_state(loaded, environment, None));
+
+            for uri in ["/posts/wip", "/posts/flagged"] {
+                let response = app.clone().oneshot(get(uri)).await.expect("serve request");
+
+                assert_eq!(response.status(), expected, "{environment:?} {uri}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn posts_carry_validators_and_answer_matching_etags_with_304() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "cached.md",
+            "---\ntitle: \"Cached\"\ndate: 2026-03-04\nslug: cached\n---\nBody.",
+        );
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let app = setup_router(build_router_state(loaded, Environment::Production, None));
+        let get = |if_none_match: Option<&str>| {
+            let mut request = Request::builder().uri("/posts/cached");
+            if let Some(tag) = if_none_match {
+                request = request.header("if-none-match", tag);
+            }
+            request.body(Body::empty()).expect("build request")
+        };
+
+        let response = app.clone().oneshot(get(None)).await.expect("serve request");
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[axum::http::header::ETAG]
+            .to_str()
+            .expect("ascii etag")
+            .to_string();
+        assert!(etag.starts_with("W/\""), "{etag}");
+        let last_modified = &response.headers()[axum::http::header::LAST_MODIFIED];
+        assert!(last_modified
+            .to_str()
+            .expect("ascii date")
+            .ends_with(" GMT"));
+
+        let response = app
+            .clone()
+            .oneshot(get(Some(&format!("\"other\", {etag}"))))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[axum::http::header::ETAG], etag.as_str());
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        assert!(body.is_empty());
+
+        let (status, _) = get_body(app.clone(), get(Some("W/\"stale\""))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Validators come from the load, not from the file at request time.
+        std::fs::remove_file(content.path().join("posts/cached.md")).expect("remove post");
+        let response = app.clone().oneshot(get(None)).await.expect("serve request");
+        assert_eq!(
+            &response.headers()[axum::http::header::LAST_MODIFIED],
+            last_modified
+        );
+
+        let partial = |if_none_match: Option<&str>| {
+            let mut request = Request::builder().uri("/posts/cached?partial=1");
+            if let Some(tag) = if_none_match {
+                request = request.header("if-none-match", tag);
+            }
+            request.body(Body::empty()).expect("build request")
+        };
+        let response = app
+            .clone()
+            .oneshot(partial(None))
+            .await
+            .expect("serve request");
+        assert_eq!(
+            response.headers()[axum::http::header::VARY],
+            "X-Requested-With"
+        );
+        let partial_etag = response.headers()[axum::http::header::ETAG].clone();
+        let response = app
+            .oneshot(partial(partial_etag.to_str().ok()))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            response.headers()[axum::http::header::VARY],
+            "X-Requested-With"
+        );
+    }
+
+    #[test]
+    fn weak_etags_use_a_stable_hash() {
+        assert_eq!(weak_etag(""), "W/\"cbf29ce484222325\"");
+        assert_eq!(weak_etag("a"), "W/\"af63dc4c8601ec8c\"");
+    }
+
+    #[tokio::test]
+    async fn invalid_slugs_are_rewritten_for_routes_and_links_or_rejected() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "spaced.md",
+            "---\ntitle: \"Spaced\"\ndate: 2026-03-04\nslug: My Spaced_Post\n---\nBody.",
+        );
+
+        for (invalid_slugs, expected) in [
+            (InvalidSlugs::Rewrite, StatusCode::OK),
+            (InvalidSlugs::Reject, StatusCode::NOT_FOUND),
+        ] {
+            let loaded = load_content_from(
+                &content.roots(),
+                LoadOptions {
+                    invalid_slugs,
+                    ..LoadOptions::default()
+                },
+            )
+            .await
+            .expect("load");
+            let app = setup_router(build_router_state(loaded, Environment::Production, None));
+
+            let (status, body) = get_body(app, get("/posts/my-spaced-post")).await;
+            assert_eq!(status, expected, "{invalid_slugs:?}");
+            assert_eq!(
+                body.contains("href=\"/posts/my-spaced-post\""),
+                invalid_slugs == InvalidSlugs::Rewrite,
+                "{invalid_slugs:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_front_matter_shows_error_banner_only_in_development() {
+        let content = TestContentDir::new();
+        content.write_post("broken.md", "---\ntitle: \"Broken\"\n---\nBody.");
+
+        for (environment, shows_banner) in [
+            (Environment::Development, true),
+            (Environment::Production, false),
+        ] {
+            let loaded = load_content_from(&content.roots(), LoadOptions::default())
+                .await
+                .expect("load");
+            let app = setup_router(build_router_state(loaded, environment, None));
+
+            let (status, body) = get_body(app, get("/posts/error")).await;
+
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(
+                body.contains("<aside class=\"dev-error-banner\" role=\"alert\"><p class=\"dev-error-banner-title\">Problems in "),
+                shows_banner,
+                "{environment:?}"
+            );
+            assert_eq!(body.contains("posts/broken.md"), shows_banner);
+            assert_eq!(body.contains("Invalid front matter"), shows_banner);
+        }
+    }
+
+    async fn get_body(app: Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.expect("serve request");
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        (status, String::from_utf8(body.to_vec()).expect("utf8 body"))
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("build request")
+    }
+
+    #[tokio::test]
+    async fn partial_query_returns_content_without_layout() {
+        let app = setup_router(test_router_state());
+
+        let (status, full) = get_body(app.clone(), get("/posts/first-post")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(full.contains("<html>"));
+
+        let (status, partial) = get_body(app, get("/posts/first-post?partial=1")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(partial.starts_with("<header class=\"post-header\">"));
+        assert!(partial.contains("<p>Body</p>"));
+        assert!(!partial.contains("<html>"));
+        assert!(!partial.contains("<header>banner</header>"));
+    }
+
+    #[tokio::test]
+    async fn requested_with_header_returns_partial_homepage() {
+        let app = setup_router(test_router_state());
+        let (status, partial) = get_body(
+            app,
+            Request::builder()
+                .uri("/")
+                .header("X-Requested-With", "XMLHttpRequest")
+                .body(Body::empty())
+                .expect("build request"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(partial, "<h1>Home</h1>\n");
+    }
+
+    #[tokio::test]
+    async fn api_post_returns_rendered_post_fields() {
+        let app = setup_router(test_router_state());
+        let (status, body) = get_body(app, get("/api/posts/first-post")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let json: Value = serde_json::from_str(&body).expect("json body");
+        assert_eq!(json["title"], "First post");
+        assert_eq!(json["slug"], "first-post");
+        assert_eq!(json["date"], "2026-03-04");
+        assert_eq!(json["tags"], serde_json::json!([]));
+        assert_eq!(json["html"], "<p>Body</p>\n");
+        assert_eq!(json["reading_time"], 1);
+    }
+
+    #[tokio::test]
+    async fn api_post_preview_has_excerpt_but_no_body() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(Post {
+            markdown_body: "# Heading\n\nA **short** opening paragraph.".to_string(),
+            ..make_post("previewed", "Previewed", None, Some("A subtitle"))
+        });
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/api/posts/previewed/preview")).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: Value = serde_json::from_str(&body).expect("json body");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "title": "Previewed",
+                "excerpt": "A subtitle. A short opening paragraph.",
+                "reading_time": 1
+            })
+        );
+        assert!(!body.contains("<p>"));
+
+        let (status, _) = get_body(app, get("/api/posts/Bad_Slug/preview")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn search_index_has_one_plain_text_entry_per_post() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(Post {
+            tags: vec!["rust".to_string()],
+            markdown_body: "## Heading\n\nSome **bold** and [linked](https://example.com) text.\n\n```\ncode\n```"
+                .to_string(),
+            ..make_post("second-post", "Second post", None, None)
+        });
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app, get("/search-index.json")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let json: Value = serde_json::from_str(&body).expect("json body");
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"slug": "first-post", "title": "First post", "tags": [], "body_text": "Body"},
+                {
+                    "slug": "second-post",
+                    "title": "Second post",
+                    "tags": ["rust"],
+                    "body_text": "Some bold and linked text."
+                }
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn api_post_returns_json_404_for_unknown_or_invalid_slugs() {
+        for uri in ["/api/posts/does-not-exist", "/api/posts/Bad_Slug"] {
+            let app = setup_router(test_router_state());
+            let response = app.oneshot(get(uri)).await.expect("serve request");
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+            assert_eq!(
+                response.headers()[axum::http::header::CONTENT_TYPE],
+                "application/json"
+            );
+            let body = to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("read body");
+            let json: Value = serde_json::from_slice(&body).expect("json body");
+            assert!(json["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("post not found")));
+        }
+    }
+
+    #[test]
+    fn parses_dev_allowed_origins_with_localhost_default() {
+        assert_eq!(
+            parse_allowed_origins(None),
+            ["localhost", "127.0.0.1", "[::1]"]
+        );
+        assert_eq!(
+            parse_allowed_origins(Some(" , ")),
+            parse_allowed_origins(None)
+        );
+        assert_eq!(
+            parse_allowed_origins(Some("https://Blog.Example.com/, devbox")),
+            ["https://blog.example.com", "devbox"]
+        );
+    }
+
+    #[tokio::test]
+    async fn dev_channel_rejects_disallowed_origins() {
+        let post_path = |origin: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/__dev/current-path")
+                .header("host", "127.0.0.1:8080")
+                .header("origin", origin)
+                .body(Body::from("/posts/first-post"))
+                .expect("build request")
+        };
+        let dev_app = || {
+            let mut state =
+                Arc::into_inner(test_router_state().app_state).expect("sole state owner");
+            state.environment = Environment::Development;
+            setup_router(RouterState {
+                app_state: Arc::new(state),
+            })
+        };
+
+        let (status, _) = get_body(dev_app(), post_path("https://evil.example")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        for origin in [
+            "http://localhost:3000",
+            "http://127.0.0.1:8080",
+            "http://[::1]:8080",
+        ] {
+            let (status, _) = get_body(dev_app(), post_path(origin)).await;
+            assert_eq!(status, StatusCode::NO_CONTENT, "{origin}");
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_posts_are_hidden_in_production() {
+        let expired = Post {
+            expires: Some("2000-01-01".to_string()),
+            ..make_post("old-announcement", "Old announcement", None, None)
+        };
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(expired);
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("/posts/first-post"));
+        assert!(!body.contains("/posts/old-announcement"));
+
+        let (status, _) = get_body(app.clone(), get("/posts/old-announcement")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get_body(app, get("/api/posts/old-announcement")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn expired_posts_can_show_a_notice_instead_of_404() {
+        let expired = Post {
+            expires: Some("2000-01-01".to_string()),
+            ..make_post("old-announcement", "Old announcement", None, None)
+        };
+        let mut state = Arc::into_inner(test_router_state().app_state).expect("sole state owner");
+        state.expired_posts = ExpiredPosts::Notice;
+        state.posts.get_mut().push(expired);
+        let app = setup_router(RouterState {
+            app_state: Arc::new(state),
+        });
+
+        let (status, body) = get_body(app, get("/posts/old-announcement")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("This post expired on 2000-01-01."));
+        assert!(!body.contains("href=\"/posts/old-announcement\""));
+    }
+
+    #[test]
+    fn expired_posts_mode_parses_notice_and_defaults_to_not_found() {
+        assert_eq!(ExpiredPosts::parse(Some("notice")), ExpiredPosts::Notice);
+        assert_eq!(ExpiredPosts::parse(Some("404")), ExpiredPosts::NotFound);
+        assert_eq!(ExpiredPosts::parse(None), ExpiredPosts::NotFound);
+    }
+
+    #[tokio::test]
+    async fn mixed_case_post_urls_redirect_to_lowercase_slug_when_enabled() {
+        let (status, _) =
+            get_body(setup_router(test_router_state()), get("/posts/First-Post")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let mut state = Arc::into_inner(test_router_state().app_state).expect("sole state owner");
+        state.case_insensitive_slugs = true;
+        let app = setup_router(RouterState {
+            app_state: Arc::new(state),
+        });
+        let response = app
+            .clone()
+            .oneshot(get("/posts/First-Post"))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers()[axum::http::header::LOCATION],
+            "/posts/first-post"
+        );
+
+        let response = app
+            .oneshot(get("/posts/First-Post?utm_source=mail"))
+            .await
+            .expect("serve request");
+        assert_eq!(
+            response.headers()[axum::http::header::LOCATION],
+            "/posts/first-post?utm_source=mail"
+        );
+    }
+
+    #[tokio::test]
+    async fn txt_suffix_serves_post_as_plain_text() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(Post {
+            markdown_body: "## Intro\n\nSome *emphasis* and a [link](https://example.com).\n\n<div class=\"x\">Raw</div>".to_string(),
+            ..make_post("plain", "Plain & simple", None, None)
+        });
+        let app = setup_router(router_state);
+        let response = app
+            .clone()
+            .oneshot(get("/posts/plain.txt"))
+            .await
+            .expect("serve request");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[axum::http::header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(body.starts_with("Plain & simple\n2026-01-01\n\n"));
+        assert!(body.contains("Some emphasis and a link."));
+        assert!(!body.contains('<'));
+
+        let (status, _) = get_body(app, get("/posts/missing.txt")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn print_route_renders_post_without_navigation() {
+        let (status, body) = get_body(
+            setup_router(test_router_state()),
+            get("/posts/first-post/print"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<body class=\"print\">"));
+        assert!(body.contains("<h1>First post</h1>"));
+        assert!(!body.contains("sidebar-post-link"));
+        assert!(!body.contains("<header>banner</header>"));
+
+        let router_state = test_router_state();
+        *router_state.app_state.print_layout_html.write().await =
+            "<html><body class=\"{{ body_class }}\"><article>{{ content }}</article></body></html>"
+                .to_string();
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/posts/first-post/print")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<article><main id=\"content\">"));
+        assert!(body.contains("<h1>First post</h1>"));
+
+        let (status, _) = get_body(app, get("/posts/missing/print")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn date_based_post_url_pattern_routes_and_links() {
+        let router_state = test_router_state();
+        router_state.app_state.site_config.write().await.post_urls =
+            PostUrlPattern::parse("/{year}/{month}/{slug}").expect("pattern");
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/2026/03/first-post")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<h1>First post</h1>"));
+        assert!(body.contains("href=\"/2026/03/first-post\" class=\"sidebar-post-link\""));
+
+        for old in ["/posts/first-post", "/2025/12/first-post"] {
+            let response = app.clone().oneshot(get(old)).await.expect("serve request");
+            assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY, "{old}");
+            assert_eq!(
+                response.headers()[axum::http::header::LOCATION],
+                "/2026/03/first-post"
+            );
+        }
+        let response = app
+            .clone()
+            .oneshot(get("/posts/first-post?partial=1"))
+            .await
+            .expect("serve request");
+        assert_eq!(
+            response.headers()[axum::http::header::LOCATION],
+            "/2026/03/first-post?partial=1"
+        );
+
+        let (status, _) = get_body(app, get("/2026/03/missing")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn accented_slugs_round_trip_through_links_and_routing() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(make_post(
+            "café-crème",
+            "Café crème",
+            None,
+            None,
+        ));
+        let app = setup_router(router_state);
+
+        let (status, home) = get_body(app.clone(), get("/")).await;
+        assert_eq!(status, StatusCode::OK);
+        let href = "/posts/caf%C3%A9-cr%C3%A8me";
+        assert!(home.contains(&format!("href=\"{href}\"")));
+
+        let (status, body) = get_body(app, get(href)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<h1>Café crème</h1>"));
+    }
+
+    #[tokio::test]
+    async fn toc_placeholder_is_filled_only_for_posts_that_opt_in() {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            *app_state.layout_html.write().await =
+                "<html><body><aside>{{ toc }}</aside><main>{{ content }}</main></body></html>"
+                    .to_string();
+            let mut posts = app_state.posts.write().await;
+            posts.push(Post {
+                toc: true,
+                markdown_body: "## Setup\n\nText.".to_string(),
+                ..make_post("with-toc", "With TOC", None, None)
+            });
+            posts.push(Post {
+                markdown_body: "## Setup\n\nText.".to_string(),
+                ..make_post("without-toc", "Without TOC", None, None)
+            });
+        }
+        let app = setup_router(router_state);
+        let (_, body) = get_body(app.clone(), get("/posts/with-toc")).await;
+        assert!(body.contains("<aside><nav class=\"post-toc\""));
+        assert!(body.contains("<a href=\"#setup\">Setup</a>"));
+        assert!(body.contains("<h2 id=\"setup\">Setup</h2>"));
+
+        let (_, body) = get_body(app, get("/posts/without-toc")).await;
+        assert!(body.contains("<aside></aside>"));
+        assert!(body.contains("<h2>Setup</h2>"));
+    }
+
+    #[tokio::test]
+    async fn og_type_comes_from_front_matter_or_page_type() {
+        let router_state = test_router_state();
+        *router_state.app_state.layout_html.write().await = content_layout().to_string();
+        router_state.app_state.posts.write().await.push(Post {
+            og_type: Some("profile".to_string()),
+            ..make_post("about", "About", None, None)
+        });
+        let app = setup_router(router_state);
+        let og_type = |body: &str| {
+            body.split("<meta property=\"og:type\" content=\"")
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .map(ToString::to_string)
+        };
 
-    fn test_layout() -> &'static str {
-        "<html><head><title>{{ page_title }}</title><meta name=\"description\" content=\"{{ page_description }}\" /><meta name=\"author\" content=\"{{ page_author }}\" /><meta property=\"og:title\" content=\"{{ page_title }}\" /><meta property=\"og:description\" content=\"{{ page_description }}\" /><meta property=\"og:url\" content=\"{{ page_url }}\" /><meta property=\"og:image\" content=\"{{ page_image }}\" />{{ page_published_time_meta }}{{ page_role_meta }}<meta name=\"twitter:title\" content=\"{{ page_title }}\" /><meta name=\"twitter:description\" content=\"{{ page_description }}\" /><meta name=\"twitter:image\" content=\"{{ page_image }}\" /></head><body>{{ banner }}<main>{{ content }}</main><ul>{{ posts }}</ul></body></html>"
+        for (uri, expected) in [
+            ("/posts/about", "profile"),
+            ("/posts/first-post", "article"),
+            ("/", "website"),
+        ] {
+            let (status, body) = get_body(app.clone(), get(uri)).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert_eq!(og_type(&body).as_deref(), Some(expected), "{uri}");
+        }
     }
 
-    fn content_layout() -> &'static str {
-        include_str!("../content/layout.html")
+    #[tokio::test]
+    async fn show_banner_false_leaves_banner_out_of_the_page() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(Post {
+            show_banner: Some(false),
+            ..make_post("landing", "Landing", None, None)
+        });
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/posts/landing")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.contains("<header>banner</header>"));
+        assert!(body.contains("<h1>Landing</h1>"));
+
+        let (_, body) = get_body(app, get("/posts/first-post")).await;
+        assert!(body.contains("<header>banner</header>"));
     }
 
-    #[test]
-    fn content_layout_contains_new_meta_placeholders() {
-        let layout = include_str!("../content/layout.html");
-        assert!(layout.contains("{{ page_author }}"));
-        assert!(layout.contains("{{ page_published_time_meta }}"));
+    #[tokio::test]
+    async fn placeholders_written_in_post_content_render_literally() {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            *app_state.layout_html.write().await =
+                "<html><body><ul>{{ posts }}</ul>{{ toc }}<main>{{ content }}</main>{{ comments }}</body></html>"
+                    .to_string();
+            app_state.posts.write().await.push(Post {
+                toc: true,
+                markdown_body:
+                    "## The {{ posts }} slot\n\nWrite {{ posts }} or {{ content }} in the layout."
+                        .to_string(),
+                ..make_post("templating", "Templating", None, None)
+            });
+        }
+        let app = setup_router(router_state);
+
+        let (status, body) = get_body(app, get("/posts/templating")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<p>Write {{ posts }} or {{ content }} in the layout.</p>"));
+        assert!(body.contains(">The {{ posts }} slot</a>"));
+        assert_eq!(body.matches("class=\"sidebar-post-link\"").count(), 2);
     }
 
     #[test]
-    fn content_templates_use_site_placeholders() {
-        let layout = include_str!("../content/layout.html");
-        let banner = include_str!("../content/banner.html");
-        assert!(layout.contains("{{ site_og_name }}"));
-        assert!(banner.contains("{{ site_title }}"));
+    fn fill_placeholders_leaves_unknown_names_alone() {
+        assert_eq!(
+            fill_placeholders(
+                "{{ a }}-{{b}}-{{ c }}-{{ open",
+                &[("a", "{{ b }}"), ("b", "B")]
+            ),
+            "{{ b }}-B-{{ c }}-{{ open"
+        );
     }
 
-    fn test_posts() -> Vec<Post> {
-        vec![Post {
-            title: "First post".to_string(),
-            slug: "first-post".to_string(),
-            date: "2026-03-04".to_string(),
-            description: None,
-            image: None,
-            role: None,
-            subtitle: None,
-            markdown_body: "Body".to_string(),
-        }]
+    #[tokio::test]
+    async fn category_pages_list_their_posts_and_uncategorized_ones() {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            let mut posts = app_state.posts.write().await;
+            posts.push(Post {
+                category: Some("Rust Notes".to_string()),
+                ..make_post("borrowing", "Borrowing", None, None)
+            });
+            *app_state.category_index.write().await = build_category_index(&posts);
+        }
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/categories/rust-notes")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<h1 class=\"category-title\">Rust Notes</h1>"));
+        assert!(body.contains("<body class=\"page-category\">"));
+        let main = body
+            .split("<main>")
+            .nth(1)
+            .and_then(|rest| rest.split("</main>").next())
+            .expect("main content");
+        assert!(main.contains("href=\"/posts/borrowing\""));
+        assert!(!main.contains("href=\"/posts/first-post\""));
+
+        let (status, body) = get_body(app.clone(), get("/categories/uncategorized")).await;
+        assert_eq!(status, StatusCode::OK);
+        let main = body
+            .split("<main>")
+            .nth(1)
+            .and_then(|rest| rest.split("</main>").next())
+            .expect("main content");
+        assert!(main.contains("href=\"/posts/first-post\""));
+        assert!(!main.contains("href=\"/posts/borrowing\""));
+
+        let (status, _) = get_body(app, get("/categories/missing")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
     }
 
-    fn test_meta() -> PageMeta {
-        PageMeta {
-            title: "Test title".to_string(),
-            description: "Test description".to_string(),
-            url: "https://example.com/posts/test".to_string(),
-            image: "https://example.com/static/test.png".to_string(),
-            author: "Daniel Vianna".to_string(),
-            published_time: Some("2026-03-04T00:00:00Z".to_string()),
-            role: Some("mechanism".to_string()),
+    #[tokio::test]
+    async fn post_titles_and_dates_are_escaped_in_header_and_listing() {
+        let router_state = test_router_state();
+        {
+            let mut posts = router_state.app_state.posts.write().await;
+            posts[0].title = "Rust & <C++>".to_string();
+            posts[0].date = "2026-03-04 <b>".to_string();
         }
-    }
+        let app = setup_router(router_state);
 
-    #[test]
-    fn site_config_defaults_match_content_defaults() {
-        let config = SiteConfig::default();
-        assert_eq!(config.title, "Bon Élève Blog");
-        assert_eq!(config.author, "Daniel Vianna");
+        let (status, body) = get_body(app, get("/posts/first-post")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<h1>Rust &amp; &lt;C++&gt;</h1>"));
+        assert!(body.contains("<p class=\"post-date\">2026-03-04 &lt;b&gt;</p>"));
+        assert!(body.contains("<span class=\"sidebar-post-title\">Rust &amp; &lt;C++&gt;</span>"));
+        assert!(!body.contains("<C++>"));
     }
 
-    #[test]
-    fn injects_hot_reload_script_once_in_development() {
-        let page = render_with_layout(
-            content_layout(),
-            "<header>banner</header>",
-            "content",
-            &test_posts(),
-            &test_meta(),
-            true,
-        );
-        assert_eq!(page.matches("new EventSource").count(), 1);
-        assert!(page.contains("window.__hotReloadController"));
-        assert!(page.contains("/static/tailwind.css?v="));
-    }
+    #[tokio::test]
+    async fn tag_pages_list_only_their_posts() {
+        let router_state = test_router_state();
+        {
+            let mut posts = router_state.app_state.posts.write().await;
+            posts[0].tags = vec!["Rust".to_string()];
+            posts.push(Post {
+                tags: vec!["rust".to_string(), "async".to_string()],
+                ..make_post("borrowing", "Borrowing", None, None)
+            });
+            posts.push(Post {
+                tags: vec!["python".to_string()],
+                ..make_post("snakes", "Snakes", None, None)
+            });
+            *router_state.app_state.tag_index.write().await = build_tag_index(&posts);
+        }
+        let app = setup_router(router_state);
+        let main = |body: &str| {
+            body.split("<main>")
+                .nth(1)
+                .and_then(|rest| rest.split("</main>").next())
+                .expect("main content")
+                .to_string()
+        };
 
-    #[test]
-    fn hot_reload_script_uses_devloop_event_stream_listener() {
-        assert!(HOT_RELOAD_SCRIPT.contains("new EventSource(devloopEventsUrl)"));
-        assert!(HOT_RELOAD_SCRIPT.contains("triggerReload()"));
-        assert!(HOT_RELOAD_SCRIPT.contains("reportCurrentPath"));
+        let (status, body) = get_body(app.clone(), get("/tags/rust")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<body class=\"page-tag\">"));
+        let listing = main(&body);
+        assert!(listing.contains("<h1 class=\"tag-title\">Rust</h1>"));
+        assert!(listing.contains("href=\"/posts/first-post\""));
+        assert!(listing.contains("href=\"/posts/borrowing\""));
+        assert!(!listing.contains("href=\"/posts/snakes\""));
+
+        let (status, body) = get_body(app.clone(), get("/tags")).await;
+        assert_eq!(status, StatusCode::OK);
+        let index = main(&body);
+        assert!(index.contains("<a href=\"/tags/rust\" class=\"tag-index-link\">Rust</a> <span class=\"tag-index-count\">2</span>"));
+        assert!(index.contains("<a href=\"/tags/python\" class=\"tag-index-link\">python</a> <span class=\"tag-index-count\">1</span>"));
+
+        let (status, _) = get_body(app, get("/tags/missing")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
     }
 
-    #[test]
-    fn injects_script_at_end_when_body_tag_is_missing() {
-        let layout = "<html><div>{{ banner }}</div><main>{{ content }}</main></html>";
-        let page = render_with_layout(
-            layout,
-            "banner",
-            "content",
-            &test_posts(),
-            &test_meta(),
-            true,
+    #[tokio::test]
+    async fn post_pages_fill_the_reading_time_placeholder() {
+        let content = TestContentDir::new();
+        content.write(
+            "layout.html",
+            "<html><body><p class=\"meta\">{{ reading_time }}</p><main>{{ content }}</main></body></html>",
         );
-        assert!(page.ends_with("</script>"));
-        assert_eq!(page.matches("new EventSource").count(), 1);
+        content.write("fragments/more.md", &"word ".repeat(250));
+        content.write_post(
+            "first-post.md",
+            &format!(
+                "---\ntitle: \"First post\"\ndate: 2026-03-04\nslug: first-post\nincludes: [more.md]\n---\n{}",
+                "word ".repeat(200)
+            ),
+        );
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        assert_eq!(loaded.posts[0].reading_minutes, 3);
+        let app = setup_router(build_router_state(loaded, Environment::Production, None));
+        let (_, body) = get_body(app.clone(), get("/posts/first-post")).await;
+        assert!(body.contains("<p class=\"meta\">3 min read</p>"));
+        let (_, body) = get_body(app, get("/")).await;
+        assert!(body.contains("<p class=\"meta\"></p>"));
     }
 
-    #[test]
-    fn does_not_inject_script_in_non_development() {
-        let page = render_with_layout(
-            content_layout(),
-            "banner",
-            "content",
-            &test_posts(),
-            &test_meta(),
-            false,
-        );
-        assert_eq!(page.matches("new EventSource").count(), 0);
-        assert!(page.contains("href=\"/static/tailwind.css\""));
+    #[tokio::test]
+    async fn comments_enabled_posts_include_embed_with_slug() {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            *app_state.layout_html.write().await =
+                "<html><body><main>{{ content }}{{ comments }}</main></body></html>".to_string();
+            app_state.site_config.write().await.comments_embed =
+                Some("<script data-thread=\"{{slug}}\"></script>".to_string());
+            let mut posts = app_state.posts.write().await;
+            posts.push(Post {
+                comments: Some(true),
+                ..make_post("with-comments", "With comments", None, None)
+            });
+        }
+        let app = setup_router(router_state);
+        let (_, body) = get_body(app.clone(), get("/posts/with-comments")).await;
+        assert!(body.contains(
+            "<section class=\"post-comments\"><script data-thread=\"with-comments\"></script></section>"
+        ));
+
+        let (_, body) = get_body(app.clone(), get("/posts/first-post")).await;
+        assert!(!body.contains("post-comments"));
+        assert!(!body.contains("{{ comments }}"));
+
+        let (_, body) = get_body(app, get("/posts/missing")).await;
+        assert!(!body.contains("{{ comments }}"));
     }
 
-    #[test]
-    fn development_pages_cache_bust_tailwind_stylesheet() {
-        let page = render_with_layout(
-            content_layout(),
-            "banner",
-            "content",
-            &test_posts(),
-            &test_meta(),
-            true,
+    #[tokio::test]
+    async fn last_updated_footer_falls_back_to_file_mtime() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "fresh.md",
+            "---\ntitle: \"Fresh\"\ndate: 2026-01-02\nslug: fresh\n---\nBody.",
         );
+        let modified = std::fs::metadata(content.path().join("posts/fresh.md"))
+            .and_then(|metadata| metadata.modified())
+            .expect("post mtime");
+        let expected = chrono::DateTime::<chrono::Utc>::from(modified)
+            .format("%Y-%m-%d")
+            .to_string();
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load content");
 
-        assert!(page.contains("href=\"/static/tailwind.css?v="));
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            app_state.site_config.write().await.show_last_updated = true;
+            app_state.posts.write().await.extend(loaded.posts);
+        }
+        let app = setup_router(router_state);
+
+        let (status, body) = get_body(app, get("/posts/fresh")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(&format!(
+            "<footer class=\"post-footer\"><p class=\"post-last-updated\">Last updated: {expected}</p></footer>"
+        )));
     }
 
-    #[test]
-    fn render_hot_reload_script_injects_devloop_events_url_when_present() {
-        let _guard = TestEnvGuard::set([(
-            "DEVLOOP_BROWSER_EVENTS_URL",
-            Some("http://127.0.0.1:4455/browser-events"),
-        )]);
+    #[tokio::test]
+    async fn edit_link_points_at_the_post_source_file() {
+        let content = TestContentDir::new();
+        content.write_post(
+            "rust/intro notes.md",
+            "---\ntitle: \"Intro\"\ndate: 2026-01-02\nslug: intro\n---\nBody.",
+        );
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load content");
 
-        let script = render_hot_reload_script();
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            app_state.site_config.write().await.edit_base_url =
+                Some("https://github.com/owner/blog/edit/main/content".to_string());
+            app_state.posts.write().await.extend(loaded.posts);
+        }
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/posts/intro")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(
+            "<p class=\"post-edit-link\"><a href=\"https://github.com/owner/blog/edit/main/content/posts/rust/intro%20notes.md\">Edit this page</a></p>"
+        ));
 
-        assert!(script.contains("\"http://127.0.0.1:4455/browser-events\""));
-        assert!(!script.contains("__DEVLOOP_BROWSER_EVENTS_URL__"));
+        let (_, body) = get_body(app, get("/posts/intro/print")).await;
+        assert!(!body.contains("post-edit-link"));
     }
 
-    #[test]
-    fn render_hot_reload_script_falls_back_to_null_when_devloop_events_url_is_missing() {
-        let _guard = TestEnvGuard::set([("DEVLOOP_BROWSER_EVENTS_URL", None)]);
+    async fn homepage_in_mode(home_mode: HomeMode) -> String {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            app_state.site_config.write().await.home_mode = home_mode;
+            let mut posts = app_state.posts.write().await;
+            posts[0].description = Some("Latest excerpt.".to_string());
+            posts.push(Post {
+                description: Some("Older excerpt.".to_string()),
+                markdown_body: "Older body".to_string(),
+                ..make_post("older-post", "Older post", None, None)
+            });
+        }
+        let app = setup_router(router_state);
 
-        let script = render_hot_reload_script();
+        let (status, body) = get_body(app, get("/")).await;
+        assert_eq!(status, StatusCode::OK);
+        body
+    }
 
-        assert!(script.contains("const devloopEventsUrl = null;"));
+    #[tokio::test]
+    async fn homepage_shows_home_page_by_default() {
+        let body = homepage_in_mode(HomeMode::Page).await;
+        assert!(body.contains("Home</h1>"));
+        assert!(!body.contains("<p>Body</p>"));
+        assert!(!body.contains("home-recent-posts"));
     }
 
-    #[test]
-    fn does_not_replace_posts_placeholder_inside_content() {
-        let content = "<p>literal {{ posts }}</p>";
-        let page = render_with_layout(
-            test_layout(),
-            "banner",
-            content,
-            &test_posts(),
-            &test_meta(),
-            false,
-        );
-        assert!(page.contains("<p>literal {{ posts }}</p>"));
+    #[tokio::test]
+    async fn homepage_latest_mode_shows_newest_post_in_full() {
+        let body = homepage_in_mode(HomeMode::Latest).await;
+        assert!(body.contains("<p>Body</p>"));
+        assert!(!body.contains("Older body"));
+        assert!(!body.contains("Home</h1>"));
     }
 
-    #[test]
-    fn renders_page_meta_tags_from_meta_input() {
-        let meta = test_meta();
-        let page = render_with_layout(
-            test_layout(),
-            "banner",
-            "content",
-            &test_posts(),
-            &meta,
-            false,
-        );
+    #[tokio::test]
+    async fn homepage_list_mode_shows_recent_excerpts_newest_first() {
+        let body = homepage_in_mode(HomeMode::List).await;
+        let latest = body
+            .find("<li><a href=\"/posts/first-post\" class=\"home-recent-post-link\">First post</a> <span class=\"home-recent-post-date\">2026-03-04</span><p class=\"home-recent-post-excerpt\">Latest excerpt.</p></li>")
+            .expect("latest post");
+        let older = body.find("Older excerpt.").expect("older post");
+        assert!(latest < older);
+        assert!(!body.contains("Home</h1>"));
+    }
 
-        assert!(page.contains("<title>Test title</title>"));
-        assert!(page.contains("<meta property=\"og:title\" content=\"Test title\" />"));
-        assert!(page.contains("<meta property=\"og:description\" content=\"Test description\" />"));
-        assert!(page
-            .contains("<meta property=\"og:url\" content=\"https://example.com/posts/test\" />"));
-        assert!(page.contains(
-            "<meta property=\"og:image\" content=\"https://example.com/static/test.png\" />"
-        ));
-        assert!(page.contains("<meta name=\"twitter:title\" content=\"Test title\" />"));
-        assert!(page.contains("<meta name=\"author\" content=\"Daniel Vianna\" />"));
-        assert!(page.contains(
-            "<meta property=\"article:published_time\" content=\"2026-03-04T00:00:00Z\" />"
-        ));
-        assert!(page.contains("<meta property=\"article:section\" content=\"mechanism\" />"));
+    #[tokio::test]
+    async fn homepage_lists_undated_posts_after_dated_ones() {
+        let content = TestContentDir::new();
+        for (slug, date) in [("dated", "2026-03-01"), ("undated", "someday")] {
+            content.write_post(
+                &format!("{slug}.md"),
+                &format!(
+                    "---\ntitle: \"{slug}\"\ndate: {date}\nslug: {slug}\n---\nBody of {slug}."
+                ),
+            );
+        }
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let router_state = build_router_state(loaded, Environment::Production, None);
+        router_state.app_state.site_config.write().await.home_mode = HomeMode::List;
+        let (_, body) = get_body(setup_router(router_state.clone()), get("/")).await;
+        let dated = body.find(">dated</a>").expect("dated post");
+        let undated = body.find(">undated</a>").expect("undated post");
+        assert!(dated < undated);
+
+        router_state.app_state.site_config.write().await.home_mode = HomeMode::Latest;
+        let (_, body) = get_body(setup_router(router_state), get("/")).await;
+        assert!(body.contains("Body of dated."));
+        assert!(!body.contains("Body of undated."));
     }
 
-    #[test]
-    fn validates_slug_characters_for_post_paths() {
-        assert!(is_valid_post_slug(
-            "2026-03-03-optimise-for-the-cheapest-change"
-        ));
-        assert!(!is_valid_post_slug("../secrets"));
-        assert!(!is_valid_post_slug("post/with/slash"));
-        assert!(!is_valid_post_slug("UPPERCASE"));
-        assert!(!is_valid_post_slug("bad_slug"));
+    #[tokio::test]
+    async fn latest_partial_lists_the_newest_posts_up_to_count() {
+        let router_state = test_router_state();
+        {
+            // In the order the loader leaves them: newest first.
+            let dated = |slug: &str, date: &str| Post {
+                date: date.to_string(),
+                ..make_post(slug, slug, None, None)
+            };
+            let mut posts = router_state.app_state.posts.write().await;
+            let first = posts.remove(0);
+            *posts = vec![
+                dated("apr", "2026-04-01"),
+                first,
+                dated("feb", "2026-02-01"),
+                dated("jan", "2026-01-15"),
+            ];
+        }
+        let app = setup_router(router_state);
+        let links = |body: &str| -> Vec<String> {
+            body.split("class=\"home-recent-post-link\">")
+                .skip(1)
+                .filter_map(|rest| rest.split('<').next())
+                .map(ToString::to_string)
+                .collect()
+        };
+        let (status, body) = get_body(app.clone(), get("/partials/latest?count=3")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("<ul class=\"home-recent-posts\">"));
+        assert!(!body.contains("<html"));
+        assert_eq!(links(&body), ["apr", "First post", "feb"]);
+
+        let (_, body) = get_body(app.clone(), get("/partials/latest?count=0")).await;
+        assert_eq!(links(&body), ["apr"]);
+        let (_, body) = get_body(app, get("/partials/latest?count=lots")).await;
+        assert_eq!(links(&body).len(), 4);
     }
 
-    #[test]
-    fn normalize_browser_path_accepts_root_and_post_paths() {
-        assert_eq!(normalize_browser_path("/").as_deref(), Some("/"));
-        assert_eq!(
-            normalize_browser_path("/posts/example-post").as_deref(),
-            Some("/posts/example-post")
-        );
-        assert_eq!(
-            normalize_browser_path("/posts/example-post/?x=1#frag").as_deref(),
-            Some("/posts/example-post")
-        );
+    #[tokio::test]
+    async fn latest_partial_follows_the_loader_order() {
+        let content = TestContentDir::new();
+        for (slug, date) in [
+            ("old", "2026-01-01"),
+            ("new", "2026-03-01"),
+            ("undated", "someday"),
+        ] {
+            content.write_post(
+                &format!("{slug}.md"),
+                &format!("---\ntitle: \"{slug}\"\ndate: {date}\nslug: {slug}\n---\nBody."),
+            );
+        }
+        let titles = |body: &str| -> Vec<String> {
+            body.split("class=\"home-recent-post-link\">")
+                .skip(1)
+                .filter_map(|rest| rest.split('<').next())
+                .map(ToString::to_string)
+                .collect()
+        };
+
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let app = setup_router(build_router_state(loaded, Environment::Production, None));
+        let (_, body) = get_body(app, get("/partials/latest?count=3")).await;
+        assert_eq!(titles(&body), ["new", "old", "undated"]);
+
+        content.write_post("order.txt", "old\n");
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
+            .await
+            .expect("load");
+        let app = setup_router(build_router_state(loaded, Environment::Production, None));
+        let (_, body) = get_body(app, get("/partials/latest?count=3")).await;
+        assert_eq!(titles(&body), ["old", "new", "undated"]);
     }
 
     #[test]
-    fn normalize_browser_path_rejects_invalid_inputs() {
-        assert_eq!(normalize_browser_path("").as_deref(), None);
-        assert_eq!(normalize_browser_path("posts/example").as_deref(), None);
-        assert_eq!(normalize_browser_path("//example.com").as_deref(), None);
+    fn home_mode_reads_env_and_ignores_unknown_values() {
+        for (raw, expected) in [("List", HomeMode::List), ("feed", HomeMode::Page)] {
+            let _env = TestEnvGuard::set([("HOME_MODE", Some(raw))]);
+            assert_eq!(HomeMode::from_env(), expected, "{raw}");
+        }
     }
 
-    #[test]
-    fn default_rust_log_uses_info_when_unset() {
-        let _guard = TestEnvGuard::set([("RUST_LOG", None)]);
-        assert_eq!(default_rust_log(), "info");
+    async fn post_index_titles(uri: &str) -> Vec<String> {
+        let router_state = test_router_state();
+        {
+            let mut posts = router_state.app_state.posts.write().await;
+            posts.push(Post {
+                date: "2026-02-01".to_string(),
+                markdown_body: "word ".repeat(900),
+                reading_minutes: 5,
+                ..make_post("banana", "banana bread", None, None)
+            });
+            posts.push(Post {
+                date: "2026-03-10".to_string(),
+                ..make_post("apple", "Apple pie", None, None)
+            });
+        }
+        let app = setup_router(router_state);
+
+        let (status, body) = get_body(app, get(uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("page-post-index"));
+        body.split("class=\"post-index-link\">")
+            .skip(1)
+            .map(|item| item.split('<').next().unwrap_or_default().to_string())
+            .collect()
     }
 
-    #[test]
-    fn default_rust_log_respects_environment_override() {
-        let _guard = TestEnvGuard::set([("RUST_LOG", Some("warn,gcp_rust_blog=debug"))]);
-        assert_eq!(default_rust_log(), "warn,gcp_rust_blog=debug");
+    #[tokio::test]
+    async fn feed_lists_posts_as_rss() {
+        let app = setup_router(test_router_state());
+        let response = app.oneshot(get("/feed.xml")).await.expect("serve request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[axum::http::header::CONTENT_TYPE],
+            "application/rss+xml"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let feed = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(feed.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\"><channel><title>"
+        ));
+        assert!(feed.contains("<item><title>First post</title><link>"));
+        assert!(feed
+            .contains("/posts/first-post</guid><pubDate>Wed, 4 Mar 2026 00:00:00 +0000</pubDate>"));
+        assert!(feed.ends_with("</channel></rss>\n"));
     }
 
-    #[test]
-    fn load_devloop_event_client_requires_url_and_token() {
+    #[tokio::test]
+    async fn feed_pages_link_their_neighbours() {
+        let router_state = test_router_state();
         {
-            let _guard = TestEnvGuard::set([
-                ("DEVLOOP_EVENT_BROWSER_PATH_URL", None),
-                ("DEVLOOP_EVENTS_TOKEN", None),
-            ]);
+            let app_state = &router_state.app_state;
+            app_state.site_config.write().await.feed_page_size = Some(2);
+            let mut posts = app_state.posts.write().await;
+            for slug in ["second", "third", "fourth", "fifth"] {
+                posts.push(make_post(slug, slug, None, None));
+            }
+        }
+        let app = setup_router(router_state);
+        let feed = |uri: &str| get_body(app.clone(), get(uri));
+        let titles = |body: &str| -> Vec<String> {
+            body.split("<item><title>")
+                .skip(1)
+                .filter_map(|rest| rest.split('<').next())
+                .map(ToString::to_string)
+                .collect()
+        };
+        // The host part follows `SITE_URL`, which other tests may change.
+        let link = |page: &str, rel: &str| {
+            format!("{page}\" rel=\"{rel}\" type=\"application/rss+xml\"/>")
+        };
 
-            assert!(load_devloop_event_client().is_none());
+        let (_, first) = feed("/feed.xml").await;
+        assert_eq!(titles(&first), ["First post", "second"]);
+        assert!(first.contains(&link("/feed.xml?page=2", "next")));
+        assert!(!first.contains("rel=\"previous\""));
+
+        let (_, middle) = feed("/feed.xml?page=2").await;
+        assert_eq!(titles(&middle), ["third", "fourth"]);
+        assert!(middle.contains(&link("/feed.xml", "previous")));
+        assert!(middle.contains(&link("/feed.xml?page=3", "next")));
+
+        let (_, last) = feed("/feed.xml?page=3").await;
+        assert_eq!(titles(&last), ["fifth"]);
+        assert!(last.contains(&link("/feed.xml?page=2", "previous")));
+        assert!(!last.contains("rel=\"next\""));
+
+        for uri in ["/feed.xml?page=4", "/feed.xml?page=0", "/feed.xml?page=x"] {
+            let (status, _) = feed(uri).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
         }
+    }
 
+    #[tokio::test]
+    async fn feed_self_link_matches_the_request_url() {
+        let router_state = test_router_state();
         {
-            let _guard = TestEnvGuard::set([
-                (
-                    "DEVLOOP_EVENT_BROWSER_PATH_URL",
-                    Some("http://127.0.0.1:1/events/browser_path"),
-                ),
-                ("DEVLOOP_EVENTS_TOKEN", None),
-            ]);
+            let app_state = &router_state.app_state;
+            app_state.site_config.write().await.feed_page_size = Some(1);
+            app_state
+                .posts
+                .write()
+                .await
+                .push(make_post("second", "second", None, None));
+        }
+        let app = setup_router(router_state);
 
-            assert!(load_devloop_event_client().is_none());
+        for uri in ["/feed.xml", "/feed.xml?page=2"] {
+            let (_, feed) = get_body(app.clone(), get(uri)).await;
+            let self_link = feed
+                .split("<atom:link href=\"")
+                .skip(1)
+                .find(|link| link.contains("rel=\"self\""))
+                .and_then(|link| link.split('"').next())
+                .expect("self link");
+            assert!(self_link.starts_with("http"), "{self_link}");
+            assert!(self_link.ends_with(uri), "{self_link}");
         }
+    }
 
+    #[tokio::test]
+    async fn feed_build_date_is_the_newest_post_date() {
+        let router_state = test_router_state();
         {
-            let _guard = TestEnvGuard::set([
-                (
-                    "DEVLOOP_EVENT_BROWSER_PATH_URL",
-                    Some("http://127.0.0.1:1/events/browser_path"),
-                ),
-                ("DEVLOOP_EVENTS_TOKEN", Some("secret")),
-            ]);
-            let client = load_devloop_event_client().expect("load client");
-            assert_eq!(
-                client.browser_path_url,
-                "http://127.0.0.1:1/events/browser_path"
-            );
-            assert_eq!(client.token, "secret");
+            let mut posts = router_state.app_state.posts.write().await;
+            posts.push(make_post("older", "Older", None, None));
+            posts.push(Post {
+                date: "someday".to_string(),
+                ..make_post("undated", "Undated", None, None)
+            });
         }
+        let app = setup_router(router_state);
+        let response = app.oneshot(get("/feed.xml")).await.expect("serve request");
+
+        assert_eq!(
+            response.headers()[axum::http::header::LAST_MODIFIED],
+            "Wed, 04 Mar 2026 00:00:00 GMT"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let feed = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(feed.contains(
+            "</description><lastBuildDate>Wed, 4 Mar 2026 00:00:00 +0000</lastBuildDate>"
+        ));
     }
 
-    #[tokio::test]
-    async fn publish_browser_path_event_sends_bearer_token_and_json_body() {
-        async fn capture_request(
-            State(tx): State<CapturedBrowserPathRequest>,
-            headers: HeaderMap,
-            Json(body): Json<Value>,
-        ) -> StatusCode {
-            let auth = headers
-                .get(axum::http::header::AUTHORIZATION)
-                .and_then(|value| value.to_str().ok())
-                .unwrap_or("")
-                .to_string();
-            if let Some(sender) = tx.lock().expect("lock capture sender").take() {
-                let _ = sender.send((auth, body));
+    /// Every tag closes in order and nothing but whitespace follows the
+    /// root element; enough to catch a feed broken by an empty listing.
+    fn assert_well_formed_xml(xml: &str) {
+        let body = xml
+            .strip_prefix("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")
+            .expect("XML declaration");
+        let mut open: Vec<&str> = Vec::new();
+        let mut rest = body;
+        let mut closed_root = false;
+        while let Some(start) = rest.find('<') {
+            assert!(!closed_root, "content after the root element: {xml}");
+            let end = rest[start..].find('>').expect("unterminated tag") + start;
+            let tag = &rest[start + 1..end];
+            let name = tag
+                .trim_start_matches('/')
+                .split([' ', '/'])
+                .next()
+                .unwrap_or_default();
+            if let Some(closing) = tag.strip_prefix('/') {
+                assert_eq!(open.pop(), Some(closing), "{xml}");
+            } else if !tag.ends_with('/') {
+                open.push(name);
             }
-            StatusCode::NO_CONTENT
+            closed_root = open.is_empty();
+            rest = &rest[end + 1..];
         }
+        assert!(open.is_empty() && closed_root, "{xml}");
+        assert!(rest.trim().is_empty(), "{xml}");
+    }
 
-        let (tx, rx) = oneshot::channel();
-        let shared_tx = Arc::new(Mutex::new(Some(tx)));
-        let app = Router::new()
-            .route("/events/browser_path", post(capture_request))
-            .with_state(shared_tx);
-        let listener = TcpListener::bind("127.0.0.1:0")
+    #[tokio::test]
+    async fn feed_and_sitemap_stay_well_formed_without_posts() {
+        let content = TestContentDir::new();
+        let loaded = load_content_from(&content.roots(), LoadOptions::default())
             .await
-            .expect("bind test listener");
-        let addr = listener.local_addr().expect("listener addr");
-        let server = tokio::spawn(async move {
-            axum::serve(listener, app).await.expect("serve test app");
-        });
+            .expect("load");
+        assert!(loaded.posts.is_empty());
+        let app = setup_router(build_router_state(loaded, Environment::Production, None));
 
-        let client = DevloopEventClient {
-            browser_path_url: format!("http://{addr}/events/browser_path"),
-            token: "secret".into(),
-            client: reqwest::Client::new(),
-        };
+        let (status, feed) = get_body(app.clone(), get("/feed.xml")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_well_formed_xml(&feed);
+        assert!(!feed.contains("<item>"));
+        assert!(!feed.contains("<lastBuildDate>"));
+        assert!(!feed.contains("rel=\"next\""));
 
-        publish_browser_path_event(&client, "/posts/example-post")
+        let (status, sitemap) = get_body(app, get("/sitemap.xml")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_well_formed_xml(&sitemap);
+        assert_eq!(sitemap.matches("<url>").count(), 1, "{sitemap}");
+    }
+
+    #[tokio::test]
+    async fn sitemap_lists_the_homepage_and_every_post() {
+        let router_state = test_router_state();
+        router_state
+            .app_state
+            .posts
+            .write()
             .await
-            .expect("publish browser path event");
+            .push(make_post("r&d-notes", "R&D", None, None));
+        let slugs: Vec<String> = router_state
+            .app_state
+            .posts
+            .read()
+            .await
+            .iter()
+            .map(|post| post.slug.clone())
+            .collect();
+        let app = setup_router(router_state);
+        let response = app
+            .oneshot(get("/sitemap.xml"))
+            .await
+            .expect("serve request");
 
-        let (auth, body) = rx.await.expect("receive request");
-        assert_eq!(auth, "Bearer secret");
-        assert_eq!(body["value"], Value::String("/posts/example-post".into()));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[axum::http::header::CONTENT_TYPE],
+            "application/xml"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let sitemap = String::from_utf8(body.to_vec()).expect("utf8 body");
+        assert!(sitemap
+            .contains("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\"><url><loc>"));
+        for slug in &slugs {
+            let path = crate::page_meta::post_path(slug);
+            assert!(sitemap.contains(&format!("{path}</loc>")), "{slug}");
+        }
+        assert!(sitemap.contains("/posts/first-post</loc><lastmod>2026-03-04</lastmod></url>"));
+    }
 
-        server.abort();
+    #[tokio::test]
+    async fn post_index_sorts_by_query() {
+        assert_eq!(
+            post_index_titles("/posts?sort=title&order=asc").await,
+            ["Apple pie", "banana bread", "First post"]
+        );
+        assert_eq!(
+            post_index_titles("/posts?sort=reading_time").await,
+            ["banana bread", "First post", "Apple pie"]
+        );
     }
 
-    fn make_post(slug: &str, title: &str, role: Option<&str>, subtitle: Option<&str>) -> Post {
-        Post {
-            slug: slug.to_string(),
-            title: title.to_string(),
-            date: "2026-01-01".to_string(),
-            role: role.map(ToString::to_string),
-            subtitle: subtitle.map(ToString::to_string),
-            description: None,
-            image: None,
-            markdown_body: String::new(),
+    #[tokio::test]
+    async fn post_index_date_sort_keeps_undated_posts_last() {
+        let router_state = test_router_state();
+        {
+            let mut posts = router_state.app_state.posts.write().await;
+            posts.insert(
+                0,
+                Post {
+                    date: "someday".to_string(),
+                    ..make_post("undated", "Undated", None, None)
+                },
+            );
+            posts.push(make_post("older", "Older", None, None));
         }
-    }
+        let app = setup_router(router_state);
+        let titles = |body: String| -> Vec<String> {
+            body.split("class=\"post-index-link\">")
+                .skip(1)
+                .map(|item| item.split('<').next().unwrap_or_default().to_string())
+                .collect()
+        };
 
-    fn test_router_state() -> RouterState {
-        let state = Arc::new(AppState {
-            site_config: RwLock::new(SiteConfig::default()),
-            banner_html: RwLock::new("<header>banner</header>".to_string()),
-            layout_html: RwLock::new(test_layout().to_string()),
-            home_post: RwLock::new(Post {
-                title: "Home".to_string(),
-                slug: "home".to_string(),
-                date: "2026-03-24".to_string(),
-                description: None,
-                image: None,
-                role: None,
-                subtitle: None,
-                markdown_body: "# Home".to_string(),
-            }),
-            current_browser_path: RwLock::new("/".to_string()),
-            devloop_event_client: None,
-            not_found_markdown: RwLock::new(
-                "# Terra incognita\n\nThis path is not mapped.".to_string(),
+        for (uri, expected) in [
+            ("/posts?sort=date", ["First post", "Older", "Undated"]),
+            (
+                "/posts?sort=date&order=asc",
+                ["Older", "First post", "Undated"],
             ),
-            posts: RwLock::new(test_posts()),
-            is_development: false,
-        });
+        ] {
+            let (_, body) = get_body(app.clone(), get(uri)).await;
+            assert_eq!(titles(body), expected, "{uri}");
+        }
+    }
 
-        RouterState { app_state: state }
+    #[tokio::test]
+    async fn post_index_falls_back_to_newest_first() {
+        let newest_first = ["Apple pie", "First post", "banana bread"];
+        assert_eq!(post_index_titles("/posts").await, newest_first);
+        assert_eq!(
+            post_index_titles("/posts?sort=views&order=sideways").await,
+            newest_first
+        );
     }
 
-    #[test]
-    fn groups_posts_by_role_in_first_seen_order() {
-        let posts = vec![
-            make_post("a", "A", Some("mechanism"), None),
-            make_post("b", "B", Some("strategy"), None),
-            make_post("c", "C", Some("mechanism"), None),
-        ];
-        let html = render_post_list(&posts);
-        let mech_pos = html.find("Mechanism").unwrap();
-        let strat_pos = html.find("Strategy").unwrap();
-        // Mechanism group header appears before Strategy
-        assert!(mech_pos < strat_pos);
-        // Post C (second mechanism post) appears after post A
-        let a_pos = html.find("/posts/a").unwrap();
-        let c_pos = html.find("/posts/c").unwrap();
-        assert!(a_pos < c_pos);
-        // All posts are present
-        assert!(html.contains("/posts/b"));
+    #[tokio::test]
+    async fn katex_stylesheet_is_linked_only_on_pages_with_math() {
+        let router_state = test_router_state();
+        {
+            let app_state = &router_state.app_state;
+            *app_state.layout_html.write().await = content_layout().to_string();
+            app_state.site_config.write().await.katex_css_url =
+                "/static/katex/katex.min.css".to_string();
+            app_state.posts.write().await.push(Post {
+                markdown_body: "Euler: $e^{i\\pi} + 1 = 0$".to_string(),
+                ..make_post("math", "Math", None, None)
+            });
+        }
+        let app = setup_router(router_state);
+        let katex_link = "<link rel=\"stylesheet\" href=\"/static/katex/katex.min.css\" />";
+
+        let (_, body) = get_body(app.clone(), get("/posts/math")).await;
+        assert!(body.contains(katex_link));
+
+        let (_, body) = get_body(app, get("/posts/first-post")).await;
+        assert!(!body.contains("katex.min.css"));
+        assert!(!body.contains("{{ math_css }}"));
     }
 
-    #[test]
-    fn escapes_html_in_sidebar_titles_and_subtitles() {
-        let posts = vec![make_post(
-            "xss",
-            "<script>alert(1)</script>",
-            Some("<role>"),
-            Some("<b>bad</b>"),
-        )];
-        let html = render_post_list(&posts);
-        assert!(!html.contains("<script>"));
-        assert!(!html.contains("<b>bad</b>"));
-        assert!(html.contains("&lt;script&gt;"));
-        assert!(html.contains("&lt;b&gt;"));
+    #[tokio::test]
+    async fn homepage_without_posts_shows_no_posts_message() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.clear();
+        let app = setup_router(router_state);
+
+        let (status, body) = get_body(app, get("/")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<ul><li class=\"sidebar-empty\">No posts yet.</li></ul>"));
     }
 
     #[test]
-    fn posts_without_role_render_without_group_header() {
-        let posts = vec![make_post("no-role", "No Role", None, None)];
-        let html = render_post_list(&posts);
-        assert!(!html.contains("sidebar-group-header"));
-        assert!(html.contains("/posts/no-role"));
+    fn listing_excerpts_end_with_configured_read_more_link() {
+        let mut post = make_post("a", "A", None, None);
+        post.description = Some("Short excerpt.".to_string());
+        let site_config = SiteConfig {
+            listing_excerpts: true,
+            read_more_text: "Continue reading".to_string(),
+            ..SiteConfig::default()
+        };
+
+        let html = render_post_list(std::slice::from_ref(&post), &site_config);
+        assert!(html.contains(
+            "<p class=\"sidebar-post-excerpt\">Short excerpt. <a href=\"/posts/a\" class=\"read-more-link\">Continue reading</a></p>"
+        ));
+
+        let default_html = render_post_list(std::slice::from_ref(&post), &SiteConfig::default());
+        assert!(!default_html.contains("read-more-link"));
     }
 
     #[tokio::test]
-    async fn missing_root_path_uses_not_found_page() {
-        let app = setup_router(test_router_state());
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/missing")
-                    .body(Body::empty())
-                    .expect("build request"),
-            )
-            .await
-            .expect("serve request");
+    async fn render_preview_is_only_routed_in_development() {
+        let preview = || {
+            Request::builder()
+                .method("POST")
+                .uri("/api/render")
+                .body(Body::from("# Hi\n\n\\(x^2\\)"))
+                .expect("build request")
+        };
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-        let body = to_bytes(response.into_body(), usize::MAX)
-            .await
-            .expect("read body");
-        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
-        assert!(body.contains("Terra incognita"));
-        assert!(body.contains("This path is not mapped."));
+        let (status, _) = get_body(setup_router(test_router_state()), preview()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let mut state = Arc::into_inner(test_router_state().app_state).expect("sole state owner");
+        state.environment = Environment::Development;
+        let app = setup_router(RouterState {
+            app_state: Arc::new(state),
+        });
+        let (status, body) = get_body(app, preview()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("<h1>Hi</h1>"));
+        assert!(body.contains("katex"));
     }
 
     #[tokio::test]
-    async fn missing_nested_path_uses_not_found_page() {
+    async fn body_class_reflects_page_type() {
         let app = setup_router(test_router_state());
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/any/other/path")
-                    .body(Body::empty())
-                    .expect("build request"),
-            )
-            .await
-            .expect("serve request");
+        let (_, home) = get_body(app.clone(), get("/")).await;
+        assert!(home.contains("<body class=\"page-home\">"));
+        let (_, post) = get_body(app.clone(), get("/posts/first-post")).await;
+        assert!(post.contains("<body class=\"page-post\">"));
+        let (_, missing) = get_body(app, get("/posts/missing")).await;
+        assert!(missing.contains("<body class=\"page-not-found\">"));
+    }
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-        let body = to_bytes(response.into_body(), usize::MAX)
+    #[tokio::test]
+    async fn slugs_listed_as_gone_answer_410_and_others_404() {
+        let router_state = test_router_state();
+        router_state
+            .app_state
+            .gone_slugs
+            .write()
             .await
-            .expect("read body");
-        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
-        assert!(body.contains("Terra incognita"));
+            .insert("removed-post".to_string());
+        let app = setup_router(router_state);
+        let (status, body) = get_body(app.clone(), get("/posts/removed-post")).await;
+        assert_eq!(status, StatusCode::GONE);
+        assert!(body.contains("<body class=\"page-gone\">"));
+        assert!(body.contains("<code>removed-post</code> has been removed"));
+
+        let (status, _) = get_body(app, get("/posts/never-existed")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn missing_post_slug_still_uses_not_found_page() {
-        let app = setup_router(test_router_state());
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/posts/does-not-exist")
-                    .body(Body::empty())
-                    .expect("build request"),
-            )
-            .await
-            .expect("serve request");
+    async fn cross_posted_posts_show_source_notice_and_canonical_link() {
+        let router_state = test_router_state();
+        router_state.app_state.posts.write().await.push(Post {
+            canonical_source: Some(CanonicalSource {
+                url: "https://medium.example/p/abc".to_string(),
+                name: "Medium".to_string(),
+            }),
+            ..make_post("cross-posted", "Cross posted", None, None)
+        });
+        let app = setup_router(router_state);
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-        let body = to_bytes(response.into_body(), usize::MAX)
-            .await
-            .expect("read body");
-        let body = String::from_utf8(body.to_vec()).expect("utf8 body");
-        assert!(body.contains("Terra incognita"));
+        let (status, body) = get_body(app, get("/posts/cross-posted")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(
+            "<p class=\"post-cross-post-notice\">Originally published at <a href=\"https://medium.example/p/abc\">Medium</a>.</p>"
+        ));
+        assert!(body.contains("<link rel=\"canonical\" href=\"https://medium.example/p/abc\" />"));
     }
 }
@@ -1,4 +1,12 @@
-use pulldown_cmark::{html, CowStr, Event, Options, Parser};
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use pulldown_cmark::{
+    html, CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd,
+};
+use tracing::warn;
+
+use crate::page_meta::{escape_html, slugify};
+use crate::sanitize::{is_unsafe_url, SanitizeOptions};
 
 fn markdown_options() -> Options {
     let mut options = Options::empty();
@@ -8,22 +16,673 @@ fn markdown_options() -> Options {
     options
 }
 
-pub fn render_markdown_to_html(markdown: &str) -> String {
-    let normalized_markdown = normalize_latex_delimiters(markdown);
-    let parser =
-        Parser::new_ext(&normalized_markdown, markdown_options()).map(|event| match event {
-            Event::InlineMath(math) => Event::Html(CowStr::Boxed(
-                render_math_html(&math, false).into_boxed_str(),
-            )),
-            Event::DisplayMath(math) => Event::Html(CowStr::Boxed(
-                render_math_html(&math, true).into_boxed_str(),
-            )),
-            other => other,
-        });
+/// Renderer switches resolved once at startup.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// Render single newlines inside paragraphs as `<br>` instead of the
+    /// CommonMark soft break.
+    pub hard_breaks: bool,
+    /// Emit delimited `<span class="math">` markup for a client-side KaTeX
+    /// script instead of rendering on the server. Set when the KaTeX
+    /// backend fails its startup probe.
+    pub client_side_math: bool,
+    /// Open external links in a new tab and mark them with an indicator.
+    pub external_link_icons: bool,
+    /// Have KaTeX emit MathML next to its HTML so screen readers can read
+    /// formulas, at the cost of noticeably larger pages.
+    pub mathml: bool,
+    /// Add `loading="lazy"` to images that do not set `loading` themselves.
+    pub lazy_images: bool,
+    /// Filter raw HTML written in posts down to these tags and attributes.
+    /// Markup the renderer itself produces is never filtered.
+    pub sanitize: Option<SanitizeOptions>,
+}
+
+impl RenderOptions {
+    pub fn from_env() -> Self {
+        Self {
+            hard_breaks: env_flag("MD_HARD_BREAKS"),
+            client_side_math: !server_math_available(katex::render("x")),
+            external_link_icons: env_flag("MD_EXTERNAL_LINK_ICONS"),
+            mathml: env_flag("MD_MATHML"),
+            lazy_images: env_flag("MD_LAZY_IMAGES"),
+            sanitize: env_flag("MD_SANITIZE").then(SanitizeOptions::from_env),
+        }
+    }
+
+    /// The enabled post-render transforms, in the order they run.
+    pub fn transforms(&self) -> Vec<HtmlTransform> {
+        let mut transforms: Vec<HtmlTransform> = Vec::new();
+        if self.lazy_images {
+            transforms.push(lazy_images);
+        }
+        transforms
+    }
+}
+
+/// A step over the finished HTML of a post. Features that need parser
+/// context (math, code blocks, links, sidenotes) stay in the event pass;
+/// anything that can work on the output alone belongs here.
+pub type HtmlTransform = fn(String) -> String;
+
+/// Runs `transforms` in order, each receiving the previous one's output.
+pub fn apply_transforms(html: String, transforms: &[HtmlTransform]) -> String {
+    transforms
+        .iter()
+        .fold(html, |html, transform| transform(html))
+}
+
+fn lazy_images(html: String) -> String {
+    const IMG_OPEN: &str = "<img ";
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find(IMG_OPEN) {
+        let (before, tag_and_rest) = rest.split_at(start);
+        out.push_str(before);
+        let end = tag_and_rest.find('>').map_or(tag_and_rest.len(), |i| i + 1);
+        let tag = &tag_and_rest[..end];
+        if tag.contains("loading=") {
+            out.push_str(tag);
+        } else {
+            out.push_str("<img loading=\"lazy\" ");
+            out.push_str(&tag[IMG_OPEN.len()..]);
+        }
+        rest = &tag_and_rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A KaTeX backend that cannot render a trivial expression will fail for
+/// every expression, so it is detected once here rather than degrading
+/// each formula silently.
+fn server_math_available(probe: katex::Result<String>) -> bool {
+    match probe {
+        Ok(_) => true,
+        Err(error) => {
+            warn!(
+                %error,
+                "KaTeX is unavailable; emitting client-side math markup. \
+                 Include the KaTeX auto-render script in layout.html to typeset it"
+            );
+            false
+        }
+    }
+}
+
+fn env_flag(key: &str) -> bool {
+    std::env::var(key)
+        .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+pub fn render_markdown_to_html(markdown: &str, options: &RenderOptions) -> String {
+    render_markdown(markdown, options, false).0
+}
+
+/// Like `render_markdown_to_html`, but gives `h2`/`h3` headings `id`s and
+/// also returns a table of contents linking to them (empty when the post
+/// has no such headings).
+pub fn render_markdown_with_toc(markdown: &str, options: &RenderOptions) -> (String, String) {
+    render_markdown(markdown, options, true)
+}
+
+/// KaTeX's message for each math expression in `markdown` that fails to
+/// typeset. Empty when KaTeX itself cannot run.
+pub fn math_errors(markdown: &str) -> Vec<String> {
+    let normalized_markdown = normalize_latex_delimiters(&markdown.replace("\r\n", "\n"));
+    Parser::new_ext(&normalized_markdown, markdown_options())
+        .filter_map(|event| match event {
+            Event::InlineMath(math) => Some((math, false)),
+            Event::DisplayMath(math) => Some((math, true)),
+            _ => None,
+        })
+        .filter_map(|(math, display_mode)| {
+            let opts = katex::Opts::builder()
+                .display_mode(display_mode)
+                .build()
+                .ok()?;
+            match katex::render_with_opts(&math, opts) {
+                Err(katex::Error::JsExecError(message)) => Some(format!("{message} in `{math}`")),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn render_markdown(markdown: &str, options: &RenderOptions, with_toc: bool) -> (String, String) {
+    let normalized_markdown = normalize_latex_delimiters(&isolate_collapsible_markers(
+        &markdown.replace("\r\n", "\n"),
+    ));
+    let mut events = Vec::new();
+    let mut highlighted_block: Option<HighlightedBlock> = None;
+    let mut in_external_link = false;
+    let mut raw_html = RawHtmlState::default();
+
+    for event in Parser::new_ext(&normalized_markdown, markdown_options()) {
+        let event = match &options.sanitize {
+            Some(sanitizer) => match raw_html.filter(sanitizer, event) {
+                Some(event) => event,
+                None => continue,
+            },
+            None => event,
+        };
+        if let Some(mut block) = highlighted_block.take() {
+            match event {
+                Event::Text(text) => {
+                    block.source.push_str(&text);
+                    highlighted_block = Some(block);
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    events.push(Event::Html(CowStr::Boxed(block.render().into_boxed_str())));
+                    events.push(Event::End(TagEnd::CodeBlock));
+                }
+                other => {
+                    events.push(other);
+                    highlighted_block = Some(block);
+                }
+            }
+            continue;
+        }
+
+        match event {
+            Event::SoftBreak if options.hard_breaks => events.push(Event::HardBreak),
+            Event::Start(Tag::Link {
+                dest_url, title, ..
+            }) if options.external_link_icons && is_external_url(&dest_url) => {
+                let title_attr = if title.is_empty() {
+                    String::new()
+                } else {
+                    format!(" title=\"{}\"", escape_html(&title))
+                };
+                events.push(Event::Html(CowStr::from(format!(
+                    "<a href=\"{}\"{title_attr} target=\"_blank\" rel=\"noopener noreferrer\">",
+                    escape_html(&dest_url)
+                ))));
+                in_external_link = true;
+            }
+            Event::End(TagEnd::Link) if in_external_link => {
+                events.push(Event::Html(CowStr::from(EXTERNAL_LINK_INDICATOR)));
+                in_external_link = false;
+            }
+            Event::InlineMath(math) => events.push(Event::Html(CowStr::Boxed(
+                render_math_html(&math, false, options).into_boxed_str(),
+            ))),
+            Event::DisplayMath(math) => events.push(Event::Html(CowStr::Boxed(
+                render_math_html(&math, true, options).into_boxed_str(),
+            ))),
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let info = CodeBlockInfo::parse(&info);
+                if let Some(filename) = &info.filename {
+                    events.push(Event::Html(CowStr::Boxed(
+                        format!(
+                            "<div class=\"code-filename\">{}</div>",
+                            escape_html(filename)
+                        )
+                        .into_boxed_str(),
+                    )));
+                }
+                events.push(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(
+                    CowStr::Boxed(info.lang.into_boxed_str()),
+                ))));
+                if let Some(lines) = info.highlight_lines {
+                    highlighted_block = Some(HighlightedBlock {
+                        lines,
+                        source: String::new(),
+                    });
+                }
+            }
+            other => events.push(other),
+        }
+    }
+
+    let mut events = apply_sidenotes(apply_collapsibles(events));
+    let toc_html = if with_toc {
+        render_toc(&assign_heading_ids(&mut events))
+    } else {
+        String::new()
+    };
 
     let mut html_out = String::new();
-    html::push_html(&mut html_out, parser);
-    html_out
+    html::push_html(&mut html_out, events.into_iter());
+    (apply_transforms(html_out, &options.transforms()), toc_html)
+}
+
+struct TocEntry {
+    level: HeadingLevel,
+    id: String,
+    text: String,
+}
+
+/// Sets a slug `id` on every `h2`/`h3` without an explicit one, suffixing
+/// repeats (`-2`, `-3`, ...) so anchors stay unique within the post.
+fn assign_heading_ids(events: &mut [Event<'_>]) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for index in 0..events.len() {
+        let Event::Start(Tag::Heading { level, id, .. }) = &events[index] else {
+            continue;
+        };
+        if !matches!(level, HeadingLevel::H2 | HeadingLevel::H3) {
+            continue;
+        }
+        let level = *level;
+        let explicit_id = id.as_ref().map(|id| id.to_string());
+
+        let mut text = String::new();
+        for event in &events[index + 1..] {
+            match event {
+                Event::End(TagEnd::Heading(_)) => break,
+                Event::Text(chunk) | Event::Code(chunk) => text.push_str(chunk),
+                _ => {}
+            }
+        }
+
+        let id = explicit_id.unwrap_or_else(|| {
+            let base = heading_slug(&text);
+            let count = seen.entry(base.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                base
+            } else {
+                format!("{base}-{count}")
+            }
+        });
+        if let Event::Start(Tag::Heading { id: slot, .. }) = &mut events[index] {
+            *slot = Some(CowStr::from(id.clone()));
+        }
+        entries.push(TocEntry { level, id, text });
+    }
+
+    entries
+}
+
+fn heading_slug(text: &str) -> String {
+    let slug = slugify(text);
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+fn render_toc(entries: &[TocEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let items: String = entries
+        .iter()
+        .map(|entry| {
+            let class = match entry.level {
+                HeadingLevel::H2 => "post-toc-item",
+                _ => "post-toc-item post-toc-subitem",
+            };
+            format!(
+                "<li class=\"{class}\"><a href=\"#{}\">{}</a></li>",
+                escape_html(&entry.id),
+                escape_html(entry.text.trim())
+            )
+        })
+        .collect();
+    format!("<nav class=\"post-toc\" aria-label=\"Table of contents\"><ol>{items}</ol></nav>")
+}
+
+/// Tracks raw HTML from the post while sanitizing: HTML blocks are cleaned
+/// as a whole, inline tags one at a time. Markdown links and images get
+/// the same URL check as raw `href`s and `src`s.
+#[derive(Default)]
+struct RawHtmlState {
+    block: Option<String>,
+    /// Inside an inline `<script>` (or similar) being dropped, up to its
+    /// closing tag or the end of the paragraph.
+    dropping_until: Option<String>,
+}
+
+impl RawHtmlState {
+    fn filter<'a>(&mut self, sanitizer: &SanitizeOptions, event: Event<'a>) -> Option<Event<'a>> {
+        match event {
+            Event::Start(Tag::HtmlBlock) => {
+                self.block = Some(String::new());
+                None
+            }
+            Event::Html(html) if self.block.is_some() => {
+                if let Some(block) = &mut self.block {
+                    block.push_str(&html);
+                }
+                None
+            }
+            Event::End(TagEnd::HtmlBlock) => {
+                let block = self.block.take().unwrap_or_default();
+                Some(Event::Html(CowStr::from(sanitizer.clean_block(&block))))
+            }
+            Event::InlineHtml(tag) => {
+                if let Some(name) = &self.dropping_until {
+                    if tag.trim().eq_ignore_ascii_case(&format!("</{name}>")) {
+                        self.dropping_until = None;
+                    }
+                    return None;
+                }
+                if let Some(name) = sanitizer.drops_inline_content(&tag) {
+                    self.dropping_until = Some(name);
+                    return None;
+                }
+                sanitizer
+                    .clean_inline_tag(&tag)
+                    .map(|tag| Event::InlineHtml(CowStr::from(tag)))
+            }
+            Event::End(TagEnd::Paragraph) => {
+                self.dropping_until = None;
+                Some(event)
+            }
+            _ if self.dropping_until.is_some() => None,
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) if is_unsafe_url(&dest_url) => Some(Event::Start(Tag::Link {
+                link_type,
+                dest_url: CowStr::Borrowed(""),
+                title,
+                id,
+            })),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) if is_unsafe_url(&dest_url) => Some(Event::Start(Tag::Image {
+                link_type,
+                dest_url: CowStr::Borrowed(""),
+                title,
+                id,
+            })),
+            other => Some(other),
+        }
+    }
+}
+
+const COLLAPSIBLE_FENCE: &str = ":::";
+const COLLAPSIBLE_KEYWORD: &str = "details";
+
+enum CollapsibleMarker<'a> {
+    /// The summary text after the keyword, untrimmed at the end since inline
+    /// markup may follow it.
+    Open(&'a str),
+    Close,
+}
+
+impl<'a> CollapsibleMarker<'a> {
+    /// `::: details Summary text` opens a section, a bare `:::` closes it.
+    fn parse(line: &'a str) -> Option<Self> {
+        let rest = line
+            .trim_start()
+            .strip_prefix(COLLAPSIBLE_FENCE)?
+            .trim_start();
+        if rest.trim_end().is_empty() {
+            return Some(Self::Close);
+        }
+        let summary = rest.strip_prefix(COLLAPSIBLE_KEYWORD)?;
+        if !summary.is_empty() && !summary.starts_with(char::is_whitespace) {
+            return None;
+        }
+        Some(Self::Open(summary.trim_start()))
+    }
+}
+
+/// Puts blank lines around `:::` marker lines outside code blocks, so each
+/// marker parses as a paragraph of its own and the lines between them as
+/// ordinary markdown.
+fn isolate_collapsible_markers(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+    // An indented code block starts after a blank line (it cannot interrupt
+    // a paragraph) and runs until a non-blank line drops the indent.
+    let mut after_blank = true;
+    let mut indented_code = false;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let blank = trimmed.trim_end().is_empty();
+        if fence.is_none() && !blank {
+            indented_code = is_code_indented(line) && (after_blank || indented_code);
+        }
+        after_blank = blank;
+        match fence {
+            Some(open) if trimmed.starts_with(open) => fence = None,
+            Some(_) => {}
+            None if indented_code => {}
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None if CollapsibleMarker::parse(line).is_some() => {
+                out.push('\n');
+                out.push_str(line.trim_end());
+                out.push_str("\n\n");
+                // The marker is now followed by a blank line.
+                after_blank = true;
+                continue;
+            }
+            None => {}
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Four columns of leading whitespace, a tab counting as four, mark an
+/// indented code line.
+fn is_code_indented(line: &str) -> bool {
+    let mut columns = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => columns += 1,
+            '\t' => columns += 4 - columns % 4,
+            _ => break,
+        }
+        if columns >= 4 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Replaces marker paragraphs with `<details class="collapsible">` and a
+/// `<summary>` keeping the marker line's inline markup. A stray `:::` with
+/// nothing open stays as text, and sections left open are closed at the
+/// end of the post.
+fn apply_collapsibles(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    let mut open = 0usize;
+    let mut events = events.into_iter();
+
+    while let Some(event) = events.next() {
+        if !matches!(event, Event::Start(Tag::Paragraph)) {
+            out.push(event);
+            continue;
+        }
+
+        let mut inline = Vec::new();
+        for inner in events.by_ref() {
+            if matches!(inner, Event::End(TagEnd::Paragraph)) {
+                break;
+            }
+            inline.push(inner);
+        }
+
+        let marker = match inline.first() {
+            Some(Event::Text(first)) => match CollapsibleMarker::parse(first) {
+                Some(CollapsibleMarker::Open(summary)) => Some(Some(summary.to_string())),
+                Some(CollapsibleMarker::Close) if inline.len() == 1 => Some(None),
+                _ => None,
+            },
+            _ => None,
+        };
+        match marker {
+            Some(Some(summary)) => {
+                open += 1;
+                out.push(Event::Html(CowStr::from(
+                    "<details class=\"collapsible\"><summary>",
+                )));
+                if !summary.is_empty() {
+                    out.push(Event::Text(CowStr::from(summary)));
+                }
+                out.extend(inline.into_iter().skip(1));
+                out.push(Event::Html(CowStr::from("</summary>\n")));
+            }
+            Some(None) if open > 0 => {
+                open -= 1;
+                out.push(Event::Html(CowStr::from("</details>\n")));
+            }
+            _ => {
+                out.push(Event::Start(Tag::Paragraph));
+                out.extend(inline);
+                out.push(Event::End(TagEnd::Paragraph));
+            }
+        }
+    }
+    for _ in 0..open {
+        out.push(Event::Html(CowStr::from("</details>\n")));
+    }
+    out
+}
+
+const SIDENOTE_OPEN: &str = "{^";
+const SIDENOTE_CLOSE: char = '}';
+
+/// Turns `{^ aside text }` in prose into `<span class="sidenote">`.
+/// Adjacent text events are merged first because the parser may split a
+/// run of text around punctuation; code blocks and code spans are left
+/// untouched, and math never reaches here as text.
+fn apply_sidenotes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    let mut pending = String::new();
+    let mut in_code_block = false;
+
+    for event in events {
+        match event {
+            Event::Text(text) if !in_code_block => {
+                pending.push_str(&text);
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            _ => {}
+        }
+        push_text_with_sidenotes(&mut out, std::mem::take(&mut pending));
+        out.push(event);
+    }
+    push_text_with_sidenotes(&mut out, pending);
+    out
+}
+
+fn push_text_with_sidenotes(out: &mut Vec<Event<'_>>, text: String) {
+    let mut rest = text.as_str();
+    while let Some(open) = rest.find(SIDENOTE_OPEN) {
+        let body_start = open + SIDENOTE_OPEN.len();
+        let Some(close) = rest[body_start..].find(SIDENOTE_CLOSE) else {
+            break;
+        };
+        let aside = rest[body_start..body_start + close].trim();
+        if aside.is_empty() {
+            break;
+        }
+        if open > 0 {
+            out.push(Event::Text(CowStr::from(rest[..open].to_string())));
+        }
+        out.push(Event::Html(CowStr::from(format!(
+            "<span class=\"sidenote\">{}</span>",
+            escape_html(aside)
+        ))));
+        rest = &rest[body_start + close + 1..];
+    }
+    if !rest.is_empty() {
+        out.push(Event::Text(CowStr::from(rest.to_string())));
+    }
+}
+
+const EXTERNAL_LINK_INDICATOR: &str =
+    "<span class=\"external-link-icon\" role=\"img\" aria-label=\"opens in new tab\">↗</span></a>";
+
+fn is_external_url(url: &str) -> bool {
+    let lower = url.trim_start().to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+/// A fenced code block info string such as `rust:src/main.rs {1,3-5}`:
+/// an optional `:path` filename label after the language, and an optional
+/// `{...}` line spec selecting lines to highlight.
+struct CodeBlockInfo {
+    lang: String,
+    filename: Option<String>,
+    highlight_lines: Option<Vec<RangeInclusive<usize>>>,
+}
+
+impl CodeBlockInfo {
+    fn parse(info: &str) -> Self {
+        let info = info.trim();
+        let (first, rest) = info.split_once(char::is_whitespace).unwrap_or((info, ""));
+        let (lang, filename) = match first.split_once(':') {
+            Some((lang, filename)) if !filename.is_empty() => (lang, Some(filename.to_string())),
+            _ => (first, None),
+        };
+
+        let (rest, highlight_lines) = match (rest.find('{'), rest.rfind('}')) {
+            (Some(open), Some(close)) if open < close => (
+                format!("{} {}", &rest[..open], &rest[close + 1..]),
+                parse_line_spec(&rest[open + 1..close]),
+            ),
+            _ => (rest.to_string(), None),
+        };
+
+        Self {
+            lang: format!("{lang} {}", rest.trim()).trim().to_string(),
+            filename,
+            highlight_lines,
+        }
+    }
+}
+
+/// Parses `1,3-5` into 1-based inclusive ranges. Any malformed entry
+/// rejects the whole spec so the block renders without highlighting.
+fn parse_line_spec(spec: &str) -> Option<Vec<RangeInclusive<usize>>> {
+    let mut ranges = Vec::new();
+    for part in spec.split(',').map(str::trim) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+            None => {
+                let line = part.parse().ok()?;
+                (line, line)
+            }
+        };
+        if start == 0 || start > end {
+            return None;
+        }
+        ranges.push(start..=end);
+    }
+    Some(ranges)
+}
+
+struct HighlightedBlock {
+    lines: Vec<RangeInclusive<usize>>,
+    source: String,
+}
+
+impl HighlightedBlock {
+    fn render(&self) -> String {
+        let mut out = String::with_capacity(self.source.len());
+        for (index, line) in self.source.lines().enumerate() {
+            let escaped = escape_html(line);
+            if self.lines.iter().any(|range| range.contains(&(index + 1))) {
+                out.push_str(&format!(
+                    "<span class=\"code-line-highlight\">{escaped}</span>\n"
+                ));
+            } else {
+                out.push_str(&escaped);
+                out.push('\n');
+            }
+        }
+        out
+    }
 }
 
 fn normalize_latex_delimiters(input: &str) -> String {
@@ -72,9 +731,18 @@ fn delimiter_at(input: &str, index: usize) -> Option<(&'static str, &'static str
     }
 }
 
-fn render_math_html(source: &str, display_mode: bool) -> String {
+fn render_math_html(source: &str, display_mode: bool, options: &RenderOptions) -> String {
+    if options.client_side_math {
+        return client_side_math_html(source, display_mode);
+    }
+
     let mut opts = katex::Opts::builder();
     opts.display_mode(display_mode);
+    opts.output_type(if options.mathml {
+        katex::OutputType::HtmlAndMathml
+    } else {
+        katex::OutputType::Html
+    });
 
     let rendered = match opts.build() {
         Ok(opts) => katex::render_with_opts(source, opts),
@@ -96,9 +764,63 @@ fn fallback_math_html(source: &str, display_mode: bool) -> String {
     format!("<span class=\"{class_name}\">{source}</span>")
 }
 
+/// Wraps the source in the `\(`/`\[` delimiters KaTeX auto-render scans
+/// for, so the browser can typeset it.
+fn client_side_math_html(source: &str, display_mode: bool) -> String {
+    let (class_name, open, close) = if display_mode {
+        ("math math-display", "\\[", "\\]")
+    } else {
+        ("math math-inline", "\\(", "\\)")
+    };
+    format!(
+        "<span class=\"{class_name}\">{open}{}{close}</span>",
+        escape_html(source)
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use super::render_markdown_to_html;
+    use super::{
+        apply_transforms, render_markdown_to_html as render_with, render_markdown_with_toc,
+        server_math_available, HtmlTransform, RenderOptions,
+    };
+    use crate::sanitize::SanitizeOptions;
+
+    fn render_markdown_to_html(markdown: &str) -> String {
+        render_with(markdown, &RenderOptions::default())
+    }
+
+    #[test]
+    fn collapsible_sections_render_details_with_nested_markdown() {
+        let html = render_markdown_to_html(
+            "Intro.\n::: details Show the *proof*\nIt follows from **induction**:\n\n- base case\n- step\n\n::: details Nested\n`inner`\n:::\n:::\n\n```\n:::\n```",
+        );
+        assert_eq!(
+            html,
+            "<p>Intro.</p>\n\
+             <details class=\"collapsible\"><summary>Show the <em>proof</em></summary>\n\
+             <p>It follows from <strong>induction</strong>:</p>\n\
+             <ul>\n<li>base case</li>\n<li>step</li>\n</ul>\n\
+             <details class=\"collapsible\"><summary>Nested</summary>\n\
+             <p><code>inner</code></p>\n\
+             </details>\n</details>\n\
+             <pre><code>:::\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn collapsible_markers_in_indented_code_stay_code() {
+        let html = render_markdown_to_html(
+            "Syntax:\n\n    ::: details Summary\n    body\n\n    :::\n\nAfter.\n\tstill a paragraph\n:::",
+        );
+        assert_eq!(
+            html,
+            "<p>Syntax:</p>\n\
+             <pre><code>::: details Summary\nbody\n\n:::\n</code></pre>\n\
+             <p>After.\nstill a paragraph</p>\n\
+             <p>:::</p>\n"
+        );
+    }
 
     #[test]
     fn renders_math_with_latex_paren_and_bracket_delimiters() {
@@ -114,10 +836,282 @@ mod tests {
         assert!(output.contains("katex"));
     }
 
+    #[test]
+    fn renders_code_block_filename_label() {
+        let input = "```rust:src/main.rs\nfn main() {}\n```";
+        let output = render_markdown_to_html(input);
+        assert!(output.contains("<div class=\"code-filename\">src/main.rs</div>"));
+        assert!(output.contains("<code class=\"language-rust\">"));
+        assert!(output.find("code-filename") < output.find("<pre>"));
+    }
+
+    #[test]
+    fn code_blocks_without_filename_are_unchanged() {
+        let output = render_markdown_to_html("```mermaid\nflowchart TD\n```");
+        assert!(!output.contains("code-filename"));
+        assert!(output.contains("<code class=\"language-mermaid\">"));
+    }
+
+    #[test]
+    fn highlights_lines_from_info_string_spec() {
+        let input = "```rust {1,3-4}\nline one\nline two\nline three\nline four\nline five\n```";
+        let output = render_markdown_to_html(input);
+        assert!(output.contains("<code class=\"language-rust\">"));
+        assert!(output.contains("<span class=\"code-line-highlight\">line one</span>"));
+        assert!(!output.contains("<span class=\"code-line-highlight\">line two</span>"));
+        assert!(output.contains("<span class=\"code-line-highlight\">line three</span>"));
+        assert!(output.contains("<span class=\"code-line-highlight\">line four</span>"));
+        assert!(!output.contains("<span class=\"code-line-highlight\">line five</span>"));
+    }
+
+    #[test]
+    fn combines_filename_label_and_line_highlighting() {
+        let input = "```rust:src/main.rs {2}\nfn main() {\n    run();\n}\n```";
+        let output = render_markdown_to_html(input);
+        assert!(output.contains("<div class=\"code-filename\">src/main.rs</div>"));
+        assert!(output.contains("<span class=\"code-line-highlight\">    run();</span>"));
+    }
+
+    #[test]
+    fn ignores_invalid_line_specs() {
+        for spec in ["{a}", "{0}", "{5-3}", "{1,}"] {
+            let input = format!("```rust {spec}\nlet x = 1 < 2;\n```");
+            let output = render_markdown_to_html(&input);
+            assert!(!output.contains("code-line-highlight"), "spec {spec}");
+            assert!(output.contains("let x = 1 &lt; 2;"));
+        }
+    }
+
+    #[test]
+    fn hard_breaks_option_turns_soft_breaks_into_br() {
+        let input = "first line\nsecond line";
+
+        let default = render_with(input, &RenderOptions::default());
+        assert_eq!(default, "<p>first line\nsecond line</p>\n");
+
+        let hard = render_with(
+            input,
+            &RenderOptions {
+                hard_breaks: true,
+                ..RenderOptions::default()
+            },
+        );
+        assert_eq!(hard, "<p>first line<br />\nsecond line</p>\n");
+    }
+
+    #[test]
+    fn crlf_line_endings_do_not_leak_into_math() {
+        let crlf = render_markdown_to_html("Start \\( \\frac{a}{b}\r\n\\approx 1 \\) end\r\n");
+        let lf = render_markdown_to_html("Start \\( \\frac{a}{b}\n\\approx 1 \\) end\n");
+        assert_eq!(crlf, lf);
+        assert!(crlf.contains("katex"));
+    }
+
+    #[test]
+    fn external_links_get_new_tab_indicator_when_enabled() {
+        let input = "[Docs](https://example.com/a?b=1&c=2) and [post](/posts/first-post)";
+        let options = RenderOptions {
+            external_link_icons: true,
+            ..RenderOptions::default()
+        };
+
+        let output = render_with(input, &options);
+        assert!(output.contains(
+            "<a href=\"https://example.com/a?b=1&amp;c=2\" target=\"_blank\" rel=\"noopener noreferrer\">Docs<span class=\"external-link-icon\" role=\"img\" aria-label=\"opens in new tab\">↗</span></a>"
+        ));
+        assert!(output.contains("<a href=\"/posts/first-post\">post</a>"));
+        assert_eq!(output.matches("external-link-icon").count(), 1);
+
+        let plain = render_markdown_to_html(input);
+        assert!(!plain.contains("external-link-icon"));
+        assert!(!plain.contains("target="));
+    }
+
+    #[test]
+    fn renders_sidenote_markup_from_aside_syntax() {
+        let output = render_markdown_to_html("Claim{^ only if x < y } holds.");
+        assert_eq!(
+            output,
+            "<p>Claim<span class=\"sidenote\">only if x &lt; y</span> holds.</p>\n"
+        );
+    }
+
+    #[test]
+    fn sidenotes_leave_code_and_math_alone() {
+        let output = render_markdown_to_html("`{^ code }` and \\(a^{2}\\) {^ note }");
+        assert!(output.contains("<code>{^ code }</code>"));
+        assert!(output.contains("katex"));
+        assert!(output.contains("<span class=\"sidenote\">note</span>"));
+
+        let block = render_markdown_to_html("```\n{^ not a note }\n```");
+        assert!(!block.contains("sidenote"));
+    }
+
+    #[test]
+    fn falls_back_to_client_side_math_when_katex_fails_to_initialise() {
+        let probe = Err(katex::Error::JsInitError("no js engine".to_string()));
+        let options = RenderOptions {
+            client_side_math: !server_math_available(probe),
+            ..RenderOptions::default()
+        };
+        assert!(options.client_side_math);
+
+        let output = render_with("Inline \\(a<b\\) and \\[x^2\\]", &options);
+        assert!(!output.contains("katex"));
+        assert!(output.contains("<span class=\"math math-inline\">\\(a&lt;b\\)</span>"));
+        assert!(output.contains("<span class=\"math math-display\">\\[x^2\\]</span>"));
+    }
+
+    #[test]
+    fn math_errors_names_expressions_katex_rejects() {
+        let errors = super::math_errors("Fine $x^2$ and broken $\\frac{1}$.");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].ends_with("in `\\frac{1}`"), "{errors:?}");
+    }
+
+    #[test]
+    fn working_katex_keeps_server_side_math() {
+        assert!(server_math_available(katex::render("x")));
+    }
+
     #[test]
     fn renders_html_from_ai_mar_2026_post() {
         let post = include_str!("../content/posts/2026-03-02-strangelove-ai-systems.md");
         let output = render_markdown_to_html(post);
         assert!(output.contains("<h2>"));
     }
+
+    #[test]
+    fn toc_links_to_unique_heading_ids() {
+        let (html, toc) = render_markdown_with_toc(
+            "# Title\n\n## Getting `started`\n\n### Details\n\n## Details\n",
+            &RenderOptions::default(),
+        );
+
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<h2 id=\"getting-started\">"));
+        assert!(html.contains("<h3 id=\"details\">"));
+        assert!(html.contains("<h2 id=\"details-2\">"));
+        assert_eq!(
+            toc,
+            "<nav class=\"post-toc\" aria-label=\"Table of contents\"><ol>\
+             <li class=\"post-toc-item\"><a href=\"#getting-started\">Getting started</a></li>\
+             <li class=\"post-toc-item post-toc-subitem\"><a href=\"#details\">Details</a></li>\
+             <li class=\"post-toc-item\"><a href=\"#details-2\">Details</a></li>\
+             </ol></nav>"
+        );
+    }
+
+    #[test]
+    fn mathml_is_emitted_only_when_enabled() {
+        let html_only = render_markdown_to_html("$x^2$");
+        assert!(html_only.contains("katex-html"));
+        assert!(!html_only.contains("<math"));
+
+        let with_mathml = render_with(
+            "$x^2$",
+            &RenderOptions {
+                mathml: true,
+                ..RenderOptions::default()
+            },
+        );
+        assert!(with_mathml.contains("<math"));
+        assert!(with_mathml.contains("katex-html"));
+    }
+
+    #[test]
+    fn transforms_run_in_order_on_the_previous_output() {
+        fn wrap_section(html: String) -> String {
+            format!("<section>{html}</section>")
+        }
+        fn count_chars(html: String) -> String {
+            format!("{html}<!-- {} -->", html.len())
+        }
+        let transforms: [HtmlTransform; 2] = [wrap_section, count_chars];
+
+        assert_eq!(
+            apply_transforms("<p>x</p>".to_string(), &transforms),
+            "<section><p>x</p></section><!-- 27 -->"
+        );
+    }
+
+    #[test]
+    fn lazy_images_transform_skips_images_with_explicit_loading() {
+        let options = RenderOptions {
+            lazy_images: true,
+            ..RenderOptions::default()
+        };
+        let html = render_with(
+            "![a](a.png)\n\n<img src=\"b.png\" loading=\"eager\">",
+            &options,
+        );
+        assert!(html.contains("<img loading=\"lazy\" src=\"a.png\" alt=\"a\" />"));
+        assert!(html.contains("<img src=\"b.png\" loading=\"eager\">"));
+        assert!(!render_markdown_to_html("![a](a.png)").contains("loading="));
+    }
+
+    #[test]
+    fn sanitizing_keeps_allowed_details_and_strips_scripts() {
+        let options = RenderOptions {
+            sanitize: Some(SanitizeOptions::default()),
+            ..RenderOptions::default()
+        };
+        let html = render_with(
+            "<details><summary>More</summary>Hidden text</details>\n\n<script>alert(1)</script>\n\nInline <script>alert(2)</script> and <kbd>Ctrl</kbd> and $x$.",
+            &options,
+        );
+
+        assert!(html.contains("<details><summary>More</summary>Hidden text</details>"));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert"));
+        assert!(html.contains("<kbd>Ctrl</kbd>"));
+        assert!(html.contains("class=\"katex\""));
+    }
+
+    #[test]
+    fn sanitizing_keeps_inline_svg_without_its_handlers() {
+        let options = RenderOptions {
+            sanitize: Some(SanitizeOptions::default()),
+            ..RenderOptions::default()
+        };
+        let html = render_with(
+            "<svg viewBox=\"0 0 10 10\" width=\"40\"><circle cx=\"5\" cy=\"5\" r=\"4\" fill=\"red\" /></svg>\n\n<svg onload=\"alert(1)\"><script>alert(2)</script><rect width=\"2\" height=\"2\" /></svg>",
+            &options,
+        );
+
+        assert!(
+            html.contains(
+                "<svg viewBox=\"0 0 10 10\" width=\"40\"><circle cx=\"5\" cy=\"5\" fill=\"red\" r=\"4\" /></svg>"
+            ),
+            "{html}"
+        );
+        assert!(
+            html.contains("<svg><rect height=\"2\" width=\"2\" /></svg>"),
+            "{html}"
+        );
+        assert!(!html.contains("onload"));
+        assert!(!html.contains("alert"));
+    }
+
+    #[test]
+    fn sanitizing_clears_script_urls_from_markdown_links_and_images() {
+        let options = RenderOptions {
+            sanitize: Some(SanitizeOptions::default()),
+            ..RenderOptions::default()
+        };
+        let html = render_with(
+            "[a](javascript:alert(1)) [b](<JavaScript:alert(2)>) ![c](data:text/html,x) [d][ref] <vbscript:msgbox> [ok](https://example.com) ![dot](data:image/png;base64,iVBO)\n\n[ref]: javascript:alert(3)",
+            &options,
+        );
+
+        let lowercase = html.to_ascii_lowercase();
+        assert!(!lowercase.contains("=\"javascript:"), "{html}");
+        assert!(!lowercase.contains("=\"vbscript:"), "{html}");
+        assert!(!html.contains("data:text"), "{html}");
+        assert!(html.contains("<a href=\"\">a</a>"), "{html}");
+        assert!(html.contains("<img src=\"\" alt=\"c\" />"), "{html}");
+        assert!(html.contains("<a href=\"https://example.com\">ok</a>"));
+        assert!(html.contains("src=\"data:image/png;base64,iVBO\""));
+        assert!(render_markdown_to_html("[a](javascript:alert(1))").contains("javascript:"));
+    }
 }
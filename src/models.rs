@@ -1,4 +1,10 @@
+use std::{path::PathBuf, time::SystemTime};
+
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Deserialize;
+use tracing::warn;
+
+use crate::post_url::PostUrlPattern;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct FrontMatter {
@@ -9,6 +15,42 @@ pub struct FrontMatter {
     pub image: Option<String>,
     pub role: Option<String>,
     pub subtitle: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `YYYY-MM-DD`; the post is hidden in production after this day.
+    pub expires: Option<String>,
+    /// Overrides `SiteConfig::comments` for this post.
+    pub comments: Option<bool>,
+    /// Rendered as `<meta name="keywords">`; falls back to `tags`.
+    pub keywords: Option<Vec<String>>,
+    /// Where the post was first published; becomes the canonical URL.
+    pub canonical_source: Option<CanonicalSource>,
+    /// Date of the last substantive edit; defaults to the file's mtime.
+    pub updated: Option<String>,
+    /// Fill the layout's `{{ toc }}` with a table of contents.
+    #[serde(default)]
+    pub toc: bool,
+    /// Single primary category, listed at `/categories/<slug>`; unlike
+    /// `tags` a post has at most one.
+    pub category: Option<String>,
+    /// `false` leaves `{{ banner }}` empty on this page.
+    pub show_banner: Option<bool>,
+    /// Open Graph type, e.g. `profile`; defaults to `article` for posts and
+    /// `website` for the home page.
+    pub og_type: Option<String>,
+    /// Markdown files under `fragments/` appended to the body in order.
+    #[serde(default)]
+    pub includes: Vec<String>,
+    /// Treat this file like one in `drafts/`: loaded only where drafts are
+    /// shown.
+    #[serde(default)]
+    pub draft: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalSource {
+    pub url: String,
+    pub name: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -17,6 +59,119 @@ pub struct SiteConfig {
     pub author: String,
     pub description: String,
     pub og_site_name: String,
+    #[serde(default)]
+    pub theme_color: Option<ThemeColor>,
+    /// Whether posts show the comments embed unless their front matter
+    /// says otherwise.
+    #[serde(default)]
+    pub comments: bool,
+    /// Snippet injected at `{{ comments }}` on post pages, with `{{slug}}`
+    /// replaced by the post slug.
+    #[serde(default)]
+    pub comments_embed: Option<String>,
+    /// Show each post's `description` under its sidebar entry, followed by
+    /// a read-more link.
+    #[serde(default)]
+    pub listing_excerpts: bool,
+    #[serde(default = "default_read_more_text")]
+    pub read_more_text: String,
+    /// Shown in place of the post list when there is nothing to list.
+    #[serde(default = "default_no_posts_text")]
+    pub no_posts_text: String,
+    /// Show a "Last updated" footer under each post.
+    #[serde(default)]
+    pub show_last_updated: bool,
+    /// Raw HTML injected before `</head>` in production only, so local
+    /// page views never reach the site's stats.
+    #[serde(default)]
+    pub analytics_snippet: Option<String>,
+    /// Raw HTML placed before `</body>` on full production pages in place
+    /// of `analytics_snippet` until the visitor has an
+    /// `analytics_consent=yes` cookie, which the banner is expected to set.
+    /// Partial responses carry neither.
+    #[serde(default)]
+    pub consent_banner: Option<String>,
+    #[serde(default)]
+    pub tag_order: TagOrder,
+    /// Posts per `/feed.xml` page; older pages are reached through
+    /// `?page=N` and RFC 5005 `next`/`previous` links. Unset keeps every
+    /// post on one page.
+    #[serde(default)]
+    pub feed_page_size: Option<usize>,
+    /// KaTeX stylesheet linked at `{{ math_css }}` on pages with math; a
+    /// CDN URL or a self-hosted `/static/...` path.
+    #[serde(default = "default_katex_css_url")]
+    pub katex_css_url: String,
+    /// Markdown served in place of a post whose render panicked while
+    /// warming up.
+    #[serde(default = "default_render_failure_text")]
+    pub render_failure_text: String,
+    /// Set from `POST_URL_PATTERN` at load time rather than `site.toml`.
+    #[serde(skip)]
+    pub post_urls: PostUrlPattern,
+    /// Set from `EDIT_BASE_URL` at load time; post pages link to this
+    /// plus the post's `content_path` when present.
+    #[serde(skip)]
+    pub edit_base_url: Option<String>,
+    /// Set from `HOME_MODE` at load time.
+    #[serde(skip)]
+    pub home_mode: HomeMode,
+}
+
+/// What `/` shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HomeMode {
+    /// The `home.md` page.
+    #[default]
+    Page,
+    /// The most recent listed post in full.
+    Latest,
+    /// Excerpts of the most recent listed posts.
+    List,
+}
+
+impl HomeMode {
+    /// Reads `HOME_MODE` (`page`, `latest` or `list`), falling back to
+    /// `page` with a warning on anything else.
+    pub fn from_env() -> Self {
+        let Ok(raw) = std::env::var("HOME_MODE") else {
+            return Self::default();
+        };
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "page" => Self::Page,
+            "latest" => Self::Latest,
+            "list" => Self::List,
+            _ => {
+                warn!(mode = %raw, "ignoring unknown HOME_MODE");
+                Self::default()
+            }
+        }
+    }
+}
+
+fn default_read_more_text() -> String {
+    "Read more →".to_string()
+}
+
+fn default_katex_css_url() -> String {
+    "https://cdn.jsdelivr.net/npm/katex@0.16.25/dist/katex.min.css".to_string()
+}
+
+fn default_render_failure_text() -> String {
+    "This post failed to render. The error has been logged.".to_string()
+}
+
+fn default_no_posts_text() -> String {
+    "No posts yet.".to_string()
+}
+
+/// Either one colour for every scheme, or a `light`/`dark` pair emitted
+/// with `prefers-color-scheme` media queries.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ThemeColor {
+    Single(String),
+    Schemes { light: String, dark: String },
 }
 
 impl Default for SiteConfig {
@@ -26,11 +181,51 @@ impl Default for SiteConfig {
             author: "Daniel Vianna".to_string(),
             description: "Engineering notes on making change cheap.".to_string(),
             og_site_name: "Bon Élève Blog".to_string(),
+            theme_color: None,
+            comments: false,
+            comments_embed: None,
+            listing_excerpts: false,
+            read_more_text: default_read_more_text(),
+            no_posts_text: default_no_posts_text(),
+            show_last_updated: false,
+            analytics_snippet: None,
+            consent_banner: None,
+            tag_order: TagOrder::default(),
+            feed_page_size: None,
+            katex_css_url: default_katex_css_url(),
+            render_failure_text: default_render_failure_text(),
+            edit_base_url: None,
+            home_mode: HomeMode::default(),
+            post_urls: PostUrlPattern::default(),
         }
     }
 }
 
-#[derive(Clone)]
+/// Order of tag and category groups in navigation (`tag_order` in
+/// `site.toml`).
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagOrder {
+    /// By name, ignoring case.
+    #[default]
+    Alphabetical,
+    /// Most posts first, ties by name.
+    Count,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct NavConfig {
+    #[serde(default)]
+    pub links: Vec<NavLink>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NavLink {
+    pub label: String,
+    pub href: String,
+}
+
+#[derive(Clone, Default, PartialEq)]
 pub struct Post {
     pub title: String,
     pub slug: String,
@@ -39,5 +234,58 @@ pub struct Post {
     pub image: Option<String>,
     pub role: Option<String>,
     pub subtitle: Option<String>,
+    pub tags: Vec<String>,
+    pub expires: Option<String>,
+    pub comments: Option<bool>,
+    pub keywords: Vec<String>,
+    pub canonical_source: Option<CanonicalSource>,
+    /// `updated` from front matter, else the source file's modification
+    /// date (`YYYY-MM-DD`).
+    pub updated: Option<String>,
+    pub toc: bool,
+    pub category: Option<String>,
+    pub show_banner: Option<bool>,
+    pub og_type: Option<String>,
+    pub includes: Vec<String>,
+    /// See `FrontMatter::draft`.
+    pub draft: bool,
+    /// The file's body followed by its `includes`.
     pub markdown_body: String,
+    /// Whole minutes to read `markdown_body` at `READING_WPM`, worked out
+    /// once at load.
+    pub reading_minutes: usize,
+    /// File the post was loaded from; the slug comes from front matter and
+    /// need not match the file name.
+    pub source_path: PathBuf,
+    /// `source_path`'s modification time when it was loaded, sent as the
+    /// page's `Last-Modified`.
+    pub modified: Option<DateTime<Utc>>,
+    /// What went wrong loading the post, such as invalid front matter or
+    /// math KaTeX rejects; development shows these above the page.
+    pub problems: Vec<String>,
+    /// `source_path` relative to the content root, e.g.
+    /// `posts/rust/intro.md`; edit links point at it.
+    pub content_path: PathBuf,
+}
+
+impl Post {
+    /// True once `today` is past the `expires` date. A missing or
+    /// unparseable date never expires the post.
+    pub fn is_expired_on(&self, today: NaiveDate) -> bool {
+        self.expires
+            .as_deref()
+            .and_then(|expires| NaiveDate::parse_from_str(expires.trim(), "%Y-%m-%d").ok())
+            .is_some_and(|expires| today > expires)
+    }
+
+    /// The `date` front-matter field as an ISO `YYYY-MM-DD` day, if it is
+    /// one.
+    pub fn published_on(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d").ok()
+    }
+}
+
+/// The current UTC calendar day, which `expires` dates are compared against.
+pub fn today() -> NaiveDate {
+    DateTime::<Utc>::from(SystemTime::now()).date_naive()
 }
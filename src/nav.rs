@@ -0,0 +1,467 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use tracing::warn;
+
+use crate::models::{today, NavLink, Post, TagOrder};
+use crate::page_meta::{category_path, escape_html, post_path, slugify};
+use crate::post_url::PostUrlPattern;
+
+/// Bucket for posts without a `category`.
+pub(crate) const UNCATEGORIZED: &str = "Uncategorized";
+
+pub(crate) fn render_nav_links(links: &[NavLink]) -> String {
+    links
+        .iter()
+        .map(|link| {
+            format!(
+                "<li><a href=\"{}\" class=\"site-nav-link\">{}</a></li>",
+                escape_html(&link.href),
+                escape_html(&link.label)
+            )
+        })
+        .collect()
+}
+
+/// Title-cases a kebab-case role or tag for use as a group heading.
+pub(crate) fn group_heading(label: &str) -> String {
+    label
+        .split('-')
+        .map(|w| {
+            let mut c = w.chars();
+            match c.next() {
+                None => String::new(),
+                Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Sorts named groups of posts per `order`; the sort is stable, so groups
+/// that compare equal keep their first-seen order.
+pub(crate) fn sort_groups<T>(
+    groups: &mut [T],
+    order: TagOrder,
+    name: impl Fn(&T) -> String,
+    count: impl Fn(&T) -> usize,
+) {
+    match order {
+        TagOrder::Alphabetical => groups.sort_by_cached_key(|group| name(group).to_lowercase()),
+        TagOrder::Count => groups.sort_by_cached_key(|group| {
+            (std::cmp::Reverse(count(group)), name(group).to_lowercase())
+        }),
+    }
+}
+
+/// Site-wide tree for the `{{ sitenav }}` placeholder: posts grouped under
+/// their primary tag (the first one), falling back to their role, in
+/// `order`. Posts with neither follow the groups unheaded.
+/// Rendered once per content load, so posts already expired are left out.
+pub(crate) fn render_site_nav(
+    posts: &[Post],
+    post_urls: &PostUrlPattern,
+    order: TagOrder,
+) -> String {
+    let today = today();
+    let mut groups: Vec<(Option<&str>, Vec<&Post>)> = Vec::new();
+    for post in posts.iter().filter(|post| !post.is_expired_on(today)) {
+        let key = post.tags.first().or(post.role.as_ref()).map(String::as_str);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some(group) => group.1.push(post),
+            None => groups.push((key, vec![post])),
+        }
+    }
+    sort_groups(
+        &mut groups,
+        order,
+        |(key, _)| key.map(group_heading).unwrap_or_default(),
+        |(_, posts)| posts.len(),
+    );
+    groups.sort_by_key(|(key, _)| key.is_none());
+
+    let mut html = String::new();
+    for (key, group_posts) in groups {
+        let links: String = group_posts
+            .iter()
+            .map(|post| {
+                format!(
+                    "<li><a href=\"{}\" class=\"sitenav-link\">{}</a></li>",
+                    post_urls.path(post),
+                    escape_html(&post.title)
+                )
+            })
+            .collect();
+        match key {
+            Some(key) => html.push_str(&format!(
+                "<li class=\"sitenav-group\"><span class=\"sitenav-heading\">{}</span><ul>{links}</ul></li>",
+                escape_html(&group_heading(key))
+            )),
+            None => html.push_str(&links),
+        }
+    }
+    html
+}
+
+/// A post's primary category, or `UNCATEGORIZED` when it has none.
+pub(crate) fn post_category(post: &Post) -> &str {
+    post.category
+        .as_deref()
+        .map(str::trim)
+        .filter(|category| !category.is_empty())
+        .unwrap_or(UNCATEGORIZED)
+}
+
+/// Posts grouped by category slug in `order`, each group named after its
+/// first post's spelling, with `Uncategorized` last.
+pub(crate) fn group_by_category<'a>(
+    posts: impl IntoIterator<Item = &'a Post>,
+    order: TagOrder,
+) -> Vec<(&'a str, Vec<&'a Post>)> {
+    let mut groups: Vec<(String, &str, Vec<&Post>)> = Vec::new();
+    for post in posts {
+        let name = post_category(post);
+        let slug = slugify(name);
+        match groups.iter_mut().find(|(s, _, _)| *s == slug) {
+            Some(group) => group.2.push(post),
+            None => groups.push((slug, name, vec![post])),
+        }
+    }
+    sort_groups(
+        &mut groups,
+        order,
+        |(_, name, _)| name.to_string(),
+        |(_, _, posts)| posts.len(),
+    );
+    groups.sort_by_key(|(_, name, _)| *name == UNCATEGORIZED);
+    groups
+        .into_iter()
+        .map(|(_, name, posts)| (name, posts))
+        .collect()
+}
+
+/// Posts grouped by tag slug in `order`, each group named after the first
+/// spelling seen. A post appears under every one of its tags.
+pub(crate) fn group_by_tag<'a>(
+    posts: impl IntoIterator<Item = &'a Post>,
+    order: TagOrder,
+) -> Vec<(&'a str, Vec<&'a Post>)> {
+    let mut groups: Vec<(String, &str, Vec<&Post>)> = Vec::new();
+    for post in posts {
+        for tag in &post.tags {
+            let slug = slugify(tag);
+            if slug.is_empty() {
+                continue;
+            }
+            match groups.iter_mut().find(|(s, _, _)| *s == slug) {
+                Some(group) if !group.2.iter().any(|member| member.slug == post.slug) => {
+                    group.2.push(post)
+                }
+                Some(_) => {}
+                None => groups.push((slug, tag.trim(), vec![post])),
+            }
+        }
+    }
+    sort_groups(
+        &mut groups,
+        order,
+        |(_, name, _)| name.to_string(),
+        |(_, _, posts)| posts.len(),
+    );
+    groups
+        .into_iter()
+        .map(|(_, name, posts)| (name, posts))
+        .collect()
+}
+
+/// Links to every category index for the `{{ categories }}` placeholder.
+/// Rendered once per content load, so posts already expired are left out.
+pub(crate) fn render_category_nav(posts: &[Post], order: TagOrder) -> String {
+    let today = today();
+    group_by_category(
+        posts.iter().filter(|post| !post.is_expired_on(today)),
+        order,
+    )
+    .into_iter()
+    .map(|(name, _)| {
+        format!(
+            "<li><a href=\"{}\" class=\"sitenav-link\">{}</a></li>",
+            category_path(&slugify(name)),
+            escape_html(name)
+        )
+    })
+    .collect()
+}
+
+/// Logs a warning for every nav entry whose internal href does not match a
+/// route this server knows how to answer, and returns one message per such
+/// entry. External URLs and anchor-only links are never checked.
+pub(crate) fn warn_on_unknown_nav_routes(
+    links: &[NavLink],
+    posts: &[Post],
+    post_urls: &PostUrlPattern,
+) -> Vec<String> {
+    let mut dangling = Vec::new();
+    for link in links {
+        if !is_known_nav_target(&link.href, posts, post_urls) {
+            warn!(
+                label = %link.label,
+                href = %link.href,
+                "nav entry points at an unknown internal route"
+            );
+            dangling.push(format!(
+                "nav entry `{}` points at unknown route {}",
+                link.label, link.href
+            ));
+        }
+    }
+    dangling
+}
+
+/// Logs a warning for every markdown link in `sources` to a `/posts/...`
+/// page no post in `posts` answers, and returns one message per link.
+pub(crate) fn warn_on_dangling_post_links<'a>(
+    sources: impl IntoIterator<Item = &'a Post>,
+    posts: &[Post],
+) -> Vec<String> {
+    let mut dangling = Vec::new();
+    for source in sources {
+        for event in Parser::new(&source.markdown_body) {
+            let Event::Start(Tag::Link { dest_url, .. }) = event else {
+                continue;
+            };
+            let Some(rest) = dest_url.strip_prefix("/posts/") else {
+                continue;
+            };
+            let slug = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            let slug = slug.strip_suffix(".txt").unwrap_or(slug);
+            // Links may spell a non-ASCII slug raw or percent-encoded.
+            let linked = format!("/posts/{slug}");
+            if slug.is_empty()
+                || posts
+                    .iter()
+                    .any(|post| post.slug == slug || post_path(&post.slug) == linked)
+            {
+                continue;
+            }
+            warn!(
+                path = %source.source_path.display(),
+                href = %dest_url,
+                "post links to an unknown post"
+            );
+            dangling.push(format!(
+                "{}: links to unknown post {dest_url}",
+                source.source_path.display()
+            ));
+        }
+    }
+    dangling
+}
+
+fn is_known_nav_target(href: &str, posts: &[Post], post_urls: &PostUrlPattern) -> bool {
+    if !href.starts_with('/') || href.starts_with("//") {
+        return true;
+    }
+
+    let path = href.split(['?', '#']).next().unwrap_or(href);
+    if path == "/" || path.starts_with("/static/") {
+        return true;
+    }
+
+    let path = path.trim_end_matches('/');
+    if matches!(
+        path,
+        "/posts" | "/feed.xml" | "/favicon.ico" | "/favicon.png"
+    ) {
+        return true;
+    }
+
+    if path == "/tags" {
+        return true;
+    }
+    if let Some(tag) = path.strip_prefix("/tags/") {
+        return posts
+            .iter()
+            .any(|post| post.tags.iter().any(|name| slugify(name) == tag));
+    }
+
+    if let Some(category) = path.strip_prefix("/categories/") {
+        return posts
+            .iter()
+            .any(|post| slugify(post_category(post)) == category);
+    }
+
+    path.strip_prefix("/posts/")
+        .is_some_and(|slug| posts.iter().any(|post| post.slug == slug))
+        || posts.iter().any(|post| post_urls.path(post) == path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_known_nav_target, render_category_nav, render_nav_links, render_site_nav};
+    use crate::models::{NavLink, Post, TagOrder};
+    use crate::post_url::PostUrlPattern;
+
+    fn link(label: &str, href: &str) -> NavLink {
+        NavLink {
+            label: label.to_string(),
+            href: href.to_string(),
+        }
+    }
+
+    fn post(slug: &str) -> Post {
+        Post {
+            title: slug.to_string(),
+            slug: slug.to_string(),
+            date: "2026-03-04".to_string(),
+            description: None,
+            image: None,
+            role: None,
+            subtitle: None,
+            markdown_body: String::new(),
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn renders_configured_links_in_order() {
+        let html = render_nav_links(&[
+            link("Home", "/"),
+            link("Blog", "/posts/first-post"),
+            link("About", "#about"),
+        ]);
+
+        let home = html.find("href=\"/\"").expect("home link");
+        let blog = html.find("href=\"/posts/first-post\"").expect("blog link");
+        let about = html.find("href=\"#about\"").expect("about link");
+        assert!(home < blog && blog < about);
+        assert!(html.contains(">Blog</a>"));
+    }
+
+    #[test]
+    fn escapes_nav_labels_and_hrefs() {
+        let html = render_nav_links(&[link("<b>x</b>", "/\"onclick")]);
+        assert!(html.contains("&lt;b&gt;x&lt;/b&gt;"));
+        assert!(html.contains("href=\"/&quot;onclick\""));
+    }
+
+    #[test]
+    fn recognises_known_and_unknown_internal_routes() {
+        let posts = vec![post("first-post")];
+        assert!(is_known_nav_target("/", &posts, &PostUrlPattern::default()));
+        assert!(is_known_nav_target(
+            "#posts",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(is_known_nav_target(
+            "https://example.com/about",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(is_known_nav_target(
+            "/posts/first-post#intro",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(is_known_nav_target(
+            "/static/favicon.png",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(!is_known_nav_target(
+            "/about",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(!is_known_nav_target(
+            "/posts/missing",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(is_known_nav_target(
+            "/categories/uncategorized",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+        assert!(!is_known_nav_target(
+            "/categories/rust",
+            &posts,
+            &PostUrlPattern::default()
+        ));
+    }
+
+    #[test]
+    fn site_nav_groups_posts_under_primary_tag_or_role() {
+        let tagged = |slug: &str, tags: &[&str], role: Option<&str>| Post {
+            tags: tags.iter().map(ToString::to_string).collect(),
+            role: role.map(ToString::to_string),
+            ..post(slug)
+        };
+        let html = render_site_nav(
+            &[
+                tagged("loose", &[], None),
+                tagged("rust-one", &["rust-notes", "web"], None),
+                tagged("essay", &[], Some("strategy")),
+                tagged("rust-two", &["rust-notes"], Some("strategy")),
+            ],
+            &PostUrlPattern::default(),
+            TagOrder::Alphabetical,
+        );
+
+        let rust = html.find(">Rust Notes</span>").expect("tag heading");
+        let strategy = html.find(">Strategy</span>").expect("role heading");
+        let rust_one = html.find("/posts/rust-one").expect("rust-one");
+        let rust_two = html.find("/posts/rust-two").expect("rust-two");
+        let essay = html.find("/posts/essay").expect("essay");
+        let loose = html.find("/posts/loose").expect("loose");
+        assert!(rust < rust_one && rust_one < rust_two && rust_two < strategy);
+        assert!(strategy < essay && essay < loose);
+        assert!(!html.contains(">Web</span>"));
+    }
+
+    #[test]
+    fn site_nav_leaves_out_expired_posts() {
+        let expired = Post {
+            expires: Some("2000-01-01".to_string()),
+            ..post("expired")
+        };
+        let html = render_site_nav(
+            &[expired, post("current")],
+            &PostUrlPattern::default(),
+            TagOrder::Alphabetical,
+        );
+        assert!(!html.contains("/posts/expired"));
+        assert!(html.contains("/posts/current"));
+    }
+
+    #[test]
+    fn category_nav_lists_categories_in_order_with_uncategorized_last() {
+        let in_category = |slug: &str, category: Option<&str>| Post {
+            category: category.map(ToString::to_string),
+            ..post(slug)
+        };
+        let posts = [
+            in_category("loose", None),
+            in_category("one", Some("Rust Notes")),
+            in_category("two", Some("essays")),
+            in_category("three", Some("rust notes")),
+            in_category("four", Some("Zines")),
+            in_category("five", Some("Zines")),
+            in_category("six", Some("Zines")),
+        ];
+
+        assert_eq!(
+            render_category_nav(&posts, TagOrder::Alphabetical),
+            "<li><a href=\"/categories/essays\" class=\"sitenav-link\">essays</a></li>\
+             <li><a href=\"/categories/rust-notes\" class=\"sitenav-link\">Rust Notes</a></li>\
+             <li><a href=\"/categories/zines\" class=\"sitenav-link\">Zines</a></li>\
+             <li><a href=\"/categories/uncategorized\" class=\"sitenav-link\">Uncategorized</a></li>"
+        );
+        assert_eq!(
+            render_category_nav(&posts, TagOrder::Count),
+            "<li><a href=\"/categories/zines\" class=\"sitenav-link\">Zines</a></li>\
+             <li><a href=\"/categories/rust-notes\" class=\"sitenav-link\">Rust Notes</a></li>\
+             <li><a href=\"/categories/essays\" class=\"sitenav-link\">essays</a></li>\
+             <li><a href=\"/categories/uncategorized\" class=\"sitenav-link\">Uncategorized</a></li>"
+        );
+    }
+}
@@ -3,28 +3,93 @@ use pulldown_cmark::{Event, Parser, Tag, TagEnd};
 use scraper::Html;
 use serde_json::Value;
 
-use crate::models::SiteConfig;
+use crate::models::{Post, SiteConfig, ThemeColor};
+use crate::state::Environment;
 
 const DEFAULT_SITE_URL: &str = "https://boneleve.blog";
 const DEFAULT_SOCIAL_IMAGE_PATH: &str = "/static/favicon.png";
+const DEFAULT_EXCERPT_LENGTH: usize = 160;
+const MIN_EXCERPT_LENGTH: usize = 40;
+const DEFAULT_READING_WPM: usize = 200;
+const MIN_READING_WPM: usize = 50;
+
+/// Which kind of page is being rendered, exposed to CSS as `{{ body_class }}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PageType {
+    Home,
+    Post,
+    NotFound,
+    Gone,
+    Category,
+    Tag,
+    TagIndex,
+    PostIndex,
+    Maintenance,
+    Error,
+    Print,
+}
+
+impl PageType {
+    pub(crate) fn body_class(self) -> &'static str {
+        match self {
+            Self::Home => "page-home",
+            Self::Post => "page-post",
+            Self::NotFound => "page-not-found",
+            Self::Gone => "page-gone",
+            Self::Category => "page-category",
+            Self::Tag => "page-tag",
+            Self::TagIndex => "page-tag-index",
+            Self::PostIndex => "page-post-index",
+            Self::Maintenance => "page-maintenance",
+            Self::Error => "page-error",
+            Self::Print => "print",
+        }
+    }
+
+    /// Open Graph type when the page does not set its own.
+    pub(crate) fn default_og_type(self) -> &'static str {
+        match self {
+            Self::Post | Self::Print => "article",
+            _ => "website",
+        }
+    }
+}
 
 pub(crate) struct PageMeta {
     pub(crate) title: String,
     pub(crate) description: String,
     pub(crate) url: String,
+    /// `url`, unless the post was first published elsewhere.
+    pub(crate) canonical_url: String,
     pub(crate) image: String,
     pub(crate) author: String,
     pub(crate) published_time: Option<String>,
     pub(crate) role: Option<String>,
+    pub(crate) keywords: Vec<String>,
+    pub(crate) page_type: PageType,
+    /// `og_type` from front matter; `PageType::default_og_type` otherwise.
+    pub(crate) og_type: Option<String>,
+}
+
+impl PageMeta {
+    pub(crate) fn og_type(&self) -> &str {
+        self.og_type
+            .as_deref()
+            .unwrap_or_else(|| self.page_type.default_og_type())
+    }
 }
 
 pub(crate) struct PostMetaInput<'a> {
     pub(crate) title: Option<&'a str>,
     pub(crate) date: Option<&'a str>,
     pub(crate) subtitle: Option<&'a str>,
+    /// Front-matter `description`; used verbatim when present.
+    pub(crate) description: Option<&'a str>,
     pub(crate) role: Option<&'a str>,
     pub(crate) image: Option<&'a str>,
     pub(crate) markdown_body: &'a str,
+    pub(crate) keywords: &'a [String],
+    pub(crate) canonical_url: Option<&'a str>,
 }
 
 pub(crate) fn default_not_found_meta(slug: &str, site_config: &SiteConfig) -> PageMeta {
@@ -33,13 +98,99 @@ pub(crate) fn default_not_found_meta(slug: &str, site_config: &SiteConfig) -> Pa
         title: format!("Post not found | {}", site_config.title),
         description: format!("The post \"{}\" was not found.", slug),
         url: format!("{base}/posts/{slug}"),
+        canonical_url: format!("{base}/posts/{slug}"),
         image: absolute_url(DEFAULT_SOCIAL_IMAGE_PATH),
         author: site_config.author.clone(),
         published_time: None,
         role: None,
+        keywords: Vec::new(),
+        page_type: PageType::NotFound,
+        og_type: None,
+    }
+}
+
+pub(crate) fn default_gone_meta(slug: &str, site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("Post removed | {}", site_config.title),
+        description: format!("The post \"{}\" has been removed.", slug),
+        page_type: PageType::Gone,
+        ..default_not_found_meta(slug, site_config)
+    }
+}
+
+pub(crate) fn default_maintenance_meta(page_path: &str, site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("Down for maintenance | {}", site_config.title),
+        description: format!("{} is down for maintenance.", site_config.title),
+        page_type: PageType::Maintenance,
+        ..site_page_meta(page_path, site_config)
     }
 }
 
+/// For the panic page, which has no request path to describe.
+pub(crate) fn default_error_meta(site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("Something went wrong | {}", site_config.title),
+        page_type: PageType::Error,
+        ..site_page_meta("/", site_config)
+    }
+}
+
+pub(crate) fn category_meta(name: &str, page_path: &str, site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("{name} | {}", site_config.title),
+        description: format!("Posts filed under {name}."),
+        page_type: PageType::Category,
+        ..site_page_meta(page_path, site_config)
+    }
+}
+
+pub(crate) fn tag_meta(name: &str, page_path: &str, site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("{name} | {}", site_config.title),
+        description: format!("Posts tagged {name}."),
+        page_type: PageType::Tag,
+        ..site_page_meta(page_path, site_config)
+    }
+}
+
+pub(crate) fn tag_index_meta(site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("Tags | {}", site_config.title),
+        description: format!("Every tag on {}.", site_config.title),
+        page_type: PageType::TagIndex,
+        ..site_page_meta("/tags", site_config)
+    }
+}
+
+pub(crate) fn post_index_meta(site_config: &SiteConfig) -> PageMeta {
+    PageMeta {
+        title: format!("Posts | {}", site_config.title),
+        description: format!("Every post on {}.", site_config.title),
+        page_type: PageType::PostIndex,
+        ..site_page_meta("/posts", site_config)
+    }
+}
+
+/// Site-level defaults for a page at `page_path` that has no post behind it.
+fn site_page_meta(page_path: &str, site_config: &SiteConfig) -> PageMeta {
+    build_post_meta(
+        page_path,
+        site_config,
+        PostMetaInput {
+            title: None,
+            date: None,
+            subtitle: None,
+            description: None,
+            role: None,
+            image: None,
+            markdown_body: "",
+            keywords: &[],
+            canonical_url: None,
+        },
+    )
+}
+
 pub(crate) fn build_post_meta(
     page_path: &str,
     site_config: &SiteConfig,
@@ -50,7 +201,10 @@ pub(crate) fn build_post_meta(
         .map(ToString::to_string)
         .unwrap_or_else(|| site_config.title.clone());
 
-    let description = build_social_description(input.subtitle, input.markdown_body);
+    let description = match input.description.map(str::trim) {
+        Some(description) if !description.is_empty() => description.to_string(),
+        _ => build_social_description(input.subtitle, input.markdown_body),
+    };
     let description = if description.is_empty() {
         site_config.description.clone()
     } else {
@@ -63,14 +217,22 @@ pub(crate) fn build_post_meta(
         .filter(|i| !i.trim().is_empty())
         .unwrap_or_else(|| DEFAULT_SOCIAL_IMAGE_PATH.to_string());
 
+    let url = page_url(page_path);
     PageMeta {
         title,
         description,
-        url: page_url(page_path),
+        canonical_url: input
+            .canonical_url
+            .map(ToString::to_string)
+            .unwrap_or_else(|| url.clone()),
+        url,
         image: absolute_url(&image_path),
         author: site_config.author.clone(),
         published_time: input.date.and_then(iso_published_time),
         role: input.role.map(ToString::to_string),
+        keywords: input.keywords.to_vec(),
+        page_type: PageType::Post,
+        og_type: None,
     }
 }
 
@@ -78,6 +240,48 @@ pub(crate) fn page_url(page_path: &str) -> String {
     format!("{}{}", site_url(), normalize_page_path(page_path))
 }
 
+/// Path for a post page with the slug percent-encoded, so non-ASCII slugs
+/// produce valid URLs. Axum decodes the segment again when routing.
+pub(crate) fn post_path(slug: &str) -> String {
+    encoded_path("/posts/", slug)
+}
+
+/// Path for a category index page, encoded like `post_path`.
+pub(crate) fn category_path(category_slug: &str) -> String {
+    encoded_path("/categories/", category_slug)
+}
+
+pub(crate) fn tag_path(tag_slug: &str) -> String {
+    encoded_path("/tags/", tag_slug)
+}
+
+pub(crate) fn encoded_path(prefix: &str, segment: &str) -> String {
+    let mut path = String::with_capacity(prefix.len() + segment.len());
+    path.push_str(prefix);
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            path.push(byte as char);
+        } else {
+            path.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    path
+}
+
+/// Lowercases `text` and collapses every run of characters other than
+/// letters and digits into a single `-`, trimmed at both ends.
+pub(crate) fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
 fn normalize_page_path(page_path: &str) -> String {
     if page_path.is_empty() {
         "/".to_string()
@@ -109,12 +313,28 @@ fn iso_published_time(date: &str) -> Option<String> {
     None
 }
 
-/// Generates a social-card description (≤160 chars):
+/// Generates a social-card description (≤ `EXCERPT_LENGTH` chars, 160 by
+/// default):
 /// - begins with subtitle, ensuring it ends with '.'
 /// - appends normalised body text
-/// - if combined text exceeds 160 chars, truncates at the last word
-///   boundary before char 157 and appends "..."
+/// - if combined text exceeds the limit, truncates at the last word
+///   boundary that leaves room for "..." and appends it
 pub(crate) fn build_social_description(subtitle: Option<&str>, markdown_body: &str) -> String {
+    build_social_description_with_length(subtitle, markdown_body, excerpt_length())
+}
+
+/// The post's `description`, else a summary of its subtitle and body.
+pub(crate) fn post_excerpt(post: &Post) -> String {
+    post.description
+        .clone()
+        .unwrap_or_else(|| build_social_description(post.subtitle.as_deref(), &post.markdown_body))
+}
+
+fn build_social_description_with_length(
+    subtitle: Option<&str>,
+    markdown_body: &str,
+    max_chars: usize,
+) -> String {
     let prefix = match subtitle {
         Some(s) if !s.trim().is_empty() => {
             let s = s.trim();
@@ -137,14 +357,14 @@ pub(crate) fn build_social_description(subtitle: Option<&str>, markdown_body: &s
         format!("{} {}", prefix, body_text)
     };
 
-    if combined.chars().count() <= 160 {
+    if combined.chars().count() <= max_chars {
         return combined;
     }
 
-    // Find the byte offset of char 157 (leaves room for "...")
+    // Find the byte offset that leaves room for "..."
     let cut = combined
         .char_indices()
-        .nth(157)
+        .nth(max_chars - 3)
         .map(|(i, _)| i)
         .unwrap_or(combined.len());
 
@@ -153,6 +373,49 @@ pub(crate) fn build_social_description(subtitle: Option<&str>, markdown_body: &s
     format!("{}...", combined[..last_space].trim_end())
 }
 
+fn excerpt_length() -> usize {
+    parse_excerpt_length(std::env::var("EXCERPT_LENGTH").ok().as_deref())
+}
+
+/// Unset or unparsable values use the default; tiny values clamp up so a
+/// description always keeps a few words.
+fn parse_excerpt_length(raw: Option<&str>) -> usize {
+    raw.and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_EXCERPT_LENGTH)
+        .max(MIN_EXCERPT_LENGTH)
+}
+
+fn reading_wpm() -> usize {
+    parse_reading_wpm(std::env::var("READING_WPM").ok().as_deref())
+}
+
+/// Unset or unparsable values use the default; tiny values clamp up so one
+/// dense post cannot claim hours of reading.
+fn parse_reading_wpm(raw: Option<&str>) -> usize {
+    raw.and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_READING_WPM)
+        .max(MIN_READING_WPM)
+}
+
+/// Whole minutes needed to read the prose of a post (headings and code
+/// blocks excluded) at `READING_WPM`, rounded up and never less than one.
+pub(crate) fn reading_time_minutes(markdown_body: &str) -> usize {
+    reading_time_minutes_at(markdown_body, reading_wpm())
+}
+
+fn reading_time_minutes_at(markdown_body: &str, words_per_minute: usize) -> usize {
+    let words = normalize_body_for_description(markdown_body)
+        .split_whitespace()
+        .count();
+    words.div_ceil(words_per_minute).max(1)
+}
+
+/// The post's prose as a single line of plain text, without headings,
+/// code blocks or images.
+pub(crate) fn plain_text(markdown_body: &str) -> String {
+    normalize_body_for_description(markdown_body)
+}
+
 /// Strips markdown block-level syntax, flattens lines to a single
 /// normalised string suitable for sentence extraction.
 fn normalize_body_for_description(markdown: &str) -> String {
@@ -253,6 +516,24 @@ fn append_normalized_fragment(out: &mut String, fragment: &str) {
     }
 }
 
+pub(crate) fn render_theme_color_meta(theme_color: Option<&ThemeColor>) -> String {
+    match theme_color {
+        None => String::new(),
+        Some(ThemeColor::Single(color)) => {
+            format!(
+                "<meta name=\"theme-color\" content=\"{}\" />",
+                escape_html(color)
+            )
+        }
+        Some(ThemeColor::Schemes { light, dark }) => format!(
+            "<meta name=\"theme-color\" media=\"(prefers-color-scheme: light)\" content=\"{}\" />\
+             <meta name=\"theme-color\" media=\"(prefers-color-scheme: dark)\" content=\"{}\" />",
+            escape_html(light),
+            escape_html(dark)
+        ),
+    }
+}
+
 pub(crate) fn escape_html(input: &str) -> String {
     htmlescape::encode_minimal(input)
 }
@@ -274,7 +555,7 @@ fn site_url() -> String {
 }
 
 fn development_site_url() -> Option<String> {
-    if std::env::var("RUST_ENV").ok().as_deref() != Some("development") {
+    if !Environment::from_env().is_development() {
         return None;
     }
     let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
@@ -306,10 +587,12 @@ fn absolute_url(path_or_url: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::{
-        absolute_url, build_post_meta, build_social_description, escape_html, iso_published_time,
-        page_url, site_url, PostMetaInput,
+        absolute_url, build_post_meta, build_social_description,
+        build_social_description_with_length, escape_html, iso_published_time, page_url,
+        parse_excerpt_length, parse_reading_wpm, post_path, reading_time_minutes_at,
+        render_theme_color_meta, site_url, PostMetaInput,
     };
-    use crate::models::{FrontMatter, SiteConfig};
+    use crate::models::{FrontMatter, SiteConfig, ThemeColor};
     use crate::test_support::TestEnvGuard;
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -322,6 +605,18 @@ mod tests {
             image: image.map(ToString::to_string),
             role: None,
             subtitle: None,
+            tags: Vec::new(),
+            expires: None,
+            comments: None,
+            keywords: None,
+            canonical_source: None,
+            updated: None,
+            toc: false,
+            category: None,
+            show_banner: None,
+            og_type: None,
+            includes: Vec::new(),
+            draft: false,
         }
     }
 
@@ -331,6 +626,7 @@ mod tests {
             author: "Configured Author".to_string(),
             description: "Configured description.".to_string(),
             og_site_name: "Configured OG Name".to_string(),
+            ..SiteConfig::default()
         }
     }
 
@@ -403,6 +699,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn social_description_respects_custom_length_on_word_boundary() {
+        let body = "alpha bravo charlie delta echo foxtrot golf hotel india juliet kilo lima";
+        let desc = build_social_description_with_length(None, body, 40);
+        assert!(
+            desc.chars().count() <= 40,
+            "length was {}",
+            desc.chars().count()
+        );
+        assert!(desc.ends_with("..."));
+        let kept = desc.trim_end_matches("...");
+        assert!(body.starts_with(kept));
+        assert_eq!(body.as_bytes()[kept.len()], b' ', "cut mid-word: {desc:?}");
+    }
+
+    #[test]
+    fn excerpt_length_defaults_and_clamps() {
+        assert_eq!(parse_excerpt_length(None), 160);
+        assert_eq!(parse_excerpt_length(Some("not a number")), 160);
+        assert_eq!(parse_excerpt_length(Some("220")), 220);
+        assert_eq!(parse_excerpt_length(Some("5")), 40);
+    }
+
     #[test]
     fn social_description_includes_trailing_fragment() {
         // Body ends mid-sentence — truncation still includes it (up to char limit)
@@ -470,6 +789,40 @@ mod tests {
         assert_eq!(desc, "First block. Second block.");
     }
 
+    #[test]
+    fn post_path_percent_encodes_non_ascii_slugs() {
+        assert_eq!(post_path("first-post"), "/posts/first-post");
+        assert_eq!(post_path("café-crème"), "/posts/caf%C3%A9-cr%C3%A8me");
+    }
+
+    #[test]
+    fn reading_time_rounds_up_whole_minutes() {
+        assert_eq!(reading_time_minutes_at("", 200), 1);
+        assert_eq!(reading_time_minutes_at(&"word ".repeat(200), 200), 1);
+        assert_eq!(reading_time_minutes_at(&"word ".repeat(201), 200), 2);
+        let with_code = format!(
+            "{}\n\n```\n{}\n```",
+            "word ".repeat(10),
+            "code ".repeat(500)
+        );
+        assert_eq!(reading_time_minutes_at(&with_code, 200), 1);
+    }
+
+    #[test]
+    fn lower_reading_wpm_gives_longer_estimates() {
+        let text = "word ".repeat(600);
+        assert_eq!(reading_time_minutes_at(&text, 200), 3);
+        assert_eq!(reading_time_minutes_at(&text, 120), 5);
+    }
+
+    #[test]
+    fn reading_wpm_defaults_and_clamps() {
+        assert_eq!(parse_reading_wpm(None), 200);
+        assert_eq!(parse_reading_wpm(Some("fast")), 200);
+        assert_eq!(parse_reading_wpm(Some("150")), 150);
+        assert_eq!(parse_reading_wpm(Some("0")), 50);
+    }
+
     // ── build_post_meta ───────────────────────────────────────────────────────
 
     #[test]
@@ -482,9 +835,12 @@ mod tests {
                 title: Some(&fm.title),
                 date: Some(&fm.date),
                 subtitle: fm.subtitle.as_deref(),
+                description: None,
                 role: fm.role.as_deref(),
                 image: fm.image.as_deref(),
                 markdown_body: "Body sentence.",
+                keywords: &[],
+                canonical_url: None,
             },
         );
         assert_eq!(meta.title, "Test Post");
@@ -503,15 +859,38 @@ mod tests {
                 title: Some("Test Post"),
                 date: Some("2026-03-04"),
                 subtitle: Some("Punchy subtitle"),
+                description: None,
                 role: None,
                 image: None,
                 markdown_body: "First sentence. Second sentence.",
+                keywords: &[],
+                canonical_url: None,
             },
         );
         assert!(meta.description.starts_with("Punchy subtitle."));
         assert!(meta.description.contains("First sentence."));
     }
 
+    #[test]
+    fn build_post_meta_prefers_the_front_matter_description() {
+        let meta = build_post_meta(
+            "/posts/test-post",
+            &test_site_config(),
+            PostMetaInput {
+                title: Some("Test Post"),
+                date: Some("2026-03-04"),
+                subtitle: Some("Punchy subtitle"),
+                description: Some(" Written for search results. "),
+                role: None,
+                image: None,
+                markdown_body: "First sentence. Second sentence.",
+                keywords: &[],
+                canonical_url: None,
+            },
+        );
+        assert_eq!(meta.description, "Written for search results.");
+    }
+
     #[test]
     fn build_post_meta_falls_back_to_body_sentences_when_no_subtitle() {
         let fm = front_matter_with(None);
@@ -523,9 +902,12 @@ mod tests {
                 title: Some(&fm.title),
                 date: Some(&fm.date),
                 subtitle: fm.subtitle.as_deref(),
+                description: None,
                 role: fm.role.as_deref(),
                 image: fm.image.as_deref(),
                 markdown_body: markdown,
+                keywords: &[],
+                canonical_url: None,
             },
         );
         assert_eq!(meta.description, "First line. Second line.");
@@ -543,9 +925,12 @@ mod tests {
                 title: Some(&fm.title),
                 date: Some(&fm.date),
                 subtitle: fm.subtitle.as_deref(),
+                description: None,
                 role: fm.role.as_deref(),
                 image: fm.image.as_deref(),
                 markdown_body: "body",
+                keywords: &[],
+                canonical_url: None,
             },
         );
         assert_eq!(meta.role.as_deref(), Some("mechanism"));
@@ -560,9 +945,12 @@ mod tests {
                 title: Some("Home"),
                 date: Some("2026-03-24"),
                 subtitle: Some("Home subtitle"),
+                description: None,
                 role: None,
                 image: Some("/static/home-card.png"),
                 markdown_body: "Home body.",
+                keywords: &[],
+                canonical_url: None,
             },
         );
         assert_eq!(meta.title, "Home");
@@ -605,6 +993,18 @@ mod tests {
         assert_eq!(site_url(), "http://127.0.0.1:18080");
     }
 
+    #[test]
+    fn site_url_falls_back_to_localhost_for_development_aliases() {
+        let _guard = TestEnvGuard::set([
+            ("DEVLOOP_STATE", None),
+            ("SITE_URL", None),
+            ("RUST_ENV", Some(" Dev ")),
+            ("PORT", Some("18081")),
+        ]);
+
+        assert_eq!(site_url(), "http://127.0.0.1:18081");
+    }
+
     #[test]
     fn iso_published_time_expands_date_only_values() {
         assert_eq!(
@@ -667,6 +1067,49 @@ mod tests {
         assert_eq!(page_url(""), "http://127.0.0.1:18080/");
     }
 
+    #[test]
+    fn theme_color_meta_uses_configured_single_value() {
+        let meta = render_theme_color_meta(Some(&ThemeColor::Single("#073642".to_string())));
+        assert_eq!(meta, "<meta name=\"theme-color\" content=\"#073642\" />");
+    }
+
+    #[test]
+    fn theme_color_meta_emits_light_and_dark_media_queries() {
+        let meta = render_theme_color_meta(Some(&ThemeColor::Schemes {
+            light: "#fdf6e3".to_string(),
+            dark: "#002b36".to_string(),
+        }));
+        assert!(meta.contains(
+            "<meta name=\"theme-color\" media=\"(prefers-color-scheme: light)\" content=\"#fdf6e3\" />"
+        ));
+        assert!(meta.contains(
+            "<meta name=\"theme-color\" media=\"(prefers-color-scheme: dark)\" content=\"#002b36\" />"
+        ));
+    }
+
+    #[test]
+    fn theme_color_meta_is_empty_when_unconfigured() {
+        assert_eq!(render_theme_color_meta(None), "");
+    }
+
+    #[test]
+    fn site_config_parses_theme_color_variants() {
+        let single: SiteConfig = toml::from_str(
+            "title = \"t\"\nauthor = \"a\"\ndescription = \"d\"\nog_site_name = \"o\"\ntheme_color = \"#073642\"",
+        )
+        .expect("parse single");
+        assert_eq!(
+            single.theme_color,
+            Some(ThemeColor::Single("#073642".to_string()))
+        );
+
+        let pair: SiteConfig = toml::from_str(
+            "title = \"t\"\nauthor = \"a\"\ndescription = \"d\"\nog_site_name = \"o\"\n[theme_color]\nlight = \"#fdf6e3\"\ndark = \"#002b36\"",
+        )
+        .expect("parse pair");
+        assert!(matches!(pair.theme_color, Some(ThemeColor::Schemes { .. })));
+    }
+
     #[test]
     fn escape_html_escapes_meta_sensitive_characters() {
         let escaped = escape_html(r#""A&B" <tag> 'q'"#);
@@ -0,0 +1,226 @@
+use chrono::{Datelike, NaiveDate};
+use tracing::warn;
+
+use crate::models::Post;
+use crate::page_meta::{encoded_path, post_path};
+use crate::BUILT_IN_ROUTES;
+
+const DEFAULT_PATTERN: &str = "/posts/{slug}";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Year,
+    Month,
+    Day,
+    Slug,
+}
+
+/// Where post pages live, e.g. `/posts/{slug}` or `/{year}/{month}/{slug}`.
+/// Date parts come from the post's `date`; posts without a parseable date
+/// stay at `/posts/{slug}`, which is always routed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PostUrlPattern {
+    segments: Vec<Segment>,
+}
+
+impl Default for PostUrlPattern {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PATTERN).expect("default post URL pattern parses")
+    }
+}
+
+impl PostUrlPattern {
+    /// Reads `POST_URL_PATTERN`, falling back to `/posts/{slug}` with a
+    /// warning when it is malformed or collides with a built-in route.
+    pub fn from_env() -> Self {
+        let Ok(raw) = std::env::var("POST_URL_PATTERN") else {
+            return Self::default();
+        };
+        Self::parse(&raw).unwrap_or_else(|| {
+            warn!(pattern = %raw, "ignoring malformed POST_URL_PATTERN");
+            Self::default()
+        })
+    }
+
+    /// Each segment is literal text or exactly one of `{year}`, `{month}`,
+    /// `{day}` and `{slug}`; `{slug}` must appear once. Patterns that could
+    /// match a built-in route's path are refused: axum either panics on the
+    /// overlap or quietly sends some posts to the other handler.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        if !raw.starts_with('/') {
+            return None;
+        }
+        let segments = raw
+            .trim_matches('/')
+            .split('/')
+            .map(|segment| match segment {
+                "{year}" => Some(Segment::Year),
+                "{month}" => Some(Segment::Month),
+                "{day}" => Some(Segment::Day),
+                "{slug}" => Some(Segment::Slug),
+                literal if !literal.is_empty() && !literal.contains(['{', '}', '*', '?', '#']) => {
+                    Some(Segment::Literal(literal.to_string()))
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let slugs = segments.iter().filter(|s| **s == Segment::Slug).count();
+        let pattern = Self { segments };
+        let collides = pattern.route() != DEFAULT_PATTERN
+            && BUILT_IN_ROUTES.iter().any(|route| pattern.overlaps(route));
+        (slugs == 1 && !collides).then_some(pattern)
+    }
+
+    /// Whether some path matches both this pattern and `route`. Date
+    /// segments only ever hold digits, so they never meet a word.
+    fn overlaps(&self, route: &str) -> bool {
+        let route: Vec<&str> = route.trim_start_matches('/').split('/').collect();
+        let nested = route.last().is_some_and(|last| last.starts_with("{*"));
+        let fixed = if nested {
+            &route[..route.len() - 1]
+        } else {
+            &route[..]
+        };
+        if self.segments.len() < fixed.len() || (!nested && self.segments.len() != fixed.len()) {
+            return false;
+        }
+        self.segments.iter().zip(fixed).all(|(segment, part)| {
+            if part.starts_with('{') {
+                return true;
+            }
+            match segment {
+                Segment::Literal(literal) => literal == part,
+                Segment::Slug => true,
+                Segment::Year | Segment::Month | Segment::Day => {
+                    part.chars().all(|c| c.is_ascii_digit())
+                }
+            }
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The axum route for this pattern; captures are named after the
+    /// placeholders.
+    pub fn route(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(literal) => format!("/{literal}"),
+                Segment::Year => "/{year}".to_string(),
+                Segment::Month => "/{month}".to_string(),
+                Segment::Day => "/{day}".to_string(),
+                Segment::Slug => "/{slug}".to_string(),
+            })
+            .collect()
+    }
+
+    /// Link to a post's page, with the slug percent-encoded like
+    /// `post_path`.
+    pub fn path(&self, post: &Post) -> String {
+        let needs_date = self
+            .segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Year | Segment::Month | Segment::Day));
+        let date = post_date(post);
+        if needs_date && date.is_none() {
+            return post_path(&post.slug);
+        }
+
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(literal) => format!("/{literal}"),
+                Segment::Year => date
+                    .map(|d| format!("/{:04}", d.year()))
+                    .unwrap_or_default(),
+                Segment::Month => date
+                    .map(|d| format!("/{:02}", d.month()))
+                    .unwrap_or_default(),
+                Segment::Day => date.map(|d| format!("/{:02}", d.day())).unwrap_or_default(),
+                Segment::Slug => encoded_path("/", &post.slug),
+            })
+            .collect()
+    }
+}
+
+fn post_date(post: &Post) -> Option<NaiveDate> {
+    let date = post.date.trim();
+    NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostUrlPattern;
+    use crate::models::Post;
+
+    fn post(slug: &str, date: &str) -> Post {
+        Post {
+            slug: slug.to_string(),
+            date: date.to_string(),
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn default_pattern_keeps_posts_prefix() {
+        let pattern = PostUrlPattern::default();
+        assert_eq!(pattern.route(), "/posts/{slug}");
+        assert_eq!(pattern.path(&post("hello", "2026-02-03")), "/posts/hello");
+    }
+
+    #[test]
+    fn date_parts_come_from_the_post_date() {
+        let pattern = PostUrlPattern::parse("/{year}/{month}/{slug}").expect("pattern");
+        assert_eq!(pattern.route(), "/{year}/{month}/{slug}");
+        assert_eq!(pattern.path(&post("hello", "2026-02-03")), "/2026/02/hello");
+        assert_eq!(
+            pattern.path(&post("café", "2026-02-03T09:00:00Z")),
+            "/2026/02/caf%C3%A9"
+        );
+        assert_eq!(pattern.path(&post("undated", "soon")), "/posts/undated");
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        for raw in [
+            "posts/{slug}",
+            "/{year}/{month}",
+            "/{slug}/{slug}",
+            "/{yr}/{slug}",
+            "/posts//{slug}",
+        ] {
+            assert_eq!(PostUrlPattern::parse(raw), None, "{raw}");
+        }
+    }
+
+    #[test]
+    fn rejects_patterns_that_overlap_built_in_routes() {
+        for raw in [
+            "/tags/{slug}",
+            "/categories/{slug}",
+            "/api/posts/{slug}",
+            "/static/{slug}",
+            "/static/{year}/{slug}",
+            "/posts/{year}/{slug}",
+            "/{slug}",
+            "/{slug}/print",
+        ] {
+            assert_eq!(PostUrlPattern::parse(raw), None, "{raw}");
+        }
+        for raw in [
+            "/posts/{slug}",
+            "/{year}/{slug}",
+            "/{year}/{month}/{slug}",
+            "/posts/{year}/{month}/{slug}",
+            "/blog/{slug}",
+            "/tags/{year}/{slug}",
+        ] {
+            assert!(PostUrlPattern::parse(raw).is_some(), "{raw}");
+        }
+    }
+}
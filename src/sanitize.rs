@@ -0,0 +1,572 @@
+use std::collections::{HashMap, HashSet};
+
+use scraper::{node::Element, ElementRef, Html, Node};
+
+use crate::page_meta::escape_html;
+
+/// Tags kept when sanitizing raw HTML in posts, before `MD_ALLOWED_TAGS`
+/// adds to them.
+const DEFAULT_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+const DEFAULT_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href", "title"]),
+    ("img", &["src", "alt", "title", "width", "height"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan"]),
+];
+
+/// Inline SVG diagrams. Nothing here can load or run anything: no
+/// `script`, `foreignObject`, `use`, `image` or `style`, and no `href`s.
+const SVG_TAGS: &[&str] = &[
+    "svg",
+    "g",
+    "defs",
+    "title",
+    "desc",
+    "path",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "rect",
+    "text",
+    "tspan",
+    "marker",
+    "lineargradient",
+    "radialgradient",
+    "stop",
+];
+
+/// Prepended to `id`s inside inline SVG and to the `#fragment`s that refer
+/// to them, so a diagram cannot shadow an `id` the page relies on.
+const SVG_ID_PREFIX: &str = "svg-";
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// Presentation attributes kept on every SVG tag. Names are compared in
+/// lowercase, since the HTML parser keeps SVG's camelCase.
+const SVG_PRESENTATION_ATTRIBUTES: &[&str] = &[
+    "id",
+    "class",
+    "transform",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-dasharray",
+    "opacity",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "text-anchor",
+    "dominant-baseline",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+];
+
+const SVG_ATTRIBUTES: &[(&str, &[&str])] = &[
+    (
+        "svg",
+        &[
+            "viewbox",
+            "width",
+            "height",
+            "role",
+            "aria-label",
+            "preserveaspectratio",
+        ],
+    ),
+    ("path", &["d", "pathlength"]),
+    ("circle", &["cx", "cy", "r"]),
+    ("ellipse", &["cx", "cy", "rx", "ry"]),
+    ("line", &["x1", "y1", "x2", "y2"]),
+    ("polyline", &["points"]),
+    ("polygon", &["points"]),
+    ("rect", &["x", "y", "width", "height", "rx", "ry"]),
+    ("text", &["x", "y", "dx", "dy"]),
+    ("tspan", &["x", "y", "dx", "dy"]),
+    (
+        "marker",
+        &[
+            "viewbox",
+            "refx",
+            "refy",
+            "markerwidth",
+            "markerheight",
+            "orient",
+        ],
+    ),
+    ("lineargradient", &["x1", "y1", "x2", "y2", "gradientunits"]),
+    (
+        "radialgradient",
+        &["cx", "cy", "r", "fx", "fy", "gradientunits"],
+    ),
+    ("stop", &["offset", "stop-color", "stop-opacity"]),
+];
+
+/// Hosts an allowed `<iframe>` may load from over HTTPS, unless
+/// `MD_IFRAME_HOSTS` names others.
+const DEFAULT_IFRAME_HOSTS: &[&str] = &[
+    "www.youtube.com",
+    "www.youtube-nocookie.com",
+    "player.vimeo.com",
+];
+
+/// `data:` URLs that are kept: raster images, which cannot run script.
+const SAFE_DATA_TYPES: &[&str] = &["image/png", "image/gif", "image/jpeg", "image/webp"];
+
+/// Disallowed elements whose content is dropped along with them, rather
+/// than kept as text.
+const DROP_CONTENT: &[&str] = &["script", "style", "iframe", "object", "embed", "template"];
+
+const VOID_TAGS: &[&str] = &["br", "hr", "img", "wbr", "source", "track"];
+
+/// Which raw HTML tags and attributes survive in post markdown when
+/// `MD_SANITIZE` is on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SanitizeOptions {
+    tags: HashSet<String>,
+    attributes: HashMap<String, HashSet<String>>,
+    iframe_hosts: HashSet<String>,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        let mut options = Self {
+            tags: DEFAULT_TAGS.iter().map(ToString::to_string).collect(),
+            attributes: DEFAULT_ATTRIBUTES
+                .iter()
+                .map(|(tag, attributes)| {
+                    (
+                        tag.to_string(),
+                        attributes.iter().map(ToString::to_string).collect(),
+                    )
+                })
+                .collect(),
+            iframe_hosts: DEFAULT_IFRAME_HOSTS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        };
+        for tag in SVG_TAGS {
+            options.tags.insert(tag.to_string());
+            options
+                .attributes
+                .entry(tag.to_string())
+                .or_default()
+                .extend(SVG_PRESENTATION_ATTRIBUTES.iter().map(ToString::to_string));
+        }
+        for (tag, attributes) in SVG_ATTRIBUTES {
+            options
+                .attributes
+                .entry(tag.to_string())
+                .or_default()
+                .extend(attributes.iter().map(ToString::to_string));
+        }
+        options
+    }
+}
+
+impl SanitizeOptions {
+    /// The defaults plus `MD_ALLOWED_TAGS`, with iframes limited to
+    /// `MD_IFRAME_HOSTS` when it is set.
+    pub fn from_env() -> Self {
+        Self::with_allowed(std::env::var("MD_ALLOWED_TAGS").ok().as_deref())
+            .with_iframe_hosts(std::env::var("MD_IFRAME_HOSTS").ok().as_deref())
+    }
+
+    /// Replaces the iframe host allowlist with comma-separated host names;
+    /// `None` keeps the defaults.
+    pub fn with_iframe_hosts(mut self, raw: Option<&str>) -> Self {
+        if let Some(raw) = raw {
+            self.iframe_hosts = raw
+                .split(',')
+                .map(|host| host.trim().to_ascii_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect();
+        }
+        self
+    }
+
+    /// Extends the defaults with comma-separated `tag` or
+    /// `tag:attribute:attribute` entries, e.g. `iframe:src:allowfullscreen`.
+    pub fn with_allowed(raw: Option<&str>) -> Self {
+        let mut options = Self::default();
+        for entry in raw.unwrap_or_default().split(',') {
+            let mut parts = entry
+                .split(':')
+                .map(|part| part.trim().to_ascii_lowercase())
+                .filter(|part| !part.is_empty());
+            let Some(tag) = parts.next() else {
+                continue;
+            };
+            options
+                .attributes
+                .entry(tag.clone())
+                .or_default()
+                .extend(parts);
+            options.tags.insert(tag);
+        }
+        options
+    }
+
+    fn allows_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag.to_ascii_lowercase())
+    }
+
+    fn allows_attribute(&self, tag: &str, attribute: &str) -> bool {
+        self.attributes
+            .get(&tag.to_ascii_lowercase())
+            .is_some_and(|attributes| attributes.contains(&attribute.to_ascii_lowercase()))
+    }
+
+    /// An allowed iframe keeps its `src` only for an HTTPS URL on one of
+    /// the allowlisted hosts.
+    fn allows_iframe_src(&self, src: &str) -> bool {
+        let src = src.trim().to_ascii_lowercase();
+        let Some(rest) = src.strip_prefix("https://") else {
+            return false;
+        };
+        let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        self.iframe_hosts.contains(host)
+    }
+
+    fn allows_value(&self, tag: &str, attribute: &str, value: &str) -> bool {
+        if tag.eq_ignore_ascii_case("iframe") && attribute.eq_ignore_ascii_case("src") {
+            return self.allows_iframe_src(value);
+        }
+        !is_unsafe_url(value)
+    }
+
+    /// Rebuilds an HTML block keeping only allowed tags and attributes.
+    /// Disallowed tags are unwrapped, except the `DROP_CONTENT` ones.
+    pub fn clean_block(&self, html: &str) -> String {
+        let fragment = Html::parse_fragment(html);
+        let mut out = String::new();
+        self.push_children(&mut out, fragment.root_element());
+        out
+    }
+
+    /// One inline tag such as `<kbd>` or `</kbd>`: the cleaned tag, or
+    /// `None` when it is not allowed. Inline HTML arrives one tag at a
+    /// time, so opening and closing tags are judged separately.
+    pub fn clean_inline_tag(&self, tag: &str) -> Option<String> {
+        let trimmed = tag.trim();
+        if let Some(name) = trimmed
+            .strip_prefix("</")
+            .and_then(|rest| rest.strip_suffix('>'))
+        {
+            let name = name.trim().to_ascii_lowercase();
+            return self.allows_tag(&name).then(|| format!("</{name}>"));
+        }
+
+        let fragment = Html::parse_fragment(trimmed);
+        let element = fragment
+            .root_element()
+            .children()
+            .find_map(|child| match child.value() {
+                Node::Element(element) => Some(element),
+                _ => None,
+            })?;
+        let start_tag = self
+            .allows_tag(element.name())
+            .then(|| self.start_tag(element))?;
+        // Inside `<svg>` a self-closing `<circle />` must stay closed, or
+        // the shapes after it would nest inside it.
+        Some(match start_tag.strip_suffix('>') {
+            Some(open) if trimmed.ends_with("/>") && !open.ends_with(" /") => format!("{open} />"),
+            _ => start_tag,
+        })
+    }
+
+    /// For a disallowed inline tag whose content must go too (`<script>`
+    /// and the like), the tag name to skip ahead to the closing tag of.
+    pub fn drops_inline_content(&self, tag: &str) -> Option<String> {
+        let name: String = tag
+            .trim()
+            .trim_start_matches('<')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        (DROP_CONTENT.contains(&name.as_str()) && !self.allows_tag(&name)).then_some(name)
+    }
+
+    fn push_children(&self, out: &mut String, parent: ElementRef<'_>) {
+        for child in parent.children() {
+            match child.value() {
+                Node::Text(text) => out.push_str(&escape_html(text)),
+                Node::Element(element) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.push_element(out, element, child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn push_element(&self, out: &mut String, element: &Element, node: ElementRef<'_>) {
+        let name = element.name();
+        if self.allows_tag(name) {
+            out.push_str(&self.start_tag(element));
+            self.push_children(out, node);
+            if !VOID_TAGS.contains(&name) {
+                out.push_str(&format!("</{name}>"));
+            }
+        } else if !DROP_CONTENT.contains(&name) {
+            self.push_children(out, node);
+        }
+    }
+
+    fn start_tag(&self, element: &Element) -> String {
+        let name = element.name();
+        // Inline tags are parsed on their own, outside any `<svg>`, so the
+        // tag list stands in for the namespace there.
+        let is_svg = &*element.name.ns == SVG_NAMESPACE
+            || SVG_TAGS.contains(&name.to_ascii_lowercase().as_str());
+        let attributes: String = element
+            .attrs()
+            .filter(|(attribute, value)| {
+                self.allows_attribute(name, attribute) && self.allows_value(name, attribute, value)
+            })
+            .filter_map(|(attribute, value)| {
+                let value = if is_svg {
+                    svg_value(attribute, value)?
+                } else {
+                    value.to_string()
+                };
+                Some(format!(" {attribute}=\"{}\"", escape_html(&value)))
+            })
+            .collect();
+        if VOID_TAGS.contains(&name) {
+            format!("<{name}{attributes} />")
+        } else {
+            format!("<{name}{attributes}>")
+        }
+    }
+}
+
+/// An attribute value on an SVG tag as written out: `id`s and the local
+/// references to them get `SVG_ID_PREFIX`, and any other `url(...)` or
+/// `href` is refused, since it could load something from elsewhere.
+fn svg_value(attribute: &str, value: &str) -> Option<String> {
+    let attribute = attribute.to_ascii_lowercase();
+    if attribute == "id" {
+        return Some(format!("{SVG_ID_PREFIX}{}", value.trim()));
+    }
+    if attribute.ends_with("href") {
+        let fragment = value.trim().strip_prefix('#')?;
+        return is_fragment_name(fragment).then(|| format!("#{SVG_ID_PREFIX}{fragment}"));
+    }
+    if !value.to_ascii_lowercase().contains("url(") {
+        return Some(value.to_string());
+    }
+    let compact: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let fragment = compact
+        .get(..4)
+        .filter(|start| start.eq_ignore_ascii_case("url("))
+        .and_then(|_| compact[4..].strip_prefix('#'))
+        .and_then(|rest| rest.strip_suffix(')'))?;
+    is_fragment_name(fragment).then(|| format!("url(#{SVG_ID_PREFIX}{fragment})"))
+}
+
+fn is_fragment_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// `javascript:`, `vbscript:` and `data:` URLs other than raster images.
+/// Browsers ignore whitespace and control characters inside the scheme,
+/// so those are dropped before comparing.
+pub fn is_unsafe_url(value: &str) -> bool {
+    let compact: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if let Some(data) = compact.strip_prefix("data:") {
+        return !SAFE_DATA_TYPES.iter().any(|media_type| {
+            data.strip_prefix(media_type)
+                .is_some_and(|rest| rest.starts_with([';', ',']))
+        });
+    }
+    compact.starts_with("javascript:") || compact.starts_with("vbscript:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_unsafe_url, SanitizeOptions};
+
+    #[test]
+    fn keeps_allowed_blocks_and_strips_scripts() {
+        let options = SanitizeOptions::default();
+        assert_eq!(
+            options.clean_block(
+                "<details open><summary>More</summary><p onclick=\"x()\">Body</p><script>alert(1)</script></details>"
+            ),
+            "<details><summary>More</summary><p>Body</p></details>"
+        );
+        assert_eq!(
+            options.clean_block("<a href=\"javascript:alert(1)\" title=\"t\">x</a>"),
+            "<a title=\"t\">x</a>"
+        );
+    }
+
+    #[test]
+    fn configured_tags_and_attributes_are_added_to_defaults() {
+        let options =
+            SanitizeOptions::with_allowed(Some(" iframe:src:allowfullscreen , Details:open"));
+        assert_eq!(
+            options.clean_block(
+                "<iframe src=\"https://www.youtube.com/embed/x\" onload=\"x()\" allowfullscreen></iframe>"
+            ),
+            "<iframe allowfullscreen=\"\" src=\"https://www.youtube.com/embed/x\"></iframe>"
+        );
+        assert_eq!(
+            options.clean_block("<details open><summary>S</summary></details>"),
+            "<details open=\"\"><summary>S</summary></details>"
+        );
+    }
+
+    #[test]
+    fn inline_tags_are_judged_one_at_a_time() {
+        let options = SanitizeOptions::default();
+        assert_eq!(
+            options.clean_inline_tag("<kbd class=\"x\">").as_deref(),
+            Some("<kbd>")
+        );
+        assert_eq!(
+            options.clean_inline_tag("</kbd>").as_deref(),
+            Some("</kbd>")
+        );
+        assert_eq!(options.clean_inline_tag("<blink>"), None);
+        assert_eq!(
+            options.drops_inline_content("<script>").as_deref(),
+            Some("script")
+        );
+        assert_eq!(options.drops_inline_content("<kbd>"), None);
+    }
+
+    #[test]
+    fn data_urls_are_dropped_unless_raster_images() {
+        let options = SanitizeOptions::default();
+        assert_eq!(
+            options.clean_block(
+                "<a href=\"data:text/html;base64,PHNjcmlwdD4=\">x</a><img src=\"data:image/svg+xml,<svg/>\" alt=\"s\"><img src=\"data:image/png;base64,iVBO\" alt=\"p\">"
+            ),
+            "<a>x</a><img alt=\"s\" /><img alt=\"p\" src=\"data:image/png;base64,iVBO\" />"
+        );
+        assert!(is_unsafe_url(" Java\tScript:alert(1)"));
+        assert!(is_unsafe_url("DATA:text/html,x"));
+        assert!(is_unsafe_url("data:image/pngx,x"));
+        assert!(!is_unsafe_url("https://example.com/a.png"));
+    }
+
+    #[test]
+    fn iframes_only_load_from_allowlisted_https_hosts() {
+        let options = SanitizeOptions::with_allowed(Some("iframe:src"));
+        assert_eq!(
+            options.clean_block("<iframe src=\"https://player.vimeo.com/video/1\"></iframe>"),
+            "<iframe src=\"https://player.vimeo.com/video/1\"></iframe>"
+        );
+        for src in [
+            "http://www.youtube.com/embed/x",
+            "https://evil.example/embed/x",
+            "https://www.youtube.com.evil.example/x",
+            "https://evil.example?www.youtube.com",
+            "javascript:alert(1)",
+        ] {
+            assert_eq!(
+                options.clean_block(&format!("<iframe src=\"{src}\"></iframe>")),
+                "<iframe></iframe>",
+                "{src}"
+            );
+        }
+
+        let options = options.with_iframe_hosts(Some(" Embed.Example ,"));
+        assert_eq!(
+            options.clean_block("<iframe src=\"https://embed.example/x\"></iframe>"),
+            "<iframe src=\"https://embed.example/x\"></iframe>"
+        );
+        assert_eq!(
+            options.clean_block("<iframe src=\"https://www.youtube.com/embed/x\"></iframe>"),
+            "<iframe></iframe>"
+        );
+    }
+
+    #[test]
+    fn svg_ids_are_prefixed_and_only_local_urls_survive() {
+        let options = SanitizeOptions::with_allowed(Some("use:href"));
+        assert_eq!(
+            options.clean_block(
+                "<svg><linearGradient id=\"fade\"></linearGradient><rect fill=\"url( #fade )\" stroke=\"url(https://evil.example/x.svg#a)\" width=\"2\" /><path marker-end=\"URL(#arrow)\" fill=\"url(#a) url(x)\" /><use href=\"#fade\"></use><use href=\"https://evil.example/x.svg#a\"></use></svg>"
+            ),
+            "<svg><linearGradient id=\"svg-fade\"></linearGradient><rect fill=\"url(#svg-fade)\" width=\"2\"></rect><path marker-end=\"url(#svg-arrow)\"></path><use href=\"#svg-fade\"></use><use></use></svg>"
+        );
+        assert_eq!(
+            options
+                .clean_inline_tag("<circle id=\"dot\" fill=\"url(//evil.example/a)\" />")
+                .as_deref(),
+            Some("<circle id=\"svg-dot\" />")
+        );
+    }
+}
@@ -1,11 +1,80 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 use tokio::sync::RwLock;
 
-use crate::models::{Post, SiteConfig};
+use crate::content_loader::GroupEntry;
+use crate::markdown::RenderOptions;
+use crate::models::{today, Post, SiteConfig};
+
+/// What production does with a post past its `expires` date.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExpiredPosts {
+    /// Answer `404` as if the post did not exist.
+    #[default]
+    NotFound,
+    /// Keep the page reachable with an expiry notice above the body.
+    Notice,
+}
+
+impl ExpiredPosts {
+    pub fn from_env() -> Self {
+        Self::parse(std::env::var("EXPIRED_POSTS").ok().as_deref())
+    }
+
+    pub fn parse(raw: Option<&str>) -> Self {
+        match raw.map(str::trim) {
+            Some("notice") => Self::Notice,
+            _ => Self::NotFound,
+        }
+    }
+}
+
+/// Deployment flavour read from `RUST_ENV`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Environment {
+    /// Hot reload, drafts and the debugging routes.
+    Development,
+    /// Production rendering with drafts visible, marked `noindex` and
+    /// without analytics.
+    Staging,
+    #[default]
+    Production,
+}
+
+impl Environment {
+    pub fn from_env() -> Self {
+        Self::parse(std::env::var("RUST_ENV").ok().as_deref())
+    }
+
+    /// Anything unrecognised, including an unset variable, is production.
+    pub fn parse(raw: Option<&str>) -> Self {
+        match raw
+            .map(|value| value.trim().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("development" | "dev" | "local" | "test") => Self::Development,
+            Some("staging" | "stage" | "preview") => Self::Staging,
+            _ => Self::Production,
+        }
+    }
+
+    pub fn is_development(self) -> bool {
+        self == Self::Development
+    }
+
+    pub fn shows_drafts(self) -> bool {
+        matches!(self, Self::Development | Self::Staging)
+    }
+}
 
 #[derive(Clone)]
 pub struct DevloopEventClient {
     pub browser_path_url: String,
+    /// Where `/__dev/reload` reports what changed; unset, nothing is sent.
+    pub reload_url: Option<String>,
     pub token: String,
     pub client: reqwest::Client,
 }
@@ -14,12 +83,56 @@ pub struct AppState {
     pub site_config: RwLock<SiteConfig>,
     pub banner_html: RwLock<String>,
     pub layout_html: RwLock<String>,
+    /// See `LoadedContent::print_layout_html`.
+    pub print_layout_html: RwLock<String>,
     pub home_post: RwLock<Post>,
     pub current_browser_path: RwLock<String>,
     pub devloop_event_client: Option<DevloopEventClient>,
     pub not_found_markdown: RwLock<String>, // supports {{slug}} placeholder
     pub posts: RwLock<Vec<Post>>,
-    pub is_development: bool,
+    pub environment: Environment,
+    pub render_options: RenderOptions,
+    /// Set once startup content has loaded; `/readyz` reports it while
+    /// `/healthz` only reports liveness.
+    pub ready: AtomicBool,
+    /// Origins (or bare hosts) allowed to call the `/__dev` channel.
+    pub dev_allowed_origins: Vec<String>,
+    pub expired_posts: ExpiredPosts,
+    /// See `LoadedContent::slug_index`.
+    pub slug_index: RwLock<HashMap<String, String>>,
+    /// See `LoadedContent::category_index`.
+    pub category_index: RwLock<HashMap<String, GroupEntry>>,
+    /// See `LoadedContent::tag_index`.
+    pub tag_index: RwLock<HashMap<String, GroupEntry>>,
+    /// Redirect mixed-case post URLs to the canonical slug instead of 404.
+    pub case_insensitive_slugs: bool,
+    /// See `LoadedContent::render_timings`; served at `/api/render-timings`.
+    pub render_timings: RwLock<HashMap<String, Duration>>,
+    /// See `LoadedContent::gone_slugs`.
+    pub gone_slugs: RwLock<HashSet<String>>,
+    /// Answer every content route with a `503` maintenance page; health
+    /// checks and static assets keep working.
+    pub maintenance: bool,
+}
+
+impl AppState {
+    pub fn is_development(&self) -> bool {
+        self.environment.is_development()
+    }
+
+    pub fn is_expired(&self, post: &Post) -> bool {
+        post.is_expired_on(today())
+    }
+
+    /// Expired posts drop out of listings in production; development keeps
+    /// them so they can still be edited and previewed.
+    pub fn is_listed(&self, post: &Post) -> bool {
+        self.is_development() || !self.is_expired(post)
+    }
+
+    pub fn is_reachable(&self, post: &Post) -> bool {
+        self.is_listed(post) || self.expired_posts == ExpiredPosts::Notice
+    }
 }
 
 #[derive(Clone)]
@@ -32,3 +145,33 @@ impl axum::extract::FromRef<RouterState> for Arc<AppState> {
         state.app_state.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Environment;
+
+    #[test]
+    fn classifies_rust_env_values() {
+        for raw in ["development", "dev", "local", "test", " Development "] {
+            assert_eq!(
+                Environment::parse(Some(raw)),
+                Environment::Development,
+                "{raw}"
+            );
+        }
+        for raw in ["staging", "stage", "preview"] {
+            assert_eq!(Environment::parse(Some(raw)), Environment::Staging, "{raw}");
+        }
+        for raw in [Some("production"), Some("prod"), Some(""), None] {
+            assert_eq!(Environment::parse(raw), Environment::Production, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn only_development_and_staging_show_drafts() {
+        assert!(Environment::Development.shows_drafts());
+        assert!(Environment::Staging.shows_drafts());
+        assert!(!Environment::Production.shows_drafts());
+        assert!(!Environment::Staging.is_development());
+    }
+}
@@ -1,9 +1,17 @@
 #[cfg(test)]
+use crate::content_loader::ContentRoots;
+#[cfg(test)]
 use std::collections::BTreeMap;
 #[cfg(test)]
 use std::ffi::{OsStr, OsString};
 #[cfg(test)]
-use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+#[cfg(test)]
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(test)]
 fn env_lock() -> &'static Mutex<()> {
@@ -73,3 +81,115 @@ fn remove_env_var(key: &str) {
         std::env::remove_var(key);
     }
 }
+
+/// A throwaway `content/` tree with the minimum files `load_content_from`
+/// requires. Removed again when dropped.
+#[cfg(test)]
+pub(crate) struct TestContentDir {
+    root: PathBuf,
+}
+
+#[cfg(test)]
+impl TestContentDir {
+    pub(crate) fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!(
+            "gcp-rust-blog-content-{unique}-{}",
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(root.join("posts")).expect("create content dir");
+
+        let dir = Self { root };
+        dir.write(
+            "site.toml",
+            "title = \"Test Blog\"\nauthor = \"Test Author\"\ndescription = \"Test description.\"\nog_site_name = \"Test Blog\"\n",
+        );
+        dir.write("banner.html", "<header>{{ site_title }}</header>");
+        dir.write(
+            "layout.html",
+            "<html><head><title>{{ page_title }}</title></head><body>{{ banner }}<main>{{ content }}</main><ul>{{ posts }}</ul></body></html>",
+        );
+        dir.write("404.md", "# Terra incognita");
+        dir.write(
+            "home.md",
+            "---\ntitle: \"Home\"\ndate: 2026-03-24\nslug: home\n---\n# Home",
+        );
+        dir
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn roots(&self) -> ContentRoots {
+        ContentRoots {
+            content_dir: self.root.clone(),
+            theme_dir: None,
+            static_dir: None,
+        }
+    }
+
+    pub(crate) fn write(&self, relative: &str, contents: &str) {
+        let path = self.root.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("create fixture parent");
+        }
+        std::fs::write(path, contents).expect("write fixture");
+    }
+
+    pub(crate) fn write_post(&self, file_name: &str, contents: &str) {
+        self.write(&format!("posts/{file_name}"), contents);
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestContentDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Log output captured from the current thread's tracing dispatcher.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl CapturedLogs {
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().expect("lock captured logs")).into_owned()
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .expect("lock captured logs")
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Routes tracing events on this thread into a buffer until the guard is
+/// dropped. `#[tokio::test]` runs on a single thread, so awaited work in
+/// the test body is captured too.
+#[cfg(test)]
+pub(crate) fn capture_logs() -> (CapturedLogs, tracing::subscriber::DefaultGuard) {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    (logs, tracing::subscriber::set_default(subscriber))
+}